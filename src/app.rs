@@ -1,25 +1,135 @@
 use eframe::egui;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use crate::data::*;
-use crate::data::analysis::*;
+use crate::data::checks::{CheckJob, CheckMessage, CheckRequest};
+use crate::data::loader::{InputFormat, LoadJob, LoadMessage, LoadOptions, LoadedDataset};
+use crate::data::locator::ArrayCandidate;
+use crate::data::columns::{BrowseColumn, DEFAULT_COLUMN_COUNT, sort_records};
+use crate::data::clustering::{Cluster, ClusterOptions, apply_merges, cluster_values};
+use crate::data::crosstab::{Crosstab, CrosstabSort};
+use crate::data::duplicates::{DuplicateCluster, DuplicateOptions};
+use crate::data::export::{ExportJob, ExportMessage, ExportOptions};
+use crate::data::filters::{FilterKind, FilterSet, RecordFilter};
+use crate::data::index::SearchIndex;
+use crate::data::json_schema::SchemaOptions;
+use crate::data::query::{Query, QueryError};
+use crate::data::rules::RuleSet;
+use crate::data::issues::{IssueFilter, IssueGroup, IssueSort, group_issues};
+use crate::data::validation::ValidationSchema;
 use crate::prefs::*;
 
 pub struct FacetsState {
     pub selected_field: Option<String>,
+    pub mode: FacetMode,
     pub current_analysis: Option<FacetAnalysis>,
+    pub numeric_analysis: Option<NumericFacet>,
+    pub timeline_analysis: Option<TimelineFacet>,
+    pub granularity: TimeGranularity,
+    pub range_drag: Option<RangeDrag>,
+    pub clustering: ClusterState,
 }
 
 impl FacetsState {
     pub fn new() -> Self {
         Self {
             selected_field: None,
+            mode: FacetMode::Values,
             current_analysis: None,
+            numeric_analysis: None,
+            timeline_analysis: None,
+            granularity: TimeGranularity::Decade,
+            range_drag: None,
+            clustering: ClusterState::new(),
         }
     }
 
     pub fn analyze_field(&mut self, records: &[BiblioRecord], field_name: &str) {
-        use crate::data::facets::analyze_field_facets;
+        use crate::data::facets::{analyze_field_facets, analyze_numeric_facet, analyze_timeline_facet};
         self.selected_field = Some(field_name.to_string());
-        self.current_analysis = Some(analyze_field_facets(records, field_name));
+        self.current_analysis = None;
+        self.numeric_analysis = None;
+        self.timeline_analysis = None;
+        match self.mode {
+            FacetMode::Numeric => {
+                self.numeric_analysis = Some(analyze_numeric_facet(records, field_name));
+            }
+            FacetMode::Timeline => {
+                self.timeline_analysis = Some(analyze_timeline_facet(records, field_name, self.granularity));
+            }
+            mode => {
+                self.current_analysis = Some(analyze_field_facets(records, field_name, mode));
+            }
+        }
+        self.range_drag = None;
+        self.clustering.reviews.clear();
+        self.clustering.status.clear();
+    }
+
+    pub fn run_clustering(&mut self) {
+        let Some(analysis) = &self.current_analysis else {
+            return;
+        };
+        let clusters = cluster_values(&analysis.values, &self.clustering.options);
+        self.clustering.status = format!("{} clusters found", clusters.len());
+        self.clustering.reviews = clusters
+            .into_iter()
+            .map(|cluster| ClusterReview {
+                merge: false,
+                new_value: cluster.suggested_value().to_string(),
+                cluster,
+            })
+            .collect();
+    }
+}
+
+/// An in-progress drag on the numeric facet histogram.
+pub struct RangeDrag {
+    /// The end of the selection that stays put while dragging
+    pub anchor: f64,
+    pub current: f64,
+}
+
+impl RangeDrag {
+    pub fn range(&self) -> (f64, f64) {
+        (self.anchor.min(self.current), self.anchor.max(self.current))
+    }
+}
+
+/// A cluster under review: whether to merge it and into which value.
+pub struct ClusterReview {
+    pub cluster: Cluster,
+    pub merge: bool,
+    pub new_value: String,
+}
+
+pub struct ClusterState {
+    pub options: ClusterOptions,
+    pub reviews: Vec<ClusterReview>,
+    pub status: String,
+}
+
+impl ClusterState {
+    pub fn new() -> Self {
+        Self {
+            options: ClusterOptions::new(),
+            reviews: Vec::new(),
+            status: String::new(),
+        }
+    }
+}
+
+pub struct SchemaState {
+    pub expanded: HashSet<String>,
+    pub json_schema_options: SchemaOptions,
+}
+
+impl SchemaState {
+    pub fn new() -> Self {
+        Self {
+            expanded: HashSet::new(),
+            json_schema_options: SchemaOptions::new(),
+        }
     }
 }
 
@@ -40,22 +150,286 @@ impl PatternsState {
     }
 }
 
+pub struct CrosstabState {
+    pub row_field: Option<String>,
+    pub col_field: Option<String>,
+    pub analysis: Option<Crosstab>,
+    pub row_sort: (CrosstabSort, bool),
+    pub col_sort: (CrosstabSort, bool),
+}
+
+impl CrosstabState {
+    pub fn new() -> Self {
+        Self {
+            row_field: None,
+            col_field: None,
+            analysis: None,
+            row_sort: (CrosstabSort::Total, true),
+            col_sort: (CrosstabSort::Total, true),
+        }
+    }
+
+    /// Recomputes the table once both fields are chosen.
+    pub fn analyze(&mut self, records: &[BiblioRecord]) {
+        use crate::data::crosstab::analyze_crosstab;
+        self.analysis = match (&self.row_field, &self.col_field) {
+            (Some(row), Some(col)) => Some(analyze_crosstab(records, row, col)),
+            _ => None,
+        };
+        self.row_sort = (CrosstabSort::Total, true);
+        self.col_sort = (CrosstabSort::Total, true);
+    }
+
+    /// Sorts rows by `sort`, flipping the direction if it is already active.
+    /// A column sort by one row follows that row to its new position.
+    pub fn sort_rows(&mut self, sort: CrosstabSort) {
+        let descending = if self.row_sort.0 == sort { !self.row_sort.1 } else { sort != CrosstabSort::Label };
+        self.row_sort = (sort, descending);
+        if let Some(analysis) = &mut self.analysis {
+            let order = analysis.sort_rows(sort, descending);
+            self.col_sort.0 = follow(self.col_sort.0, &order);
+        }
+    }
+
+    pub fn sort_columns(&mut self, sort: CrosstabSort) {
+        let descending = if self.col_sort.0 == sort { !self.col_sort.1 } else { sort != CrosstabSort::Label };
+        self.col_sort = (sort, descending);
+        if let Some(analysis) = &mut self.analysis {
+            let order = analysis.sort_columns(sort, descending);
+            self.row_sort.0 = follow(self.row_sort.0, &order);
+        }
+    }
+}
+
+/// `sort` with its `Cell` index moved to where `order` put that row or column.
+fn follow(sort: CrosstabSort, order: &[usize]) -> CrosstabSort {
+    match sort {
+        CrosstabSort::Cell(old) => order
+            .iter()
+            .position(|&i| i == old)
+            .map_or(CrosstabSort::Total, CrosstabSort::Cell),
+        other => other,
+    }
+}
+
+pub struct BrowseState {
+    pub columns: Vec<BrowseColumn>,
+    /// Index into `columns` and whether it sorts descending
+    pub sort: Option<(usize, bool)>,
+    /// Path typed into the column chooser
+    pub new_column: String,
+}
+
+impl BrowseState {
+    pub fn new() -> Self {
+        Self {
+            columns: Vec::new(),
+            sort: None,
+            new_column: String::new(),
+        }
+    }
+
+    /// The saved layout of a file, or its first top-level fields.
+    pub fn for_file(file: &str, top_level_fields: &[String]) -> Self {
+        let mut state = Self::new();
+        match load_browse_layout(file) {
+            Some(layout) => {
+                state.columns = layout.columns.iter().map(|path| BrowseColumn::new(path)).collect();
+                state.sort = layout.sort.and_then(|(path, descending)| {
+                    state.columns.iter().position(|c| c.path == path).map(|i| (i, descending))
+                });
+            }
+            None => state.reset(top_level_fields),
+        }
+        state
+    }
+
+    /// Back to the first top-level fields, unsorted.
+    pub fn reset(&mut self, top_level_fields: &[String]) {
+        self.columns = top_level_fields
+            .iter()
+            .take(DEFAULT_COLUMN_COUNT)
+            .map(|path| BrowseColumn::new(path))
+            .collect();
+        self.sort = None;
+    }
+
+    pub fn layout(&self) -> BrowseLayout {
+        BrowseLayout {
+            columns: self.columns.iter().map(|c| c.path.clone()).collect(),
+            sort: self.sort.map(|(i, descending)| (self.columns[i].path.clone(), descending)),
+        }
+    }
+
+    pub fn has_column(&self, path: &str) -> bool {
+        self.columns.iter().any(|c| c.path == path)
+    }
+
+    pub fn add_column(&mut self, path: &str) {
+        if !path.is_empty() && !self.has_column(path) {
+            self.columns.push(BrowseColumn::new(path));
+        }
+    }
+
+    pub fn remove_column(&mut self, path: &str) {
+        let Some(i) = self.columns.iter().position(|c| c.path == path) else {
+            return;
+        };
+        self.columns.remove(i);
+        self.sort = match self.sort {
+            Some((sorted, _)) if sorted == i => None,
+            Some((sorted, descending)) if sorted > i => Some((sorted - 1, descending)),
+            sort => sort,
+        };
+    }
+
+    /// Moves column `i` one place left (`-1`) or right (`1`).
+    pub fn move_column(&mut self, i: usize, offset: isize) {
+        let Some(j) = i.checked_add_signed(offset).filter(|&j| j < self.columns.len()) else {
+            return;
+        };
+        self.columns.swap(i, j);
+        self.sort = self.sort.map(|(sorted, descending)| match sorted {
+            s if s == i => (j, descending),
+            s if s == j => (i, descending),
+            s => (s, descending),
+        });
+    }
+
+    /// Sorts by column `i`, flipping the direction if it is already active.
+    pub fn toggle_sort(&mut self, i: usize) {
+        self.sort = match self.sort {
+            Some((sorted, descending)) if sorted == i => Some((i, !descending)),
+            _ => Some((i, false)),
+        };
+    }
+}
+
+/// Which fields an export writes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportColumns {
+    WholeRecords,
+    BrowseColumns,
+    Chosen,
+}
+
+pub struct ExportState {
+    pub open: bool,
+    pub options: ExportOptions,
+    pub columns: ExportColumns,
+    pub chosen: Vec<String>,
+    pub status: String,
+}
+
+impl ExportState {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            options: ExportOptions::new(),
+            columns: ExportColumns::WholeRecords,
+            chosen: Vec::new(),
+            status: String::new(),
+        }
+    }
+}
+
+pub struct IssuesState {
+    pub filter: IssueFilter,
+    pub sort: IssueSort,
+    /// The filtered issues, grouped; rebuilt when issues or filter change
+    pub groups: Vec<IssueGroup>,
+}
+
+impl IssuesState {
+    pub fn new() -> Self {
+        Self {
+            filter: IssueFilter::new(),
+            sort: IssueSort::Severity,
+            groups: Vec::new(),
+        }
+    }
+}
+
+pub struct DuplicatesState {
+    pub options: DuplicateOptions,
+    /// Options the clusters were found with
+    pub found_with: DuplicateOptions,
+    pub clusters: Vec<DuplicateCluster>,
+    pub selected: Option<usize>,
+}
+
+impl DuplicatesState {
+    pub fn new() -> Self {
+        Self {
+            options: DuplicateOptions::new(),
+            found_with: DuplicateOptions::new(),
+            clusters: Vec::new(),
+            selected: None,
+        }
+    }
+
+    /// Shows the results of a detection run, selecting the first cluster.
+    pub fn set_clusters(&mut self, options: DuplicateOptions, clusters: Vec<DuplicateCluster>) {
+        self.options = options.clone();
+        self.found_with = options;
+        self.selected = (!clusters.is_empty()).then_some(0);
+        self.clusters = clusters;
+    }
+}
+
+pub struct DetailsState {
+    /// Path highlighted in the record, e.g. the one an issue is about
+    pub focus_path: Option<String>,
+    /// Scroll the focused path into view on the next frame
+    pub scroll_pending: bool,
+}
+
+impl DetailsState {
+    pub fn new() -> Self {
+        Self {
+            focus_path: None,
+            scroll_pending: false,
+        }
+    }
+}
+
 pub struct BiblioAnalyzerApp {
-    pub records: Vec<BiblioRecord>,
+    /// Shared with check jobs running on worker threads
+    pub records: Arc<Vec<BiblioRecord>>,
     pub search_query: String,
+    pub query_error: Option<QueryError>,
+    pub filters: Vec<RecordFilter>,
     pub filtered_records: Vec<usize>,
     pub selected_record: Option<usize>,
     pub issues: Vec<RecordIssue>,
+    pub search_index: SearchIndex,
     pub current_tab: Tab,
     pub file_path: String,
+    pub input_format: InputFormat,
+    pub records_pointer: String,
+    pub pointer_candidates: Vec<ArrayCandidate>,
     pub error_message: String,
-    pub page: usize,
-    pub page_size: usize,
-    pub loading: bool,
+    pub load_job: Option<LoadJob>,
+    pub check_job: Option<CheckJob>,
+    pub export_job: Option<ExportJob>,
+    /// Records were edited and the schema, issues and duplicates not yet
+    /// recomputed
+    pub checks_outdated: bool,
     pub field_schema: Vec<FieldInfo>,
     pub top_level_fields: Vec<String>,
+    pub schema_state: SchemaState,
     pub facets_state: FacetsState,
     pub patterns_state: PatternsState,
+    pub crosstab_state: CrosstabState,
+    pub browse_state: BrowseState,
+    pub export_state: ExportState,
+    pub issues_state: IssuesState,
+    pub duplicates_state: DuplicatesState,
+    pub details_state: DetailsState,
+    /// Schema the records are validated against; kept across reloads
+    pub validation_schema: Option<Arc<ValidationSchema>>,
+    /// Data-quality rules run on load and on demand
+    pub rules: RuleSet,
 }
 
 impl Default for BiblioAnalyzerApp {
@@ -69,21 +443,37 @@ impl Default for BiblioAnalyzerApp {
         });
 
         Self {
-            records: Vec::new(),
+            records: Arc::new(Vec::new()),
             search_query: String::new(),
+            query_error: None,
+            filters: Vec::new(),
             filtered_records: Vec::new(),
             selected_record: None,
             issues: Vec::new(),
+            search_index: SearchIndex::build(&[]),
             current_tab: Tab::Browse,
             file_path: default_path,
+            input_format: InputFormat::Auto,
+            records_pointer: String::new(),
+            pointer_candidates: Vec::new(),
             error_message: String::new(),
-            page: 0,
-            page_size: 100,
-            loading: false,
+            load_job: None,
+            check_job: None,
+            export_job: None,
+            checks_outdated: false,
             field_schema: Vec::new(),
             top_level_fields: Vec::new(),
+            schema_state: SchemaState::new(),
             facets_state: FacetsState::new(),
             patterns_state: PatternsState::new(),
+            crosstab_state: CrosstabState::new(),
+            browse_state: BrowseState::new(),
+            export_state: ExportState::new(),
+            issues_state: IssuesState::new(),
+            duplicates_state: DuplicatesState::new(),
+            details_state: DetailsState::new(),
+            validation_schema: None,
+            rules: RuleSet::builtin(),
         }
     }
 }
@@ -94,56 +484,451 @@ impl BiblioAnalyzerApp {
         Default::default()
     }
 
-    pub fn load_file(&mut self, path: &str) {
+    /// Starts loading `path` on a worker thread. The current dataset stays in
+    /// place until the new one is fully parsed and analyzed.
+    pub fn load_file(&mut self, ctx: &egui::Context, path: &str) {
+        let pointer = load_records_pointer(path);
+        self.start_load(ctx, path, pointer);
+    }
+
+    /// Reloads the current file using the pointer typed or picked in the UI.
+    /// An empty pointer goes back to auto-detecting the records array.
+    pub fn reload_with_pointer(&mut self, ctx: &egui::Context) {
+        let pointer = Some(self.records_pointer.trim().to_string()).filter(|p| !p.is_empty());
+        self.start_load(ctx, &self.file_path.clone(), pointer);
+    }
+
+    fn start_load(&mut self, ctx: &egui::Context, path: &str, records_pointer: Option<String>) {
+        if let Some(job) = &self.load_job {
+            job.cancel();
+        }
+        // Checks of the old records are of no use once the new ones arrive
+        if let Some(job) = self.check_job.take() {
+            job.cancel();
+        }
         self.error_message.clear();
-        self.loading = true;
-
-        match std::fs::read_to_string(path) {
-            Ok(content) => {
-                match serde_json::from_str::<Vec<serde_json::Value>>(&content) {
-                    Ok(records) => {
-                        self.records = records;
-                        self.file_path = path.to_string();
-                        save_last_file_path(path);
-                        self.page = 0;
-                        
-                        let (schema, fields) = analyze_schema(&self.records);
-                        self.field_schema = schema;
-                        self.top_level_fields = fields;
-                        
-                        self.issues = analyze_quality(&self.records);
-                        self.apply_filter();
-                        self.loading = false;
+
+        let options = LoadOptions {
+            format: self.input_format,
+            records_pointer,
+            rule_pack: rule_pack_for(path),
+            validation: self.validation_schema.clone(),
+            duplicates: load_duplicate_options(path),
+        };
+        let ctx = ctx.clone();
+        self.load_job = Some(LoadJob::spawn(path.to_string(), options, move || ctx.request_repaint()));
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.load_job.is_some()
+    }
+
+    pub fn cancel_loading(&mut self) {
+        if let Some(job) = &self.load_job {
+            job.cancel();
+        }
+    }
+
+    /// Drains messages from the running load, if any. Called once per frame.
+    pub fn poll_loading(&mut self) {
+        while let Some(message) = self.load_job.as_ref().and_then(|job| job.try_recv()) {
+            match message {
+                LoadMessage::Progress(progress) => {
+                    if let Some(job) = &mut self.load_job {
+                        job.progress = progress;
                     }
-                    Err(e) => {
-                        self.error_message = format!("Error parsing JSON: {}", e);
-                        self.loading = false;
+                }
+                LoadMessage::Finished(dataset) => {
+                    let dataset = *dataset;
+                    self.load_job = None;
+                    save_last_file_path(&dataset.path);
+                    if let Some(pointer) = &dataset.records_pointer {
+                        save_records_pointer(&dataset.path, pointer);
                     }
+                    self.install_dataset(dataset);
+                }
+                LoadMessage::Failed(error) => {
+                    self.load_job = None;
+                    self.error_message = error;
+                }
+                LoadMessage::Cancelled => {
+                    self.load_job = None;
+                    self.error_message = "Loading cancelled - previous dataset kept".to_string();
                 }
-            }
-            Err(e) => {
-                self.error_message = format!("Error reading file: {}", e);
-                self.loading = false;
             }
         }
     }
 
+    /// Swaps a freshly loaded dataset in and resets every view of the old one.
+    fn install_dataset(&mut self, dataset: LoadedDataset) {
+        self.records_pointer = dataset.records_pointer.unwrap_or_default();
+        self.pointer_candidates = dataset.pointer_candidates;
+        self.file_path = dataset.path;
+        self.records = Arc::new(dataset.records);
+        self.field_schema = dataset.field_schema;
+        self.top_level_fields = dataset.top_level_fields;
+        self.issues = dataset.issues;
+        self.group_issues();
+        self.search_index = dataset.search_index;
+        self.rules = dataset.rules;
+        if let Some(error) = dataset.rule_pack_error {
+            self.error_message = error;
+        }
+        // Checks started while loading ran on the old records
+        if let Some(job) = self.check_job.take() {
+            job.cancel();
+        }
+        self.checks_outdated = false;
+        self.selected_record = None;
+        self.details_state = DetailsState::new();
+        self.filters.clear();
+        self.schema_state = SchemaState::new();
+        self.facets_state = FacetsState::new();
+        self.patterns_state = PatternsState::new();
+        self.crosstab_state = CrosstabState::new();
+        self.duplicates_state = DuplicatesState::new();
+        self.duplicates_state.set_clusters(dataset.duplicate_options, dataset.duplicates);
+        self.browse_state = BrowseState::for_file(&self.file_path, &self.top_level_fields);
+        self.apply_filter();
+    }
+
+    /// Recomputes `filtered_records` from the search query and the active
+    /// filters. A query with a syntax error is ignored (and the error shown)
+    /// so the list does not empty while the user is still typing.
     pub fn apply_filter(&mut self) {
-        let query = self.search_query.to_lowercase();
-        self.filtered_records = if query.is_empty() {
-            (0..self.records.len()).collect()
+        let query = match Query::parse(&self.search_query) {
+            Ok(query) => {
+                self.query_error = None;
+                query
+            }
+            Err(error) => {
+                self.query_error = Some(error);
+                Query::parse("").expect("empty query parses")
+            }
+        };
+        let filter_set = FilterSet::new(&self.filters);
+
+        if query.is_empty() && filter_set.is_empty() {
+            self.filtered_records = (0..self.records.len()).collect();
         } else {
-            self.records
-                .iter()
-                .enumerate()
-                .filter(|(_, r)| {
-                    let searchable = extract_searchable_text(r);
-                    searchable.to_lowercase().contains(&query)
-                })
-                .map(|(i, _)| i)
-                .collect()
+            // The index narrows the records down; only what it cannot decide
+            // exactly is checked record by record
+            let candidates = query.candidates(&self.search_index)
+                .and(filter_set.candidates(&self.search_index));
+            let records = &self.records;
+            let verify = |i: &usize| filter_set.matches(&records[*i]) && query.matches(&records[*i]);
+
+            self.filtered_records = match (candidates.ids, candidates.exact) {
+                (None, true) => (0..records.len()).collect(),
+                (Some(ids), true) => ids.into_iter().map(|i| i as usize).collect(),
+                (Some(ids), false) => ids.into_iter().map(|i| i as usize).filter(verify).collect(),
+                (None, false) => (0..records.len()).filter(verify).collect(),
+            };
+        }
+        self.sort_filtered();
+    }
+
+    /// Orders the filtered records by the Browse sort column, if any.
+    pub fn sort_filtered(&mut self) {
+        if let Some((i, descending)) = self.browse_state.sort {
+            sort_records(&self.records, &mut self.filtered_records, &self.browse_state.columns[i], descending);
+        }
+    }
+
+    /// Re-sorts after a layout change and remembers the layout for this file.
+    pub fn browse_layout_changed(&mut self) {
+        if self.browse_state.sort.is_some() {
+            self.sort_filtered();
+        } else {
+            self.filtered_records.sort_unstable();
+        }
+        save_browse_layout(&self.file_path, &self.browse_state.layout());
+    }
+
+    /// Starts checks over the current records on a worker thread, replacing
+    /// any checks still running.
+    fn start_checks(&mut self, ctx: &egui::Context, request: CheckRequest) {
+        if let Some(job) = &self.check_job {
+            job.cancel();
+        }
+        let ctx = ctx.clone();
+        self.check_job = Some(CheckJob::spawn(self.records.clone(), request, move || ctx.request_repaint()));
+    }
+
+    pub fn is_checking(&self) -> bool {
+        self.check_job.is_some()
+    }
+
+    pub fn cancel_checks(&mut self) {
+        if let Some(job) = &self.check_job {
+            job.cancel();
+        }
+    }
+
+    /// Drains messages from the running checks, if any. Called once per
+    /// frame.
+    pub fn poll_checks(&mut self) {
+        while let Some(message) = self.check_job.as_ref().and_then(|job| job.try_recv()) {
+            match message {
+                CheckMessage::Progress(progress) => {
+                    if let Some(job) = &mut self.check_job {
+                        job.progress = progress;
+                    }
+                }
+                CheckMessage::Finished(results) => {
+                    let results = *results;
+                    self.check_job = None;
+                    if let Some((field_schema, top_level_fields)) = results.field_schema {
+                        self.field_schema = field_schema;
+                        self.top_level_fields = top_level_fields;
+                        self.checks_outdated = false;
+                    }
+                    if let Some(rules) = results.rules {
+                        save_rule_pack_path(&self.file_path, rules.path.as_deref());
+                        self.rules = rules;
+                    }
+                    if results.validation.is_some() {
+                        self.validation_schema = results.validation;
+                    }
+                    if let Some((options, clusters)) = results.duplicates {
+                        save_duplicate_options(&self.file_path, &options);
+                        self.duplicates_state.set_clusters(options, clusters);
+                    }
+                    self.issues.retain(|issue| !results.replaces.contains(&issue.kind));
+                    self.issues.extend(results.issues);
+                    self.group_issues();
+                }
+                CheckMessage::Cancelled => {
+                    self.check_job = None;
+                    self.error_message = if self.checks_outdated {
+                        "Checks cancelled - schema, issues and duplicates predate the last merge".to_string()
+                    } else {
+                        "Checks cancelled - previous results kept".to_string()
+                    };
+                }
+            }
+        }
+    }
+
+    /// Loads a JSON Schema file and validates every record against it in
+    /// the background. The schema is kept once validation has finished.
+    pub fn load_validation_schema(&mut self, ctx: &egui::Context, path: &str) {
+        match ValidationSchema::load(path) {
+            Ok(schema) => {
+                self.error_message.clear();
+                let request = CheckRequest { validation: Some(Arc::new(schema)), ..Default::default() };
+                self.start_checks(ctx, request);
+            }
+            Err(error) => self.error_message = error,
+        }
+    }
+
+    pub fn clear_validation_schema(&mut self) {
+        self.validation_schema = None;
+        self.issues.retain(|issue| issue.kind != IssueKind::Schema);
+        self.group_issues();
+    }
+
+    /// Re-checks the records with the rule pack at `path` in the background.
+    /// Once finished the pack replaces the current rules and is remembered
+    /// for the current file.
+    pub fn load_rule_pack(&mut self, ctx: &egui::Context, path: &str) {
+        match RuleSet::load(path) {
+            Ok(rules) => {
+                self.error_message.clear();
+                self.start_checks(ctx, CheckRequest { rules: Some(rules), ..Default::default() });
+            }
+            Err(error) => self.error_message = error,
+        }
+    }
+
+    pub fn use_builtin_rules(&mut self, ctx: &egui::Context) {
+        self.start_checks(ctx, CheckRequest { rules: Some(RuleSet::builtin()), ..Default::default() });
+    }
+
+    /// Re-reads the rule pack from disk (so edits take effect) and replaces
+    /// the rule issues with a fresh run over every record.
+    pub fn rerun_rules(&mut self, ctx: &egui::Context) {
+        match &self.rules.path {
+            Some(path) => self.load_rule_pack(ctx, &path.clone()),
+            None => self.use_builtin_rules(ctx),
+        }
+    }
+
+    /// Runs duplicate detection with the Duplicates tab options in the
+    /// background; its results replace the duplicate issues.
+    pub fn find_duplicates(&mut self, ctx: &egui::Context) {
+        let options = self.duplicates_state.options.clone();
+        self.start_checks(ctx, CheckRequest { duplicates: Some(options), ..Default::default() });
+    }
+
+    /// Regroups the issues after they or the Issues tab filter changed.
+    pub fn group_issues(&mut self) {
+        let state = &mut self.issues_state;
+        state.groups = group_issues(&self.issues, &state.filter, state.sort);
+    }
+
+    /// Shows a record in the Details tab, highlighting `path` if given.
+    pub fn open_record(&mut self, idx: usize, path: Option<String>) {
+        self.selected_record = Some(idx);
+        self.details_state.scroll_pending = path.is_some();
+        self.details_state.focus_path = path;
+        self.current_tab = Tab::Details;
+    }
+
+    /// Starts writing the filtered records, in their current order, to
+    /// `path` in the background.
+    pub fn export_filtered(&mut self, ctx: &egui::Context, path: &str) {
+        let state = &self.export_state;
+        let mut options = state.options.clone();
+        options.columns = match state.columns {
+            ExportColumns::WholeRecords => None,
+            ExportColumns::BrowseColumns => Some(self.browse_state.columns.iter().map(|c| c.path.clone()).collect()),
+            ExportColumns::Chosen => Some(state.chosen.clone()),
         };
-        self.page = 0;
+
+        self.export_state.status.clear();
+        let ctx = ctx.clone();
+        self.export_job = Some(ExportJob::spawn(
+            path.to_string(),
+            self.records.clone(),
+            self.filtered_records.clone(),
+            options,
+            move || ctx.request_repaint(),
+        ));
+    }
+
+    pub fn cancel_export(&mut self) {
+        if let Some(job) = &self.export_job {
+            job.cancel();
+        }
+    }
+
+    /// Drains messages from the running export, if any. Called once per
+    /// frame.
+    pub fn poll_export(&mut self) {
+        while let Some(message) = self.export_job.as_ref().and_then(|job| job.try_recv()) {
+            match message {
+                ExportMessage::Progress(progress) => {
+                    if let Some(job) = &mut self.export_job {
+                        job.progress = progress;
+                    }
+                }
+                ExportMessage::Finished(result) => {
+                    let Some(job) = self.export_job.take() else { continue };
+                    self.export_state.status = match result {
+                        Ok(rows) if rows == job.record_count => format!("Exported {} records to {}", rows, job.path),
+                        Ok(rows) => format!("Exported {} records as {} rows to {}", job.record_count, rows, job.path),
+                        Err(e) => format!("Export failed: {}", e),
+                    };
+                }
+                ExportMessage::Cancelled => {
+                    self.export_job = None;
+                    self.export_state.status = "Export cancelled - the partial file was removed".to_string();
+                }
+            }
+        }
+    }
+
+    /// Adds a browse filter (ignoring duplicates) and switches to Browse.
+    pub fn add_filter(&mut self, filter: RecordFilter) {
+        if !self.filters.contains(&filter) {
+            self.filters.push(filter);
+            self.apply_filter();
+        }
+        self.current_tab = Tab::Browse;
+    }
+
+    pub fn remove_filter(&mut self, index: usize) {
+        if index < self.filters.len() {
+            self.filters.remove(index);
+            self.apply_filter();
+        }
+    }
+
+    pub fn clear_filters(&mut self) {
+        self.filters.clear();
+        self.apply_filter();
+    }
+
+    /// Replaces the numeric range filter on `path`, or removes it for `None`.
+    /// Stays on the current tab so the range can be adjusted further.
+    pub fn set_range_filter(&mut self, path: &str, range: Option<(f64, f64)>) {
+        self.filters.retain(|f| !(f.path == path && matches!(f.kind, FilterKind::NumericRange { .. })));
+        if let Some((min, max)) = range {
+            self.filters.push(RecordFilter {
+                path: path.to_string(),
+                kind: FilterKind::NumericRange { min, max },
+            });
+        }
+        self.apply_filter();
+    }
+
+    /// Rewrites the values of every cluster marked for merging to its new
+    /// value, then refreshes the facet and re-clusters. Everything else
+    /// derived from the records is recomputed: the pattern and crosstab
+    /// tables here, the schema, issues and duplicates in the background.
+    pub fn merge_clusters(&mut self, ctx: &egui::Context) {
+        let Some(analysis) = &self.facets_state.current_analysis else {
+            return;
+        };
+        let field = analysis.field_name.clone();
+        let explode_arrays = analysis.mode == FacetMode::ExplodeArrays;
+
+        let mut replacements = HashMap::new();
+        let mut merged_clusters = 0;
+        for review in self.facets_state.clustering.reviews.iter().filter(|r| r.merge) {
+            merged_clusters += 1;
+            for value in &review.cluster.values {
+                replacements.insert(value.value.clone(), review.new_value.clone());
+            }
+        }
+        if replacements.is_empty() {
+            return;
+        }
+
+        // Copies the records only if a check job still holds them
+        let records: &mut Vec<BiblioRecord> = Arc::make_mut(&mut self.records);
+        let outcome = apply_merges(records, &field, explode_arrays, &replacements);
+        for (id, old) in &outcome.previous {
+            self.search_index.update_record(*id, old, &self.records[*id]);
+        }
+        self.facets_state.analyze_field(&self.records, &field);
+        self.facets_state.run_clustering();
+        let skipped = match outcome.skipped {
+            0 => String::new(),
+            n => format!(", {} values left unchanged because they are not text", n),
+        };
+        self.facets_state.clustering.status = format!(
+            "Merged {} clusters ({} records changed{}); {}",
+            merged_clusters, outcome.previous.len(), skipped, self.facets_state.clustering.status
+        );
+        self.apply_filter();
+        if outcome.previous.is_empty() {
+            return;
+        }
+
+        if let (Some(facets), true) = (&self.facets_state.current_analysis, self.patterns_state.current_analysis.is_some()) {
+            self.patterns_state.analyze_from_facets(facets);
+        }
+        if self.crosstab_state.analysis.is_some() {
+            self.crosstab_state.analyze(&self.records);
+        }
+        self.checks_outdated = true;
+        self.recheck_all(ctx);
+    }
+
+    /// Re-runs everything the load derived from the records, after they
+    /// were edited.
+    pub fn recheck_all(&mut self, ctx: &egui::Context) {
+        let request = CheckRequest {
+            field_schema: true,
+            rules: Some(self.rules.clone()),
+            validation: self.validation_schema.clone(),
+            hygiene: true,
+            duplicates: Some(self.duplicates_state.found_with.clone()),
+        };
+        self.start_checks(ctx, request);
     }
 }
 
@@ -208,4 +993,101 @@ fn setup_sepia_theme(ctx: &egui::Context) {
     visuals.panel_fill = bg_dark;
     visuals.window_fill = bg_dark;
     ctx.set_visuals(visuals);
-}
\ No newline at end of file
+}
+
+/// The rule pack for a data file: the one picked for it before, else a
+/// `<file>.rules.json` next to it.
+fn rule_pack_for(path: &str) -> Option<String> {
+    load_rule_pack_path(path).or_else(|| {
+        let sibling = format!("{}.rules.json", path);
+        std::path::Path::new(&sibling).exists().then_some(sibling)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn dataset(records: Vec<BiblioRecord>, issue_sources: &[&str]) -> LoadedDataset {
+        let issues = issue_sources
+            .iter()
+            .enumerate()
+            .map(|(i, source)| RecordIssue {
+                kind: IssueKind::Rule,
+                severity: Severity::Warning,
+                source: source.to_string(),
+                record_index: Some(i % records.len()),
+                path: None,
+                value: None,
+                description: String::new(),
+                suggestion: None,
+            })
+            .collect();
+        LoadedDataset {
+            path: "/nonexistent/test-records.json".to_string(),
+            search_index: SearchIndex::build(&records),
+            records,
+            field_schema: Vec::new(),
+            top_level_fields: Vec::new(),
+            issues,
+            rules: RuleSet::builtin(),
+            rule_pack_error: None,
+            duplicates: Vec::new(),
+            duplicate_options: DuplicateOptions::for_fields(&[]),
+            records_pointer: None,
+            pointer_candidates: Vec::new(),
+        }
+    }
+
+    fn assert_groups_valid(app: &BiblioAnalyzerApp) {
+        for group in &app.issues_state.groups {
+            for &i in &group.issues {
+                assert!(i < app.issues.len(), "group {} points at issue {} of {}", group.source, i, app.issues.len());
+            }
+        }
+    }
+
+    #[test]
+    fn issue_groups_follow_each_load() {
+        let mut app = BiblioAnalyzerApp::default();
+        let records = vec![json!({"title": "a"}), json!({"title": "b"})];
+
+        app.install_dataset(dataset(records.clone(), &["x", "x", "y", "z", "z"]));
+        assert_eq!(app.issues_state.groups.len(), 3);
+        assert_eq!(app.issues_state.groups.iter().map(|g| g.issues.len()).sum::<usize>(), 5);
+        assert_groups_valid(&app);
+
+        app.install_dataset(dataset(records.clone(), &["y"]));
+        assert_eq!(app.issues_state.groups.len(), 1);
+        assert_groups_valid(&app);
+
+        app.install_dataset(dataset(records, &[]));
+        assert!(app.issues_state.groups.is_empty());
+    }
+
+    #[test]
+    fn crosstab_cell_sort_follows_its_column() {
+        let records = vec![
+            json!({"lang": "eng", "year": "1999"}),
+            json!({"lang": "ger", "year": "2000"}),
+            json!({"lang": "ger", "year": "2000"}),
+            json!({"lang": "fre", "year": "2000"}),
+            json!({"lang": "fre", "year": "2001"}),
+        ];
+        let mut state = CrosstabState::new();
+        state.row_field = Some("lang".to_string());
+        state.col_field = Some("year".to_string());
+        state.analyze(&records);
+
+        let column = |state: &CrosstabState, label: &str| {
+            state.analysis.as_ref().unwrap().columns.iter().position(|c| c == label).unwrap()
+        };
+        let by_1999 = column(&state, "1999");
+        state.sort_rows(CrosstabSort::Cell(by_1999));
+        state.sort_columns(CrosstabSort::Label);
+        state.sort_columns(CrosstabSort::Label);
+        assert_eq!(state.row_sort, (CrosstabSort::Cell(column(&state, "1999")), true));
+        assert_eq!(state.analysis.as_ref().unwrap().rows[0], "eng");
+    }
+}
@@ -1,24 +1,135 @@
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 pub mod analysis;
+pub mod checks;
+pub mod clustering;
+pub mod columns;
+pub mod compression;
+pub mod crosstab;
+pub mod dates;
+pub mod duplicates;
+pub mod export;
+pub mod extjson;
 pub mod facets;
+pub mod filters;
+pub mod hygiene;
+pub mod index;
+pub mod issues;
+pub mod json_schema;
+pub mod loader;
+pub mod locator;
+pub mod paths;
 pub mod patterns;
+pub mod query;
+pub mod reports;
+pub mod rules;
+pub mod validation;
 
 pub type BiblioRecord = Value;
 
+/// How bad an issue is. Orders most severe first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    #[default]
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub const ALL: [Severity; 3] = [Severity::Error, Severity::Warning, Severity::Info];
+
+    pub fn label(&self) -> &str {
+        match self {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+            Severity::Info => "Info",
+        }
+    }
+}
+
+/// Which check raised an issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IssueKind {
+    /// A JSON Lines line that is not valid JSON
+    MalformedLine,
+    /// A data-quality rule from the rule pack
+    Rule,
+    /// The user-supplied JSON Schema
+    Schema,
+    /// Duplicate detection
+    Duplicate,
+    /// Whitespace, Unicode and encoding problems in strings
+    Hygiene,
+}
+
+impl IssueKind {
+    pub const ALL: [IssueKind; 5] = [
+        IssueKind::MalformedLine,
+        IssueKind::Rule,
+        IssueKind::Schema,
+        IssueKind::Duplicate,
+        IssueKind::Hygiene,
+    ];
+
+    pub fn label(&self) -> &str {
+        match self {
+            IssueKind::MalformedLine => "Malformed line",
+            IssueKind::Rule => "Quality rule",
+            IssueKind::Schema => "Schema violation",
+            IssueKind::Duplicate => "Duplicate",
+            IssueKind::Hygiene => "Text hygiene",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RecordIssue {
-    pub record_index: usize,
-    pub issue_type: String,
+    pub kind: IssueKind,
+    pub severity: Severity,
+    /// What the issue is an instance of (rule id, schema keyword location,
+    /// ...); the Issues tab groups on kind and source
+    pub source: String,
+    /// `None` for issues about the file or dataset as a whole
+    pub record_index: Option<usize>,
+    /// Field path of the offending value, e.g. `authors[].name`
+    pub path: Option<String>,
+    /// The offending value, shortened for display
+    pub value: Option<String>,
     pub description: String,
+    pub suggestion: Option<String>,
 }
 
+/// Records handled between two progress updates of a load or check.
+pub const RECORDS_PER_PROGRESS_UPDATE: usize = 1_000;
+
+/// Progress callback of the long checks, called every
+/// `RECORDS_PER_PROGRESS_UPDATE` records with the number checked so far.
+/// Returning `false` stops the check early because its job was cancelled;
+/// the partial result is then thrown away.
+pub type ProgressFn<'a> = dyn FnMut(usize) -> bool + 'a;
+
 #[derive(Debug, Clone)]
 pub struct FieldInfo {
+    /// Full field path, e.g. `publisher.place` or `authors[].name`
     pub name: String,
+    /// Most common non-null type
     pub field_type: String,
+    /// Occurrences of each non-null type, most common first
+    pub type_counts: Vec<(String, usize)>,
     pub sample_count: usize,
     pub null_count: usize,
+    pub value_count: usize,
+    pub depth: usize,
+    pub parent: Option<String>,
+}
+
+impl FieldInfo {
+    pub fn is_mixed_type(&self) -> bool {
+        self.type_counts.len() > 1
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -28,17 +139,127 @@ pub struct FacetValue {
     pub percentage: f32,
 }
 
+/// How values at a path are turned into facet choices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FacetMode {
+    /// Each value as a whole; arrays are summarized into one string
+    Values,
+    /// Each array element counted individually (OpenRefine multi-valued cells)
+    ExplodeArrays,
+    /// The number of elements in each array
+    ArrayLength,
+    /// Numbers (and numeric strings) as a range with a histogram
+    Numeric,
+    /// Dates parsed into year intervals and plotted over time
+    Timeline,
+}
+
+impl FacetMode {
+    pub fn all() -> [FacetMode; 5] {
+        [FacetMode::Values, FacetMode::ExplodeArrays, FacetMode::ArrayLength, FacetMode::Numeric, FacetMode::Timeline]
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            FacetMode::Values => "Values",
+            FacetMode::ExplodeArrays => "Explode arrays",
+            FacetMode::ArrayLength => "Array length",
+            FacetMode::Numeric => "Numeric range",
+            FacetMode::Timeline => "Timeline",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FacetAnalysis {
     pub field_name: String,
+    pub mode: FacetMode,
     pub total_values: usize,
     pub unique_values: usize,
     pub null_count: usize,
     pub values: Vec<FacetValue>,
 }
 
+/// One histogram bar covering `start..end` (the last bin includes `end`).
+#[derive(Debug, Clone)]
+pub struct HistogramBin {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize,
+}
+
+/// Summary statistics and a histogram of the numbers found at a path.
+#[derive(Debug, Clone)]
+pub struct NumericFacet {
+    pub field_name: String,
+    /// Records that have at least one number at the path
+    pub record_count: usize,
+    pub value_count: usize,
+    /// Values at the path that are not numbers or numeric strings
+    pub non_numeric_count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    /// (fraction, value) pairs for the 5th, 25th, 75th and 95th percentiles
+    pub quantiles: Vec<(f64, f64)>,
+    /// Every value is a whole number
+    pub integers: bool,
+    pub bins: Vec<HistogramBin>,
+}
+
+/// Bucket size of the timeline facet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeGranularity {
+    Year,
+    Decade,
+    Century,
+}
+
+impl TimeGranularity {
+    pub fn label(&self) -> &str {
+        match self {
+            TimeGranularity::Year => "Year",
+            TimeGranularity::Decade => "Decade",
+            TimeGranularity::Century => "Century",
+        }
+    }
+
+    pub fn years(&self) -> i32 {
+        match self {
+            TimeGranularity::Year => 1,
+            TimeGranularity::Decade => 10,
+            TimeGranularity::Century => 100,
+        }
+    }
+}
+
+/// Date values falling in `start..start + granularity`. Values whose interval
+/// is wider than the bucket (ranges, centuries, circa dates) are counted as
+/// approximate at their midpoint.
+#[derive(Debug, Clone)]
+pub struct TimelineBucket {
+    pub start: i32,
+    pub exact: usize,
+    pub approximate: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct TimelineFacet {
+    pub field_name: String,
+    pub granularity: TimeGranularity,
+    /// Records with at least one parseable date at the path
+    pub record_count: usize,
+    pub parsed_count: usize,
+    pub kind_counts: Vec<(dates::DateKind, usize)>,
+    /// Consecutive buckets from the earliest to the latest date
+    pub buckets: Vec<TimelineBucket>,
+    /// Values that could not be read as dates, most common first
+    pub unparseable: Vec<FacetValue>,
+}
+
 // Re-export pattern types
-pub use patterns::{PatternAnalysis, PatternGroup, PatternType};
+pub use patterns::{PatternAnalysis, PatternType};
 
 #[derive(PartialEq, Clone, Copy)]
 pub enum Tab {
@@ -49,9 +270,15 @@ pub enum Tab {
     Schema,
     Facets,
     Patterns,
+    Crosstab,
+    Duplicates,
 }
 
 pub fn get_value_type(value: &Value) -> String {
+    if let Some(ext) = extjson::as_ext_scalar(value) {
+        return ext.ext_type.name().to_string();
+    }
+
     match value {
         Value::Null => "null".to_string(),
         Value::Bool(_) => "boolean".to_string(),
@@ -61,7 +288,15 @@ pub fn get_value_type(value: &Value) -> String {
             if arr.is_empty() {
                 "array (empty)".to_string()
             } else {
-                format!("array of {}", get_value_type(&arr[0]))
+                // Distinct element types in order of first appearance
+                let mut element_types: Vec<String> = Vec::new();
+                for item in arr {
+                    let item_type = get_value_type(item);
+                    if !element_types.contains(&item_type) {
+                        element_types.push(item_type);
+                    }
+                }
+                format!("array of {}", element_types.join(" | "))
             }
         }
         Value::Object(_) => "object".to_string(),
@@ -69,19 +304,23 @@ pub fn get_value_type(value: &Value) -> String {
 }
 
 pub fn extract_searchable_text(value: &Value) -> String {
+    if let Some(ext) = extjson::as_ext_scalar(value) {
+        return ext.text;
+    }
+
     match value {
         Value::String(s) => s.clone(),
         Value::Number(n) => n.to_string(),
         Value::Bool(b) => b.to_string(),
         Value::Array(arr) => {
             arr.iter()
-                .map(|v| extract_searchable_text(v))
+                .map(extract_searchable_text)
                 .collect::<Vec<_>>()
                 .join(" ")
         }
         Value::Object(obj) => {
             obj.values()
-                .map(|v| extract_searchable_text(v))
+                .map(extract_searchable_text)
                 .collect::<Vec<_>>()
                 .join(" ")
         }
@@ -90,19 +329,23 @@ pub fn extract_searchable_text(value: &Value) -> String {
 }
 
 pub fn get_display_value(value: &Value, max_len: usize) -> String {
-    let display = match value {
-        Value::String(s) => s.clone(),
-        Value::Number(n) => n.to_string(),
-        Value::Bool(b) => b.to_string(),
-        Value::Null => "null".to_string(),
-        Value::Array(arr) => {
-            if arr.is_empty() {
-                "[]".to_string()
-            } else {
-                format!("[{} items]", arr.len())
+    let display = if let Some(ext) = extjson::as_ext_scalar(value) {
+        ext.text
+    } else {
+        match value {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Null => "null".to_string(),
+            Value::Array(arr) => {
+                if arr.is_empty() {
+                    "[]".to_string()
+                } else {
+                    format!("[{} items]", arr.len())
+                }
             }
+            Value::Object(_) => "{...}".to_string(),
         }
-        Value::Object(_) => "{...}".to_string(),
     };
 
     if display.chars().count() > max_len {
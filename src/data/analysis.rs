@@ -1,7 +1,22 @@
 use serde_json::Value;
-use std::collections::HashMap;
-use super::{BiblioRecord, RecordIssue, FieldInfo, get_value_type};
+use std::collections::{HashMap, HashSet};
+use super::{BiblioRecord, FieldInfo, get_value_type};
+use super::extjson::as_ext_scalar;
+use super::paths::{child_key_path, element_path};
 
+#[derive(Default)]
+struct PathStats {
+    sample_count: usize,
+    null_count: usize,
+    value_count: usize,
+    type_counts: HashMap<String, usize>,
+    depth: usize,
+    parent: Option<String>,
+}
+
+/// Discovers every path in the dataset, walking into objects and arrays.
+/// Presence and null counts are per record; `value_count` counts every
+/// occurrence, so array element paths can exceed the number of records.
 pub fn analyze_schema(records: &[BiblioRecord]) -> (Vec<FieldInfo>, Vec<String>) {
     let mut field_schema = Vec::new();
     let mut top_level_fields = Vec::new();
@@ -10,32 +25,46 @@ pub fn analyze_schema(records: &[BiblioRecord]) -> (Vec<FieldInfo>, Vec<String>)
         return (field_schema, top_level_fields);
     }
 
-    let mut field_map: HashMap<String, (usize, usize, String)> = HashMap::new();
+    let mut path_map: HashMap<String, PathStats> = HashMap::new();
+    let mut top_level_keys: HashSet<&str> = HashSet::new();
+    // Paths seen in the current record, and whether any value there was non-null
+    let mut seen: HashMap<String, bool> = HashMap::new();
 
     for record in records {
         if let Value::Object(obj) = record {
             for (key, value) in obj {
-                let entry = field_map.entry(key.clone()).or_insert((0, 0, String::new()));
+                top_level_keys.insert(key);
+                walk_schema(value, child_key_path("", key), None, 0, &mut path_map, &mut seen);
+            }
+        }
 
-                if value.is_null() {
-                    entry.1 += 1;
+        for (path, has_value) in seen.drain() {
+            if let Some(stats) = path_map.get_mut(&path) {
+                if has_value {
+                    stats.sample_count += 1;
                 } else {
-                    entry.0 += 1;
-                    if entry.2.is_empty() {
-                        entry.2 = get_value_type(value);
-                    }
+                    stats.null_count += 1;
                 }
             }
         }
     }
 
-    for (name, (sample_count, null_count, field_type)) in field_map {
-        top_level_fields.push(name.clone());
+    top_level_fields.extend(top_level_keys.into_iter().map(|k| k.to_string()));
+
+    for (name, stats) in path_map {
+        let mut type_counts: Vec<(String, usize)> = stats.type_counts.into_iter().collect();
+        type_counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let field_type = type_counts.first().map(|(t, _)| t.clone()).unwrap_or_default();
+
         field_schema.push(FieldInfo {
             name,
             field_type,
-            sample_count,
-            null_count,
+            type_counts,
+            sample_count: stats.sample_count,
+            null_count: stats.null_count,
+            value_count: stats.value_count,
+            depth: stats.depth,
+            parent: stats.parent,
         });
     }
 
@@ -45,34 +74,42 @@ pub fn analyze_schema(records: &[BiblioRecord]) -> (Vec<FieldInfo>, Vec<String>)
     (field_schema, top_level_fields)
 }
 
-pub fn analyze_quality(records: &[BiblioRecord]) -> Vec<RecordIssue> {
-    let mut issues = Vec::new();
-
-    let max_records_to_analyze = 10_000;
-    let records_to_check = records.len().min(max_records_to_analyze);
-    let is_limited = records.len() > max_records_to_analyze;
-
-    for (idx, record) in records.iter().enumerate().take(records_to_check) {
-        if !record.is_object() {
-            issues.push(RecordIssue {
-                record_index: idx,
-                issue_type: "Invalid Structure".to_string(),
-                description: "Record is not a JSON object".to_string(),
-            });
-        }
+fn walk_schema(
+    value: &Value,
+    path: String,
+    parent: Option<&str>,
+    depth: usize,
+    path_map: &mut HashMap<String, PathStats>,
+    seen: &mut HashMap<String, bool>,
+) {
+    let stats = path_map.entry(path.clone()).or_insert_with(|| PathStats {
+        depth,
+        parent: parent.map(|p| p.to_string()),
+        ..Default::default()
+    });
+    stats.value_count += 1;
+    if !value.is_null() {
+        *stats.type_counts.entry(get_value_type(value)).or_insert(0) += 1;
     }
+    *seen.entry(path.clone()).or_insert(false) |= !value.is_null();
 
-    if is_limited {
-        issues.push(RecordIssue {
-            record_index: usize::MAX,
-            issue_type: "Analysis Limited".to_string(),
-            description: format!(
-                "Dataset too large - only analyzed first {} of {} records",
-                max_records_to_analyze,
-                records.len()
-            ),
-        });
+    // Extended JSON wrappers are scalars, not nested structure
+    if as_ext_scalar(value).is_some() {
+        return;
     }
 
-    issues
-}
\ No newline at end of file
+    match value {
+        Value::Object(obj) => {
+            for (key, child) in obj {
+                walk_schema(child, child_key_path(&path, key), Some(&path), depth + 1, path_map, seen);
+            }
+        }
+        Value::Array(arr) => {
+            let item_path = element_path(&path);
+            for item in arr {
+                walk_schema(item, item_path.clone(), Some(&path), depth + 1, path_map, seen);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,244 @@
+//! Checks re-run over the loaded records on a worker thread, so the window
+//! stays responsive while a new schema or rule pack is applied, or after
+//! values were merged.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use super::{BiblioRecord, FieldInfo, IssueKind, RecordIssue};
+use super::analysis::analyze_schema;
+use super::duplicates::{DuplicateCluster, DuplicateOptions, duplicate_issues, find_duplicates};
+use super::hygiene::check_hygiene;
+use super::rules::RuleSet;
+use super::validation::ValidationSchema;
+
+/// The checks a job runs. Each check that runs replaces the issues of its
+/// kind once the job has finished.
+#[derive(Default)]
+pub struct CheckRequest {
+    /// Infer the field schema again
+    pub field_schema: bool,
+    /// Evaluate this rule set over every record
+    pub rules: Option<RuleSet>,
+    /// Validate every record against this schema
+    pub validation: Option<Arc<ValidationSchema>>,
+    /// Check the text hygiene of every string
+    pub hygiene: bool,
+    /// Look for duplicate records with these settings
+    pub duplicates: Option<DuplicateOptions>,
+}
+
+impl CheckRequest {
+    fn first_step(&self) -> CheckStep {
+        if self.field_schema {
+            CheckStep::FieldSchema
+        } else if self.rules.is_some() {
+            CheckStep::Rules
+        } else if self.validation.is_some() {
+            CheckStep::Validation
+        } else if self.hygiene {
+            CheckStep::Hygiene
+        } else {
+            CheckStep::Duplicates
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckStep {
+    FieldSchema,
+    Rules,
+    Validation,
+    Hygiene,
+    Duplicates,
+}
+
+impl CheckStep {
+    pub fn label(&self) -> &str {
+        match self {
+            CheckStep::FieldSchema => "Analyzing schema",
+            CheckStep::Rules => "Running quality rules",
+            CheckStep::Validation => "Validating against schema",
+            CheckStep::Hygiene => "Checking text hygiene",
+            CheckStep::Duplicates => "Finding duplicates",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckProgress {
+    pub step: CheckStep,
+    pub records_checked: usize,
+    pub total_records: usize,
+}
+
+impl CheckProgress {
+    /// Progress of the current step in `0.0..=1.0`.
+    pub fn fraction(&self) -> f32 {
+        if self.total_records > 0 {
+            (self.records_checked as f32 / self.total_records as f32).min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// What a finished job found, applied to the app in one go.
+pub struct CheckResults {
+    /// Field schema and top-level fields, if re-inferred
+    pub field_schema: Option<(Vec<FieldInfo>, Vec<String>)>,
+    /// The rules that were run, to be kept by the app
+    pub rules: Option<RuleSet>,
+    /// The schema that was validated against, to be kept by the app
+    pub validation: Option<Arc<ValidationSchema>>,
+    /// The duplicate clusters found and the settings they were found with
+    pub duplicates: Option<(DuplicateOptions, Vec<DuplicateCluster>)>,
+    /// Issue kinds whose old issues are replaced by `issues`
+    pub replaces: Vec<IssueKind>,
+    pub issues: Vec<RecordIssue>,
+}
+
+pub enum CheckMessage {
+    Progress(CheckProgress),
+    Finished(Box<CheckResults>),
+    Cancelled,
+}
+
+/// Handle to checks running on a worker thread.
+pub struct CheckJob {
+    receiver: Receiver<CheckMessage>,
+    cancel: Arc<AtomicBool>,
+    pub progress: CheckProgress,
+}
+
+impl CheckJob {
+    /// Starts the requested checks over `records` in the background. `notify`
+    /// is called whenever a new message is available.
+    pub fn spawn(records: Arc<Vec<BiblioRecord>>, request: CheckRequest, notify: impl Fn() + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let progress = CheckProgress {
+            step: request.first_step(),
+            records_checked: 0,
+            total_records: records.len(),
+        };
+
+        let mut reporter = Reporter {
+            sender,
+            cancel: cancel.clone(),
+            notify: Box::new(notify),
+            progress: progress.clone(),
+        };
+
+        thread::spawn(move || {
+            let message = match run_checks(&records, request, &mut reporter) {
+                Some(results) => CheckMessage::Finished(Box::new(results)),
+                None => CheckMessage::Cancelled,
+            };
+            reporter.send(message);
+        });
+
+        Self {
+            receiver,
+            cancel,
+            progress,
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelling(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Returns the next pending message without blocking.
+    pub fn try_recv(&self) -> Option<CheckMessage> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+struct Reporter {
+    sender: Sender<CheckMessage>,
+    cancel: Arc<AtomicBool>,
+    notify: Box<dyn Fn() + Send>,
+    progress: CheckProgress,
+}
+
+impl Reporter {
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    fn send(&self, message: CheckMessage) {
+        // The app may have dropped the job already; nothing to do then.
+        if self.sender.send(message).is_ok() {
+            (self.notify)();
+        }
+    }
+
+    fn set_step(&mut self, step: CheckStep) {
+        self.progress.step = step;
+        self.progress.records_checked = 0;
+        self.send(CheckMessage::Progress(self.progress.clone()));
+    }
+
+    /// The progress callback handed to the checks.
+    fn records_checked(&mut self, count: usize) -> bool {
+        self.progress.records_checked = count;
+        self.send(CheckMessage::Progress(self.progress.clone()));
+        !self.is_cancelled()
+    }
+}
+
+/// Runs every requested check in turn; `None` if the job was cancelled.
+fn run_checks(records: &[BiblioRecord], request: CheckRequest, reporter: &mut Reporter) -> Option<CheckResults> {
+    let mut results = CheckResults {
+        field_schema: None,
+        rules: None,
+        validation: None,
+        duplicates: None,
+        replaces: Vec::new(),
+        issues: Vec::new(),
+    };
+
+    if request.field_schema {
+        reporter.set_step(CheckStep::FieldSchema);
+        results.field_schema = Some(analyze_schema(records));
+        if reporter.is_cancelled() {
+            return None;
+        }
+    }
+
+    if let Some(rules) = request.rules {
+        reporter.set_step(CheckStep::Rules);
+        results.issues.extend(rules.evaluate(records, &mut |n| reporter.records_checked(n)));
+        results.replaces.push(IssueKind::Rule);
+        results.rules = Some(rules);
+    }
+
+    if let Some(schema) = request.validation {
+        reporter.set_step(CheckStep::Validation);
+        results.issues.extend(schema.validate(records, &mut |n| reporter.records_checked(n)));
+        results.replaces.push(IssueKind::Schema);
+        results.validation = Some(schema);
+    }
+
+    if request.hygiene {
+        reporter.set_step(CheckStep::Hygiene);
+        results.issues.extend(check_hygiene(records, &mut |n| reporter.records_checked(n)));
+        results.replaces.push(IssueKind::Hygiene);
+    }
+
+    if let Some(options) = request.duplicates {
+        reporter.set_step(CheckStep::Duplicates);
+        let clusters = find_duplicates(records, &options, &mut |n| reporter.records_checked(n));
+        results.issues.extend(duplicate_issues(&clusters, &options));
+        results.replaces.push(IssueKind::Duplicate);
+        results.duplicates = Some((options, clusters));
+    }
+
+    (!reporter.is_cancelled()).then_some(results)
+}
@@ -0,0 +1,721 @@
+//! Clustering of facet values that are probably spelling variants of the same
+//! thing, following OpenRefine: key collision methods group values whose
+//! normalized keys are equal, nearest-neighbour methods group values within a
+//! distance radius of each other.
+
+use flate2::write::DeflateEncoder;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::Write;
+use super::FacetValue;
+use super::facets::extract_field_text;
+use super::paths::FieldPath;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClusterMethod {
+    Fingerprint,
+    NgramFingerprint,
+    Metaphone,
+    Cologne,
+    Levenshtein,
+    Ppm,
+}
+
+impl ClusterMethod {
+    pub fn all() -> [ClusterMethod; 6] {
+        [
+            ClusterMethod::Fingerprint,
+            ClusterMethod::NgramFingerprint,
+            ClusterMethod::Metaphone,
+            ClusterMethod::Cologne,
+            ClusterMethod::Levenshtein,
+            ClusterMethod::Ppm,
+        ]
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            ClusterMethod::Fingerprint => "Key collision: fingerprint",
+            ClusterMethod::NgramFingerprint => "Key collision: n-gram fingerprint",
+            ClusterMethod::Metaphone => "Key collision: metaphone",
+            ClusterMethod::Cologne => "Key collision: Cologne phonetic",
+            ClusterMethod::Levenshtein => "Nearest neighbour: Levenshtein",
+            ClusterMethod::Ppm => "Nearest neighbour: PPM",
+        }
+    }
+
+    pub fn is_nearest_neighbour(&self) -> bool {
+        matches!(self, ClusterMethod::Levenshtein | ClusterMethod::Ppm)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClusterOptions {
+    pub method: ClusterMethod,
+    /// Gram size for the n-gram fingerprint
+    pub ngram_size: usize,
+    /// Maximum distance between neighbours
+    pub radius: f32,
+    /// Values are only compared if they share a substring of this many
+    /// characters (half as many for values shorter than twice this)
+    pub block_size: usize,
+}
+
+impl ClusterOptions {
+    pub fn new() -> Self {
+        Self {
+            method: ClusterMethod::Fingerprint,
+            ngram_size: 2,
+            radius: 1.0,
+            block_size: 6,
+        }
+    }
+}
+
+/// A group of facet values, most common first.
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    pub values: Vec<FacetValue>,
+    pub record_count: usize,
+}
+
+impl Cluster {
+    /// The value the cluster is merged into unless the user types another one.
+    pub fn suggested_value(&self) -> &str {
+        &self.values[0].value
+    }
+}
+
+/// Clusters the values of a facet. Only groups of two or more values are
+/// returned, largest first.
+pub fn cluster_values(values: &[FacetValue], options: &ClusterOptions) -> Vec<Cluster> {
+    let groups = match options.method {
+        ClusterMethod::Fingerprint => key_collision(values, fingerprint),
+        ClusterMethod::NgramFingerprint => key_collision(values, |s| ngram_fingerprint(s, options.ngram_size)),
+        ClusterMethod::Metaphone => key_collision(values, metaphone),
+        ClusterMethod::Cologne => key_collision(values, cologne_phonetic),
+        ClusterMethod::Levenshtein => {
+            nearest_neighbour(values, options, |a, b| levenshtein(a, b) as f32)
+        }
+        ClusterMethod::Ppm => {
+            let mut compressor = PpmDistance::new();
+            nearest_neighbour(values, options, |a, b| compressor.distance(a, b))
+        }
+    };
+
+    let mut clusters: Vec<Cluster> = groups
+        .into_iter()
+        .map(|indices| {
+            let mut members: Vec<FacetValue> = indices.into_iter().map(|i| values[i].clone()).collect();
+            members.sort_by_key(|v| std::cmp::Reverse(v.count));
+            Cluster {
+                record_count: members.iter().map(|v| v.count).sum(),
+                values: members,
+            }
+        })
+        .collect();
+
+    clusters.sort_by_key(|c| std::cmp::Reverse((c.values.len(), c.record_count)));
+    clusters
+}
+
+fn key_collision(values: &[FacetValue], key: impl Fn(&str) -> String) -> Vec<Vec<usize>> {
+    let mut groups: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, value) in values.iter().enumerate() {
+        let k = key(&value.value);
+        if !k.is_empty() {
+            groups.entry(k).or_default().push(i);
+        }
+    }
+    groups.into_values().filter(|g| g.len() > 1).collect()
+}
+
+/// Compares only values that share a block (substring of `block_size`
+/// characters), then forms one cluster per value from its neighbours within
+/// the radius. Clusters contained in a larger one are dropped.
+///
+/// A single edit in a value not much longer than the block leaves it no
+/// block in common with its variant ("Oxfrd" and "Oxford"), so such values
+/// are also blocked on substrings of half the size.
+fn nearest_neighbour(
+    values: &[FacetValue],
+    options: &ClusterOptions,
+    mut distance: impl FnMut(&str, &str) -> f32,
+) -> Vec<Vec<usize>> {
+    let size = options.block_size.max(1);
+    let short_size = (size / 2).max(1);
+    let mut blocks: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, value) in values.iter().enumerate() {
+        let chars: Vec<char> = value.value.chars().collect();
+        let mut keys = HashSet::new();
+        block_keys(&chars, size, &mut keys);
+        if chars.len() < 2 * size {
+            block_keys(&chars, short_size, &mut keys);
+        }
+        for key in keys {
+            blocks.entry(key).or_default().push(i);
+        }
+    }
+
+    let mut compared: HashSet<(usize, usize)> = HashSet::new();
+    let mut neighbours: HashMap<usize, BTreeSet<usize>> = HashMap::new();
+    for block in blocks.values() {
+        for (n, &a) in block.iter().enumerate() {
+            for &b in &block[n + 1..] {
+                let pair = (a.min(b), a.max(b));
+                if !compared.insert(pair) {
+                    continue;
+                }
+                if distance(&values[a].value, &values[b].value) <= options.radius {
+                    neighbours.entry(a).or_default().insert(b);
+                    neighbours.entry(b).or_default().insert(a);
+                }
+            }
+        }
+    }
+
+    let mut candidates: Vec<BTreeSet<usize>> = neighbours
+        .into_iter()
+        .map(|(i, mut set)| {
+            set.insert(i);
+            set
+        })
+        .collect();
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.len()));
+
+    let mut clusters: Vec<BTreeSet<usize>> = Vec::new();
+    for candidate in candidates {
+        if !clusters.iter().any(|c| candidate.is_subset(c)) {
+            clusters.push(candidate);
+        }
+    }
+    clusters.into_iter().map(|c| c.into_iter().collect()).collect()
+}
+
+/// The `size`-character substrings of a value, or the whole value if it is
+/// shorter.
+fn block_keys(chars: &[char], size: usize, keys: &mut HashSet<String>) {
+    if chars.len() <= size {
+        keys.insert(chars.iter().collect());
+    } else {
+        for window in chars.windows(size) {
+            keys.insert(window.iter().collect());
+        }
+    }
+}
+
+/// OpenRefine's fingerprint: lowercase, strip accents and punctuation, then
+/// sort and de-duplicate the whitespace-separated tokens.
+pub fn fingerprint(value: &str) -> String {
+    let cleaned: String = fold_to_ascii(&value.trim().to_lowercase())
+        .chars()
+        .filter(|c| !c.is_ascii_punctuation() && !c.is_control())
+        .collect();
+    let tokens: BTreeSet<&str> = cleaned.split_whitespace().collect();
+    tokens.into_iter().collect::<Vec<_>>().join(" ")
+}
+
+/// Sorted, de-duplicated character n-grams of the value with punctuation and
+/// whitespace removed. Catches variants that differ in spacing.
+pub fn ngram_fingerprint(value: &str, n: usize) -> String {
+    let chars: Vec<char> = fold_to_ascii(&value.to_lowercase())
+        .chars()
+        .filter(|c| !c.is_ascii_punctuation() && !c.is_control() && !c.is_whitespace())
+        .collect();
+    let n = n.max(1);
+    if chars.len() < n {
+        return chars.into_iter().collect();
+    }
+    let grams: BTreeSet<String> = chars.windows(n).map(|w| w.iter().collect()).collect();
+    grams.into_iter().collect()
+}
+
+/// Replaces accented Latin letters with their unaccented ASCII form.
+pub fn fold_to_ascii(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        let folded = match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+            'æ' => "ae",
+            'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+            'ď' | 'đ' | 'ð' => "d",
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+            'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+            'ĥ' | 'ħ' => "h",
+            'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+            'ĵ' => "j",
+            'ķ' => "k",
+            'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+            'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+            'œ' => "oe",
+            'ŕ' | 'ŗ' | 'ř' => "r",
+            'ś' | 'ŝ' | 'ş' | 'š' => "s",
+            'ß' => "ss",
+            'ţ' | 'ť' | 'ŧ' => "t",
+            'þ' => "th",
+            'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+            'ŵ' => "w",
+            'ý' | 'ÿ' | 'ŷ' => "y",
+            'ź' | 'ż' | 'ž' => "z",
+            _ => {
+                out.push(c);
+                continue;
+            }
+        };
+        out.push_str(folded);
+    }
+    out
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'A' | 'E' | 'I' | 'O' | 'U')
+}
+
+/// Lawrence Philips' original Metaphone. Multi-word values are encoded word by
+/// word so word order still matters less than spelling.
+pub fn metaphone(value: &str) -> String {
+    let words: Vec<String> = fold_to_ascii(&value.to_lowercase())
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(|w| metaphone_word(&w.to_ascii_uppercase()))
+        .filter(|code| !code.is_empty())
+        .collect();
+    words.join(" ")
+}
+
+fn metaphone_word(word: &str) -> String {
+    let mut chars: Vec<char> = word.chars().collect();
+
+    // Initial letter exceptions
+    match (chars.first(), chars.get(1)) {
+        (Some('A'), Some('E')) | (Some('G' | 'K' | 'P'), Some('N')) | (Some('W'), Some('R')) => {
+            chars.remove(0);
+        }
+        (Some('X'), _) => chars[0] = 'S',
+        (Some('W'), Some('H')) => {
+            chars.remove(1);
+        }
+        _ => {}
+    }
+
+    let at = |i: usize| chars.get(i).copied();
+    let mut code = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        let prev = if i > 0 { at(i - 1) } else { None };
+        let next = at(i + 1);
+        let after = at(i + 2);
+
+        // Doubled letters are encoded once, except C
+        if prev == Some(c) && c != 'C' {
+            continue;
+        }
+
+        match c {
+            'A' | 'E' | 'I' | 'O' | 'U' if i == 0 => code.push(c),
+            // Silent in a final "MB" as in "dumb"
+            'B' if !(prev == Some('M') && next.is_none()) => code.push('B'),
+            'C' => {
+                if next == Some('I') && after == Some('A') {
+                    code.push('X');
+                } else if next == Some('H') {
+                    code.push(if prev == Some('S') { 'K' } else { 'X' });
+                } else if matches!(next, Some('I' | 'E' | 'Y')) {
+                    if prev != Some('S') {
+                        code.push('S');
+                    }
+                } else {
+                    code.push('K');
+                }
+            }
+            'D' => {
+                if next == Some('G') && matches!(after, Some('E' | 'I' | 'Y')) {
+                    code.push('J');
+                } else {
+                    code.push('T');
+                }
+            }
+            'G' => {
+                let silent_gh = next == Some('H') && !after.is_none_or(is_vowel);
+                let silent_gn = next == Some('N')
+                    && (after.is_none() || (after == Some('E') && at(i + 3) == Some('D') && at(i + 4).is_none()));
+                if silent_gh || silent_gn {
+                    continue;
+                }
+                if matches!(next, Some('I' | 'E' | 'Y')) && prev != Some('G') {
+                    code.push('J');
+                } else {
+                    code.push('K');
+                }
+            }
+            'H' => {
+                let after_vowel_only = prev.is_some_and(is_vowel) && !next.is_some_and(is_vowel);
+                if !after_vowel_only && !matches!(prev, Some('C' | 'S' | 'P' | 'T' | 'G')) {
+                    code.push('H');
+                }
+            }
+            'K' if prev != Some('C') => code.push('K'),
+            'P' => code.push(if next == Some('H') { 'F' } else { 'P' }),
+            'Q' => code.push('K'),
+            'S' => {
+                if next == Some('H') || (next == Some('I') && matches!(after, Some('O' | 'A'))) {
+                    code.push('X');
+                } else {
+                    code.push('S');
+                }
+            }
+            'T' => {
+                if next == Some('I') && matches!(after, Some('O' | 'A')) {
+                    code.push('X');
+                } else if next == Some('H') {
+                    code.push('0');
+                } else if !(next == Some('C') && after == Some('H')) {
+                    code.push('T');
+                }
+            }
+            'V' => code.push('F'),
+            'W' | 'Y' if next.is_some_and(is_vowel) => code.push(c),
+            'X' => code.push_str("KS"),
+            'Z' => code.push('S'),
+            'F' | 'J' | 'L' | 'M' | 'N' | 'R' => code.push(c),
+            _ => {}
+        }
+    }
+
+    code
+}
+
+/// Kölner Phonetik, a phonetic key tuned for German names and places.
+pub fn cologne_phonetic(value: &str) -> String {
+    let chars: Vec<char> = value
+        .to_uppercase()
+        .chars()
+        .filter_map(|c| match c {
+            'Ä' => Some('A'),
+            'Ö' => Some('O'),
+            'Ü' => Some('U'),
+            'ß' => Some('S'),
+            c if c.is_ascii_alphabetic() => Some(c),
+            _ => None,
+        })
+        .collect();
+
+    let mut digits = Vec::with_capacity(chars.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let prev = if i > 0 { chars.get(i - 1).copied() } else { None };
+        let next = chars.get(i + 1).copied();
+        let digit = match c {
+            'A' | 'E' | 'I' | 'J' | 'O' | 'U' | 'Y' => '0',
+            'H' => continue,
+            'B' => '1',
+            'P' => if next == Some('H') { '3' } else { '1' },
+            'D' | 'T' => if matches!(next, Some('C' | 'S' | 'Z')) { '8' } else { '2' },
+            'F' | 'V' | 'W' => '3',
+            'G' | 'K' | 'Q' => '4',
+            'C' => {
+                let hard = if i == 0 {
+                    matches!(next, Some('A' | 'H' | 'K' | 'L' | 'O' | 'Q' | 'R' | 'U' | 'X'))
+                } else {
+                    matches!(next, Some('A' | 'H' | 'K' | 'O' | 'Q' | 'U' | 'X'))
+                        && !matches!(prev, Some('S' | 'Z'))
+                };
+                if hard { '4' } else { '8' }
+            }
+            'X' => {
+                if matches!(prev, Some('C' | 'K' | 'Q')) {
+                    '8'
+                } else {
+                    digits.push('4');
+                    '8'
+                }
+            }
+            'L' => '5',
+            'M' | 'N' => '6',
+            'R' => '7',
+            'S' | 'Z' => '8',
+            _ => continue,
+        };
+        digits.push(digit);
+    }
+
+    let mut code = String::new();
+    for (i, &d) in digits.iter().enumerate() {
+        if i > 0 && digits[i - 1] == d {
+            continue;
+        }
+        if d != '0' || i == 0 {
+            code.push(d);
+        }
+    }
+    code
+}
+
+/// Edit distance in characters.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(prev[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut current);
+    }
+    prev[b.len()]
+}
+
+/// Compression distance as in OpenRefine's PPM method: two strings are close
+/// when compressing them together costs little more than compressing each
+/// with itself. Deflate stands in for PPM; self-compression sizes are cached.
+struct PpmDistance {
+    self_sizes: HashMap<String, usize>,
+}
+
+impl PpmDistance {
+    fn new() -> Self {
+        Self { self_sizes: HashMap::new() }
+    }
+
+    fn distance(&mut self, a: &str, b: &str) -> f32 {
+        let aa = self.self_size(a);
+        let bb = self.self_size(b);
+        let ab = compressed_len(&format!("{}{}", a, b));
+        let ba = compressed_len(&format!("{}{}", b, a));
+        10.0 * ((ab + ba) as f32 / (aa + bb).max(1) as f32 - 1.0)
+    }
+
+    fn self_size(&mut self, s: &str) -> usize {
+        if let Some(&size) = self.self_sizes.get(s) {
+            return size;
+        }
+        let size = compressed_len(&format!("{}{}", s, s));
+        self.self_sizes.insert(s.to_string(), size);
+        size
+    }
+}
+
+fn compressed_len(s: &str) -> usize {
+    let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+    // Writing to a Vec cannot fail
+    let _ = encoder.write_all(s.as_bytes());
+    encoder.finish().map(|out| out.len()).unwrap_or(s.len())
+}
+
+/// What a merge changed.
+pub struct MergeOutcome {
+    /// Index and previous version of every record that changed, so the
+    /// search index can be updated
+    pub previous: Vec<(usize, Value)>,
+    /// Values counted under a merged facet value that were left alone
+    /// because they are not strings (numbers, booleans, Extended JSON,
+    /// whole arrays)
+    pub skipped: usize,
+}
+
+/// Rewrites string values at `field_path` using `replacements` (old value to
+/// new value). In exploded mode array elements are rewritten individually.
+/// Other values are never retyped into strings, only counted.
+pub fn apply_merges(
+    records: &mut [Value],
+    field_path: &str,
+    explode_arrays: bool,
+    replacements: &HashMap<String, String>,
+) -> MergeOutcome {
+    let path = FieldPath::parse(field_path);
+    let mut outcome = MergeOutcome { previous: Vec::new(), skipped: 0 };
+
+    for (id, record) in records.iter_mut().enumerate() {
+        let found = path.resolve(record);
+        outcome.skipped += found.iter().map(|value| count_skipped(value, explode_arrays, replacements)).sum::<usize>();
+        let affected = found
+            .into_iter()
+            .any(|value| needs_replacement(value, explode_arrays, replacements));
+        if !affected {
+            continue;
+        }
+
+        outcome.previous.push((id, record.clone()));
+        path.for_each_mut(record, &mut |value| replace_value(value, explode_arrays, replacements));
+    }
+    outcome
+}
+
+fn count_skipped(value: &Value, explode_arrays: bool, replacements: &HashMap<String, String>) -> usize {
+    match value {
+        Value::String(_) | Value::Null => 0,
+        Value::Array(arr) if explode_arrays => arr.iter().map(|item| count_skipped(item, explode_arrays, replacements)).sum(),
+        _ => {
+            let text = extract_field_text(value);
+            usize::from(replacements.get(&text).is_some_and(|new_value| *new_value != text))
+        }
+    }
+}
+
+fn needs_replacement(value: &Value, explode_arrays: bool, replacements: &HashMap<String, String>) -> bool {
+    match value {
+        Value::String(s) => replacements.get(s.as_str()).is_some_and(|new_value| new_value != s),
+        Value::Array(arr) if explode_arrays => arr.iter().any(|item| needs_replacement(item, explode_arrays, replacements)),
+        _ => false,
+    }
+}
+
+fn replace_value(value: &mut Value, explode_arrays: bool, replacements: &HashMap<String, String>) {
+    match value {
+        Value::String(s) => {
+            if let Some(new_value) = replacements.get(s.as_str()) {
+                *s = new_value.clone();
+            }
+        }
+        Value::Array(arr) if explode_arrays => {
+            for item in arr {
+                replace_value(item, explode_arrays, replacements);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn facet_values(values: &[(&str, usize)]) -> Vec<FacetValue> {
+        values
+            .iter()
+            .map(|&(value, count)| FacetValue { value: value.to_string(), count, percentage: 0.0 })
+            .collect()
+    }
+
+    #[test]
+    fn fingerprint_ignores_case_punctuation_order_and_accents() {
+        assert_eq!(fingerprint("  Tom Cruise "), "cruise tom");
+        assert_eq!(fingerprint("Cruise, Tom."), "cruise tom");
+        assert_eq!(fingerprint("Gödel, Escher, Bach"), "bach escher godel");
+        assert_eq!(fingerprint("a a b"), "a b");
+    }
+
+    #[test]
+    fn ngram_fingerprint_known_answers() {
+        assert_eq!(ngram_fingerprint("Paris", 2), "arispari");
+        assert_eq!(ngram_fingerprint("Paris, France", 2), ngram_fingerprint("paris france", 2));
+        assert_eq!(ngram_fingerprint("abab", 2), "abba");
+        assert_eq!(ngram_fingerprint("Ab", 3), "ab");
+        // n = 0 behaves like n = 1
+        assert_eq!(ngram_fingerprint("cab", 0), "abc");
+    }
+
+    #[test]
+    fn metaphone_known_answers() {
+        let cases = [
+            ("howl", "HL"),
+            ("The", "0"),
+            ("quick", "KK"),
+            ("brown", "BRN"),
+            ("fox", "FKS"),
+            ("jumped", "JMPT"),
+            ("over", "OFR"),
+            ("lazy", "LS"),
+            ("dogs", "TKS"),
+            ("Knight", "NT"),
+            ("Wright", "RT"),
+            ("Philips", "FLPS"),
+        ];
+        for (word, expected) in cases {
+            assert_eq!(metaphone(word), expected, "metaphone({:?})", word);
+        }
+        assert_eq!(metaphone("Oxford Univ. Press"), "OKSFRT UNF PRS");
+    }
+
+    #[test]
+    fn cologne_known_answers() {
+        let cases = [
+            ("Müller-Lüdenscheidt", "65752682"),
+            ("Wikipedia", "3412"),
+            ("Breschnew", "17863"),
+            ("Christoph", "47823"),
+            ("Xaver", "4837"),
+        ];
+        for (word, expected) in cases {
+            assert_eq!(cologne_phonetic(word), expected, "cologne_phonetic({:?})", word);
+        }
+        for spelling in ["Meier", "Mayr", "Maier"] {
+            assert_eq!(cologne_phonetic(spelling), "67");
+        }
+        assert_eq!(cologne_phonetic("Müller"), cologne_phonetic("Mueller"));
+    }
+
+    #[test]
+    fn levenshtein_known_answers() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("flaw", "lawn"), 2);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("abc", ""), 3);
+        assert_eq!(levenshtein("same", "same"), 0);
+        // Distances are in characters, not bytes
+        assert_eq!(levenshtein("Müller", "Muller"), 1);
+    }
+
+    #[test]
+    fn key_collision_clusters_largest_value_first() {
+        let values = facet_values(&[("Oxford Univ. Press", 2), ("oxford univ press", 5), ("Cambridge", 9)]);
+        let clusters = cluster_values(&values, &ClusterOptions::new());
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].suggested_value(), "oxford univ press");
+        assert_eq!(clusters[0].record_count, 7);
+    }
+
+    #[test]
+    fn merges_strings_and_counts_values_that_are_not_text() {
+        let mut records = vec![
+            json!({"year": "1999"}),
+            json!({"year": 1999}),
+            json!({"year": ["1999", 1999, "2001"]}),
+            json!({"year": null}),
+            json!({"year": "2001"}),
+        ];
+        let replacements = HashMap::from([("1999".to_string(), "1999-01-01".to_string())]);
+
+        let outcome = apply_merges(&mut records, "year", true, &replacements);
+        let changed: Vec<usize> = outcome.previous.iter().map(|(id, _)| *id).collect();
+        assert_eq!(changed, vec![0, 2]);
+        assert_eq!(outcome.previous[1].1, json!({"year": ["1999", 1999, "2001"]}));
+        assert_eq!(outcome.skipped, 2);
+        assert_eq!(records[0], json!({"year": "1999-01-01"}));
+        assert_eq!(records[1], json!({"year": 1999}));
+        assert_eq!(records[2], json!({"year": ["1999-01-01", 1999, "2001"]}));
+    }
+
+    #[test]
+    fn whole_arrays_are_not_rewritten_without_exploding() {
+        let mut records = vec![json!({"tags": ["a", "b"]})];
+        let replacements = HashMap::from([("a".to_string(), "A".to_string())]);
+        let outcome = apply_merges(&mut records, "tags", false, &replacements);
+        assert!(outcome.previous.is_empty());
+        assert_eq!(records[0], json!({"tags": ["a", "b"]}));
+    }
+
+    #[test]
+    fn short_near_variants_share_a_block() {
+        let values = facet_values(&[
+            ("ger", 9),
+            ("ger ", 2),
+            ("Oxford", 5),
+            ("Oxfrd", 1),
+            ("eng", 7),
+            ("Cambridge University", 3),
+        ]);
+        let options = ClusterOptions { method: ClusterMethod::Levenshtein, ..ClusterOptions::new() };
+        let mut clusters: Vec<Vec<String>> = cluster_values(&values, &options)
+            .into_iter()
+            .map(|c| c.values.into_iter().map(|v| v.value).collect())
+            .collect();
+        clusters.sort();
+        assert_eq!(clusters, vec![vec!["Oxford", "Oxfrd"], vec!["ger", "ger "]]);
+    }
+}
@@ -0,0 +1,128 @@
+use serde_json::Value;
+use std::cmp::Ordering;
+use super::{BiblioRecord, get_display_value};
+use super::extjson::as_ext_scalar;
+use super::facets::collect_numbers;
+use super::paths::FieldPath;
+
+/// Columns shown for a file that has no saved layout.
+pub const DEFAULT_COLUMN_COUNT: usize = 5;
+
+/// A column of the Browse table: a field path, possibly nested
+/// (`publisher.place`, `authors[].name`).
+pub struct BrowseColumn {
+    pub path: String,
+    field_path: FieldPath,
+}
+
+impl BrowseColumn {
+    pub fn new(path: &str) -> BrowseColumn {
+        BrowseColumn { path: path.to_string(), field_path: FieldPath::parse(path) }
+    }
+
+    /// Cell text; several values (array elements) are joined with `; `.
+    /// `None` when the record has no value at the path.
+    pub fn display(&self, record: &BiblioRecord, max_len: usize) -> Option<String> {
+        let mut values = Vec::new();
+        for value in self.field_path.resolve(record) {
+            match value {
+                Value::Array(arr) if !arr.is_empty() => values.extend(arr),
+                _ => values.push(value),
+            }
+        }
+        match values.as_slice() {
+            [] => None,
+            [value] => Some(get_display_value(value, max_len)),
+            values => {
+                let joined = values
+                    .iter()
+                    .map(|v| get_display_value(v, max_len))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                Some(get_display_value(&Value::String(joined), max_len))
+            }
+        }
+    }
+
+    pub fn sort_key(&self, record: &BiblioRecord) -> SortKey {
+        self.field_path
+            .resolve(record)
+            .first()
+            .map(|value| SortKey::of(value))
+            .unwrap_or(SortKey::Missing)
+    }
+}
+
+/// Type-aware ordering of cell values: numbers (including numeric strings)
+/// numerically, then text case-insensitively, then booleans and nested
+/// values. Nulls and missing values always sort last.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SortKey {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Nested(String),
+    Missing,
+}
+
+impl SortKey {
+    fn of(value: &Value) -> SortKey {
+        let mut numbers = Vec::new();
+        if !value.is_array() && collect_numbers(value, &mut numbers) {
+            if let Some(&n) = numbers.first() {
+                return SortKey::Number(n);
+            }
+        }
+        if let Some(ext) = as_ext_scalar(value) {
+            return SortKey::Text(ext.text.to_lowercase());
+        }
+
+        match value {
+            Value::Null => SortKey::Missing,
+            Value::Bool(b) => SortKey::Bool(*b),
+            Value::String(s) => SortKey::Text(s.to_lowercase()),
+            // An array sorts by its first element
+            Value::Array(arr) => arr.first().map(SortKey::of).unwrap_or(SortKey::Missing),
+            _ => SortKey::Nested(get_display_value(value, 200)),
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            SortKey::Number(_) => 0,
+            SortKey::Text(_) => 1,
+            SortKey::Bool(_) => 2,
+            SortKey::Nested(_) => 3,
+            SortKey::Missing => 4,
+        }
+    }
+
+    fn cmp_same_rank(&self, other: &SortKey) -> Ordering {
+        match (self, other) {
+            (SortKey::Number(a), SortKey::Number(b)) => a.total_cmp(b),
+            (SortKey::Text(a), SortKey::Text(b)) | (SortKey::Nested(a), SortKey::Nested(b)) => a.cmp(b),
+            (SortKey::Bool(a), SortKey::Bool(b)) => a.cmp(b),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+/// Sorts record indices by the value in `column`. Ties keep their current
+/// order, and missing values stay at the end in either direction.
+pub fn sort_records(records: &[BiblioRecord], indices: &mut [usize], column: &BrowseColumn, descending: bool) {
+    let mut keyed: Vec<(SortKey, usize)> = indices
+        .iter()
+        .map(|&i| (column.sort_key(&records[i]), i))
+        .collect();
+
+    keyed.sort_by(|(a, _), (b, _)| {
+        a.rank().cmp(&b.rank()).then_with(|| {
+            let ordering = a.cmp_same_rank(b);
+            if descending { ordering.reverse() } else { ordering }
+        })
+    });
+
+    for (slot, (_, i)) in indices.iter_mut().zip(keyed) {
+        *slot = i;
+    }
+}
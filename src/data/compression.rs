@@ -0,0 +1,46 @@
+/// Container formats recognised by their leading magic bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Zip,
+}
+
+impl Compression {
+    pub fn detect(head: &[u8]) -> Compression {
+        if head.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if head.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else if head.starts_with(b"PK\x03\x04") {
+            Compression::Zip
+        } else {
+            Compression::None
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            Compression::None => "uncompressed",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Zip => "zip",
+        }
+    }
+}
+
+/// File extensions offered in the file dialog for compressed inputs.
+pub const COMPRESSED_EXTENSIONS: [&str; 4] = ["gz", "zst", "zstd", "zip"];
+
+/// Strips a trailing compression extension so `records.jsonl.gz` is still
+/// recognised as JSON Lines.
+pub fn inner_file_name(path: &str) -> &str {
+    let lower = path.to_lowercase();
+    for ext in COMPRESSED_EXTENSIONS {
+        if lower.ends_with(&format!(".{}", ext)) {
+            return &path[..path.len() - ext.len() - 1];
+        }
+    }
+    path
+}
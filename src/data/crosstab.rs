@@ -0,0 +1,207 @@
+use std::collections::{HashMap, HashSet};
+use super::{BiblioRecord, FacetMode};
+use super::facets::record_facet_values;
+use super::paths::FieldPath;
+
+/// Rows and columns beyond this many values are folded into [`OTHER`].
+pub const MAX_VALUES: usize = 50;
+/// Label for records without a value at the path.
+pub const MISSING: &str = "(missing)";
+/// Label for the least common values beyond [`MAX_VALUES`].
+pub const OTHER: &str = "(other)";
+
+/// Record counts for every combination of a row field value and a column
+/// field value. Array elements count individually, so a record with two
+/// languages contributes to two rows.
+#[derive(Debug, Clone)]
+pub struct Crosstab {
+    pub row_field: String,
+    pub col_field: String,
+    pub rows: Vec<String>,
+    pub columns: Vec<String>,
+    /// `counts[row][column]`
+    pub counts: Vec<Vec<usize>>,
+    pub row_totals: Vec<usize>,
+    pub col_totals: Vec<usize>,
+    pub record_count: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CrosstabSort {
+    Label,
+    Total,
+    /// By the count in one column (rows) or row (columns)
+    Cell(usize),
+}
+
+impl Crosstab {
+    /// Reorders rows by `sort`; column indices in `Cell` refer to the current
+    /// order. Returns the previous index of every row in its new order.
+    pub fn sort_rows(&mut self, sort: CrosstabSort, descending: bool) -> Vec<usize> {
+        let order = sorted_order(&self.rows, &self.row_totals, |i, c| self.counts[i][c], sort, descending);
+        self.rows = order.iter().map(|&i| self.rows[i].clone()).collect();
+        self.row_totals = order.iter().map(|&i| self.row_totals[i]).collect();
+        self.counts = order.iter().map(|&i| self.counts[i].clone()).collect();
+        order
+    }
+
+    /// Like [`Self::sort_rows`], for the columns.
+    pub fn sort_columns(&mut self, sort: CrosstabSort, descending: bool) -> Vec<usize> {
+        let order = sorted_order(&self.columns, &self.col_totals, |j, r| self.counts[r][j], sort, descending);
+        self.columns = order.iter().map(|&j| self.columns[j].clone()).collect();
+        self.col_totals = order.iter().map(|&j| self.col_totals[j]).collect();
+        for row in &mut self.counts {
+            *row = order.iter().map(|&j| row[j]).collect();
+        }
+        order
+    }
+}
+
+/// Whether a row or column label stands for a real value that can be
+/// filtered on.
+pub fn is_filterable(label: &str) -> bool {
+    label != MISSING && label != OTHER
+}
+
+pub fn analyze_crosstab(records: &[BiblioRecord], row_field: &str, col_field: &str) -> Crosstab {
+    let row_path = FieldPath::parse(row_field);
+    let col_path = FieldPath::parse(col_field);
+
+    let values_of = |path: &FieldPath, record: &BiblioRecord| {
+        let values = record_facet_values(path, record, FacetMode::ExplodeArrays);
+        if values.is_empty() {
+            HashSet::from([MISSING.to_string()])
+        } else {
+            values
+        }
+    };
+
+    let mut row_counts: HashMap<String, usize> = HashMap::new();
+    let mut col_counts: HashMap<String, usize> = HashMap::new();
+    for record in records {
+        for row in values_of(&row_path, record) {
+            *row_counts.entry(row).or_insert(0) += 1;
+        }
+        for col in values_of(&col_path, record) {
+            *col_counts.entry(col).or_insert(0) += 1;
+        }
+    }
+
+    let rows = top_values(row_counts);
+    let columns = top_values(col_counts);
+    let row_index = label_index(&rows);
+    let col_index = label_index(&columns);
+
+    // Values that did not make the cut land in the trailing "(other)" slot;
+    // slots are sets so each record counts once per cell
+    let slots = |values: HashSet<String>, index: &HashMap<String, usize>, len: usize| -> HashSet<usize> {
+        values.iter().map(|v| index.get(v).copied().unwrap_or(len - 1)).collect()
+    };
+
+    let mut counts = vec![vec![0; columns.len()]; rows.len()];
+    let mut row_totals = vec![0; rows.len()];
+    let mut col_totals = vec![0; columns.len()];
+    for record in records {
+        let row_slots = slots(values_of(&row_path, record), &row_index, rows.len());
+        let col_slots = slots(values_of(&col_path, record), &col_index, columns.len());
+        for &r in &row_slots {
+            row_totals[r] += 1;
+            for &c in &col_slots {
+                counts[r][c] += 1;
+            }
+        }
+        for &c in &col_slots {
+            col_totals[c] += 1;
+        }
+    }
+
+    let mut crosstab = Crosstab {
+        row_field: row_field.to_string(),
+        col_field: col_field.to_string(),
+        rows,
+        columns,
+        counts,
+        row_totals,
+        col_totals,
+        record_count: records.len(),
+    };
+    crosstab.sort_rows(CrosstabSort::Total, true);
+    crosstab.sort_columns(CrosstabSort::Total, true);
+    crosstab
+}
+
+/// The most common values, with the rest folded into [`OTHER`].
+fn top_values(counts: HashMap<String, usize>) -> Vec<String> {
+    let mut values: Vec<(String, usize)> = counts.into_iter().collect();
+    values.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let folded = values.len() > MAX_VALUES;
+    let mut labels: Vec<String> = values.into_iter().take(MAX_VALUES - usize::from(folded)).map(|(v, _)| v).collect();
+    if folded {
+        labels.push(OTHER.to_string());
+    }
+    labels
+}
+
+fn label_index(labels: &[String]) -> HashMap<String, usize> {
+    labels.iter().enumerate().filter(|(_, l)| *l != OTHER).map(|(i, l)| (l.clone(), i)).collect()
+}
+
+fn sorted_order(
+    labels: &[String],
+    totals: &[usize],
+    cell: impl Fn(usize, usize) -> usize,
+    sort: CrosstabSort,
+    descending: bool,
+) -> Vec<usize> {
+    let compare = |a: usize, b: usize| match sort {
+        CrosstabSort::Label => labels[a].cmp(&labels[b]),
+        CrosstabSort::Total => totals[a].cmp(&totals[b]),
+        CrosstabSort::Cell(other) => cell(a, other).cmp(&cell(b, other)),
+    };
+    // Descending flips the comparison, not the result, so ties keep their order
+    let mut order: Vec<usize> = (0..labels.len()).collect();
+    order.sort_by(|&a, &b| if descending { compare(b, a) } else { compare(a, b) });
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Crosstab {
+        Crosstab {
+            row_field: "lang".to_string(),
+            col_field: "year".to_string(),
+            rows: vec!["eng".into(), "ger".into(), "fre".into(), "ita".into()],
+            columns: vec!["1999".into(), "2000".into(), "2001".into()],
+            counts: vec![vec![1, 0, 4], vec![3, 2, 0], vec![1, 4, 0], vec![0, 0, 5]],
+            row_totals: vec![5, 5, 5, 5],
+            col_totals: vec![5, 6, 9],
+            record_count: 20,
+        }
+    }
+
+    #[test]
+    fn descending_sorts_keep_ties_in_order() {
+        let mut crosstab = table();
+        crosstab.sort_rows(CrosstabSort::Cell(0), true);
+        assert_eq!(crosstab.rows, vec!["ger", "eng", "fre", "ita"]);
+        crosstab.sort_rows(CrosstabSort::Total, true);
+        assert_eq!(crosstab.rows, vec!["ger", "eng", "fre", "ita"]);
+        crosstab.sort_rows(CrosstabSort::Cell(0), false);
+        assert_eq!(crosstab.rows, vec!["ita", "eng", "fre", "ger"]);
+    }
+
+    #[test]
+    fn sorting_returns_the_previous_positions() {
+        let mut crosstab = table();
+        let order = crosstab.sort_columns(CrosstabSort::Total, true);
+        assert_eq!(order, vec![2, 1, 0]);
+        assert_eq!(crosstab.columns, vec!["2001", "2000", "1999"]);
+        assert_eq!(crosstab.counts[0], vec![4, 0, 1]);
+        let order = crosstab.sort_rows(CrosstabSort::Label, false);
+        assert_eq!(order, vec![0, 2, 1, 3]);
+        assert_eq!(crosstab.row_totals, vec![5, 5, 5, 5]);
+    }
+}
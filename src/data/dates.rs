@@ -0,0 +1,354 @@
+//! Parses the date forms found in bibliographic records ("1856", "ca. 1850",
+//! "1800-1850", "18th century", "[1850?]") into year intervals.
+
+use serde_json::Value;
+use super::extjson::{ExtType, as_ext_scalar};
+
+/// How a date value was written, which says how precise its interval is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DateKind {
+    /// A full or partial ISO date such as `1999-05-03`
+    Exact,
+    Year,
+    Range,
+    /// A decade or century: `1850s`, `18th century`, `19. Jahrhundert`
+    Period,
+    /// Approximate: `circa 1800`, `ca. 1850`, `~1900`, `[1850?]`
+    Circa,
+}
+
+impl DateKind {
+    pub fn label(&self) -> &str {
+        match self {
+            DateKind::Exact => "Exact dates",
+            DateKind::Year => "Years",
+            DateKind::Range => "Ranges",
+            DateKind::Period => "Decades/centuries",
+            DateKind::Circa => "Circa",
+        }
+    }
+}
+
+/// The years a date value stands for, inclusive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DateInterval {
+    pub start: i32,
+    pub end: i32,
+    pub kind: DateKind,
+}
+
+impl DateInterval {
+    fn new(start: i32, end: i32, kind: DateKind) -> Self {
+        Self { start, end, kind }
+    }
+
+    pub fn overlaps(&self, start: i32, end: i32) -> bool {
+        self.start <= end && self.end >= start
+    }
+}
+
+/// Parses a JSON value: ISO strings and Extended JSON datetimes, whole-number
+/// years and the string forms accepted by [`parse_date_text`].
+pub fn parse_date_value(value: &Value) -> Option<DateInterval> {
+    if let Some(ext) = as_ext_scalar(value) {
+        return match ext.ext_type {
+            ExtType::DateTime | ExtType::Int32 | ExtType::Int64 => parse_date_text(&ext.text),
+            _ => None,
+        };
+    }
+
+    match value {
+        Value::Number(n) => {
+            let year = n.as_i64().filter(|y| is_year(*y))? as i32;
+            Some(DateInterval::new(year, year, DateKind::Year))
+        }
+        Value::String(s) => parse_date_text(s),
+        _ => None,
+    }
+}
+
+pub fn parse_date_text(text: &str) -> Option<DateInterval> {
+    let mut text = text.trim().to_lowercase();
+
+    // "[1850]", "(1850)", "1850." and "[1850?]" as found in catalogue records
+    let mut uncertain = false;
+    loop {
+        let stripped = text
+            .trim_start_matches(['[', '('])
+            .trim_end_matches([']', ')', '.', ','])
+            .trim();
+        let stripped = match stripped.strip_suffix('?') {
+            Some(rest) => {
+                uncertain = true;
+                rest.trim()
+            }
+            None => stripped,
+        };
+        if stripped.len() == text.len() {
+            break;
+        }
+        text = stripped.to_string();
+    }
+
+    if let Some(rest) = strip_circa(&text) {
+        return parse_plain(rest).map(|d| DateInterval::new(d.start, d.end, DateKind::Circa));
+    }
+
+    let interval = parse_plain(&text).or_else(|| embedded_year(&text))?;
+    if uncertain {
+        Some(DateInterval::new(interval.start, interval.end, DateKind::Circa))
+    } else {
+        Some(interval)
+    }
+}
+
+fn strip_circa(text: &str) -> Option<&str> {
+    for prefix in ["circa", "approx.", "ca.", "ca ", "c.", "~"] {
+        if let Some(rest) = text.strip_prefix(prefix) {
+            return Some(rest.trim());
+        }
+    }
+    // "c1850"
+    text.strip_prefix('c').filter(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+fn parse_plain(text: &str) -> Option<DateInterval> {
+    if let Some(year) = parse_year(text) {
+        return Some(DateInterval::new(year, year, DateKind::Year));
+    }
+    // A year-month such as `2001-05`, never the short range 2001-2005
+    if text.len() == 7 {
+        if let Some(interval) = parse_iso(text) {
+            return Some(interval);
+        }
+    }
+    parse_range(text)
+        .or_else(|| parse_iso(text))
+        .or_else(|| parse_decade(text))
+        .or_else(|| parse_century(text))
+}
+
+fn is_year(year: i64) -> bool {
+    (1000..=2999).contains(&year)
+}
+
+fn parse_year(text: &str) -> Option<i32> {
+    if text.len() != 4 || !text.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok().filter(|y| is_year(*y as i64))
+}
+
+/// `YYYY-MM-DD...` or `YYYY-MM`.
+fn parse_iso(text: &str) -> Option<DateInterval> {
+    let mut parts = text.splitn(3, '-');
+    let year = parse_year(parts.next()?)?;
+    let month = parts.next()?;
+    if month.len() != 2 || !(1..=12).contains(&month.parse::<u32>().ok()?) {
+        return None;
+    }
+    match parts.next() {
+        Some(rest) if !rest.starts_with(|c: char| c.is_ascii_digit()) => None,
+        _ => Some(DateInterval::new(year, year, DateKind::Exact)),
+    }
+}
+
+/// `1800-1850`, `1800–1850`, `1999/2000`, `1800 to 1850`, ISO intervals and
+/// the abbreviated `1850-60`. Year-months like `1850-06` never get here.
+fn parse_range(text: &str) -> Option<DateInterval> {
+    let (from, to) = ["/", " to ", " bis ", "–", "—", "-"]
+        .iter()
+        .find_map(|sep| text.split_once(sep))?;
+    let (from, to) = (from.trim(), to.trim());
+
+    let start = parse_year(from).or_else(|| parse_iso(from).map(|d| d.start))?;
+    let end = match to.len() {
+        2 if to.chars().all(|c| c.is_ascii_digit()) => start / 100 * 100 + to.parse::<i32>().ok()?,
+        _ => parse_year(to).or_else(|| parse_iso(to).map(|d| d.start))?,
+    };
+
+    (start <= end).then(|| DateInterval::new(start, end, DateKind::Range))
+}
+
+/// `1850s` is a decade, `1800s` a century.
+fn parse_decade(text: &str) -> Option<DateInterval> {
+    let year = parse_year(text.strip_suffix("'s").or_else(|| text.strip_suffix('s'))?)?;
+    if year % 100 == 0 {
+        Some(DateInterval::new(year, year + 99, DateKind::Period))
+    } else if year % 10 == 0 {
+        Some(DateInterval::new(year, year + 9, DateKind::Period))
+    } else {
+        None
+    }
+}
+
+/// Words for "century" that may follow the ordinal, with or without a dot.
+const CENTURY_UNITS: [&str; 5] = ["century", "jahrhundert", "jh", "jhd", "jhdt"];
+
+/// `18th century`, `19. jahrhundert`, `18. jh.`, `xviii century`: an ordinal
+/// directly followed by the unit and nothing else. A century is taken as
+/// 1700-1799 for the 18th, the way catalogues usually mean it.
+fn parse_century(text: &str) -> Option<DateInterval> {
+    let mut tokens = text.split(|c: char| c.is_whitespace() || c == '-').filter(|t| !t.is_empty());
+    let (ordinal, unit) = (tokens.next()?, tokens.next()?);
+    if tokens.next().is_some() || !CENTURY_UNITS.contains(&unit.trim_end_matches('.')) {
+        return None;
+    }
+
+    let number = parse_ordinal(ordinal)?;
+    if !(1..=30).contains(&number) {
+        return None;
+    }
+
+    let start = (number - 1) * 100;
+    Some(DateInterval::new(start, start + 99, DateKind::Period))
+}
+
+/// `18`, `18.`, `18th`, `1st` or a Roman numeral such as `xviii.`.
+fn parse_ordinal(token: &str) -> Option<i32> {
+    let digits_end = token.find(|c: char| !c.is_ascii_digit()).unwrap_or(token.len());
+    if digits_end == 0 {
+        return parse_roman(token);
+    }
+    let (digits, suffix) = token.split_at(digits_end);
+    if !matches!(suffix, "" | "." | "th" | "st" | "nd" | "rd") {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+fn parse_roman(token: &str) -> Option<i32> {
+    let mut total = 0;
+    let mut previous = 0;
+    for c in token.trim_end_matches('.').chars().rev() {
+        let value = match c {
+            'i' => 1,
+            'v' => 5,
+            'x' => 10,
+            'l' => 50,
+            _ => return None,
+        };
+        if value < previous {
+            total -= value;
+        } else {
+            total += value;
+            previous = value;
+        }
+    }
+    (total > 0).then_some(total)
+}
+
+/// Falls back to a single year inside longer text, e.g. `london : murray, 1856`.
+fn embedded_year(text: &str) -> Option<DateInterval> {
+    text.split(|c: char| !c.is_ascii_digit())
+        .find_map(parse_year)
+        .map(|year| DateInterval::new(year, year, DateKind::Year))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parsed(text: &str) -> Option<(i32, i32, DateKind)> {
+        parse_date_text(text).map(|d| (d.start, d.end, d.kind))
+    }
+
+    #[test]
+    fn years_and_iso_dates() {
+        assert_eq!(parsed("1856"), Some((1856, 1856, DateKind::Year)));
+        assert_eq!(parsed(" [1856]. "), Some((1856, 1856, DateKind::Year)));
+        assert_eq!(parsed("1999-05-03"), Some((1999, 1999, DateKind::Exact)));
+        assert_eq!(parsed("1999-05-03T10:00:00Z"), Some((1999, 1999, DateKind::Exact)));
+        assert_eq!(parsed("1999-05"), Some((1999, 1999, DateKind::Exact)));
+        assert_eq!(parsed("London : Murray, 1856"), Some((1856, 1856, DateKind::Year)));
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(parsed("1800-1850"), Some((1800, 1850, DateKind::Range)));
+        assert_eq!(parsed("1800 – 1850"), Some((1800, 1850, DateKind::Range)));
+        assert_eq!(parsed("1999/2000"), Some((1999, 2000, DateKind::Range)));
+        assert_eq!(parsed("1800 to 1850"), Some((1800, 1850, DateKind::Range)));
+        assert_eq!(parsed("1800 bis 1850"), Some((1800, 1850, DateKind::Range)));
+        assert_eq!(parsed("1850-60"), Some((1850, 1860, DateKind::Range)));
+        assert_eq!(parsed("1999-05-03/2001-01-01"), Some((1999, 2001, DateKind::Range)));
+        // Months, not the abbreviated end of a range
+        for (text, year) in [("1850-06", 1850), ("2001-05", 2001), ("1910-12", 1910), ("2020-03", 2020), ("1999-01", 1999)] {
+            assert_eq!(parsed(text), Some((year, year, DateKind::Exact)), "{:?}", text);
+        }
+        assert_eq!(parsed("1910-13"), Some((1910, 1913, DateKind::Range)));
+        assert_eq!(parsed("2001-00"), Some((2001, 2001, DateKind::Year)));
+        // Backwards ranges fall back to the first year
+        assert_eq!(parsed("1850-1840"), Some((1850, 1850, DateKind::Year)));
+    }
+
+    #[test]
+    fn circa() {
+        for text in ["circa 1850", "ca. 1850", "ca 1850", "c. 1850", "c1850", "~1850", "approx. 1850", "[1850?]", "1850?"] {
+            assert_eq!(parsed(text), Some((1850, 1850, DateKind::Circa)), "{:?}", text);
+        }
+        assert_eq!(parsed("ca. 1850-1860"), Some((1850, 1860, DateKind::Circa)));
+        assert_eq!(parsed("ca. 18. Jh."), Some((1700, 1799, DateKind::Circa)));
+        assert_eq!(parsed("[18th century?]"), Some((1700, 1799, DateKind::Circa)));
+    }
+
+    #[test]
+    fn decades_and_centuries() {
+        assert_eq!(parsed("1850s"), Some((1850, 1859, DateKind::Period)));
+        assert_eq!(parsed("1850's"), Some((1850, 1859, DateKind::Period)));
+        assert_eq!(parsed("1800s"), Some((1800, 1899, DateKind::Period)));
+        for text in [
+            "18th century",
+            "18th-century",
+            "18. Jahrhundert",
+            "18. Jh.",
+            "18 Jh",
+            "18. Jhdt.",
+            "18. Jhd.",
+            "XVIII century",
+            "xviii. Jh.",
+        ] {
+            assert_eq!(parsed(text), Some((1700, 1799, DateKind::Period)), "{:?}", text);
+        }
+        assert_eq!(parsed("1st century"), Some((0, 99, DateKind::Period)));
+        assert_eq!(parsed("21st century"), Some((2000, 2099, DateKind::Period)));
+    }
+
+    #[test]
+    fn century_words_need_an_ordinal_right_before_them() {
+        for text in [
+            "jhwh",
+            "ii jhwh",
+            "18. jhwh",
+            "jh. 18",
+            "18. jh. london",
+            "late 18th century",
+            "18x century",
+            "century",
+            "0th century",
+            "99th century",
+            "18 jh jh",
+        ] {
+            assert_eq!(parsed(text), None, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn junk_is_rejected() {
+        for text in ["", "  ", "n.d.", "s.d.", "unknown", "99", "0999", "3000", "12345", "?", "[]"] {
+            assert_eq!(parsed(text), None, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn json_values() {
+        assert_eq!(parse_date_value(&json!(1856)).map(|d| d.kind), Some(DateKind::Year));
+        assert_eq!(parse_date_value(&json!(56)), None);
+        assert_eq!(parse_date_value(&json!(1856.5)), None);
+        assert_eq!(parse_date_value(&json!(true)), None);
+        let date = parse_date_value(&json!({"$date": "1999-05-03T00:00:00Z"})).unwrap();
+        assert_eq!((date.start, date.kind), (1999, DateKind::Exact));
+        assert_eq!(parse_date_value(&json!({"$oid": "5f1a"})), None);
+    }
+}
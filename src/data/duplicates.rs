@@ -0,0 +1,576 @@
+//! Duplicate record detection: identical records, records sharing a key
+//! (ISBN, ID, ...) and near duplicates of bibliographic records compared by
+//! title, author and year.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use super::{
+    BiblioRecord, IssueKind, ProgressFn, RECORDS_PER_PROGRESS_UPDATE, RecordIssue, Severity, extract_searchable_text,
+};
+use super::clustering::{fold_to_ascii, levenshtein};
+use super::paths::FieldPath;
+
+/// Blocks larger than this are compared within a sliding window of records
+/// sorted by title instead of pair by pair.
+const MAX_BLOCK: usize = 1_000;
+const WINDOW: usize = 50;
+
+const TITLE_WEIGHT: f32 = 0.6;
+const AUTHOR_WEIGHT: f32 = 0.25;
+const YEAR_WEIGHT: f32 = 0.15;
+
+/// Leading words ignored when comparing titles.
+const ARTICLES: &[&str] = &["the", "a", "an", "der", "die", "das", "le", "la", "les", "el", "il"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DuplicateMode {
+    /// Records that are equal as JSON (key order ignored)
+    Identical,
+    /// Records with the same value in the key field
+    SameKey,
+    /// Records with similar title, author and year
+    Similar,
+}
+
+impl DuplicateMode {
+    pub const ALL: [DuplicateMode; 3] = [DuplicateMode::Identical, DuplicateMode::SameKey, DuplicateMode::Similar];
+
+    pub fn label(&self) -> &str {
+        match self {
+            DuplicateMode::Identical => "Identical records",
+            DuplicateMode::SameKey => "Same key",
+            DuplicateMode::Similar => "Near duplicates",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateOptions {
+    pub mode: DuplicateMode,
+    /// Field compared in `SameKey` mode, e.g. `isbn`
+    pub key_field: String,
+    pub title_field: String,
+    pub author_field: String,
+    pub year_field: String,
+    /// Minimum similarity (0 to 1) for near duplicates
+    pub threshold: f32,
+    /// Near duplicates must share this many leading title characters
+    pub block_chars: usize,
+}
+
+impl DuplicateOptions {
+    pub fn new() -> Self {
+        Self {
+            mode: DuplicateMode::Identical,
+            key_field: String::new(),
+            title_field: String::new(),
+            author_field: String::new(),
+            year_field: String::new(),
+            threshold: 0.85,
+            block_chars: 5,
+        }
+    }
+
+    /// Default options with the key, title, author and year fields guessed
+    /// from the dataset's field paths.
+    pub fn for_fields(fields: &[String]) -> Self {
+        let guess = |candidates: &[&str]| {
+            candidates
+                .iter()
+                .find_map(|c| fields.iter().find(|f| f.eq_ignore_ascii_case(c)))
+                .cloned()
+                .unwrap_or_default()
+        };
+        Self {
+            key_field: guess(&["isbn", "isbn[]", "id", "_id", "identifier", "doi"]),
+            title_field: guess(&["title", "title_main", "name"]),
+            author_field: guess(&["authors[].name", "author", "authors", "authors[]", "creator", "creators[].name"]),
+            year_field: guess(&["year", "date", "publication_year", "issued"]),
+            ..Self::new()
+        }
+    }
+}
+
+/// Records that are probably the same, in record order.
+#[derive(Debug, Clone)]
+pub struct DuplicateCluster {
+    pub records: Vec<usize>,
+    /// Lowest similarity that links the cluster (1 for exact duplicates)
+    pub score: f32,
+    /// What the records have in common: the key value or normalized title
+    pub key: String,
+}
+
+/// Finds clusters of duplicate records, largest first.
+pub fn find_duplicates(
+    records: &[BiblioRecord],
+    options: &DuplicateOptions,
+    progress: &mut ProgressFn,
+) -> Vec<DuplicateCluster> {
+    let mut clusters = match options.mode {
+        DuplicateMode::Identical => identical(records, progress),
+        DuplicateMode::SameKey => same_key(records, &options.key_field, progress),
+        DuplicateMode::Similar => similar(records, options, progress),
+    };
+    clusters.sort_by(|a, b| b.records.len().cmp(&a.records.len()).then_with(|| a.records[0].cmp(&b.records[0])));
+    clusters
+}
+
+/// One issue for every record of a cluster but the first, pointing at the
+/// first.
+pub fn duplicate_issues(clusters: &[DuplicateCluster], options: &DuplicateOptions) -> Vec<RecordIssue> {
+    let (source, severity, path) = match options.mode {
+        DuplicateMode::Identical => ("Identical records".to_string(), Severity::Warning, None),
+        DuplicateMode::SameKey => (format!("Same {}", options.key_field), Severity::Warning, Some(options.key_field.clone())),
+        DuplicateMode::Similar => ("Near duplicates".to_string(), Severity::Info, Some(options.title_field.clone())),
+    };
+    let mut issues = Vec::new();
+    for cluster in clusters {
+        let first = cluster.records[0];
+        for &idx in &cluster.records[1..] {
+            let description = if cluster.score < 1.0 {
+                format!("Probable duplicate of record #{} (similarity {:.2})", first + 1, cluster.score)
+            } else {
+                format!("Duplicate of record #{}", first + 1)
+            };
+            issues.push(RecordIssue {
+                kind: IssueKind::Duplicate,
+                severity,
+                source: source.clone(),
+                record_index: Some(idx),
+                path: path.clone(),
+                value: Some(cluster.key.clone()).filter(|k| !k.is_empty()),
+                description,
+                suggestion: Some("Compare the records in the Duplicates tab and remove or merge one".to_string()),
+            });
+        }
+    }
+    issues
+}
+
+fn identical(records: &[BiblioRecord], progress: &mut ProgressFn) -> Vec<DuplicateCluster> {
+    let mut by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (idx, record) in records.iter().enumerate() {
+        if idx.is_multiple_of(RECORDS_PER_PROGRESS_UPDATE) && !progress(idx) {
+            return Vec::new();
+        }
+        let mut hasher = DefaultHasher::new();
+        hash_canonical(record, &mut hasher);
+        by_hash.entry(hasher.finish()).or_default().push(idx);
+    }
+
+    let mut clusters = Vec::new();
+    for candidates in by_hash.into_values().filter(|c| c.len() > 1) {
+        // Split hash collisions by comparing the records themselves
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for idx in candidates {
+            match groups.iter_mut().find(|g| records[g[0]] == records[idx]) {
+                Some(group) => group.push(idx),
+                None => groups.push(vec![idx]),
+            }
+        }
+        clusters.extend(groups.into_iter().filter(|g| g.len() > 1).map(|records| DuplicateCluster {
+            records,
+            score: 1.0,
+            key: String::new(),
+        }));
+    }
+    clusters
+}
+
+/// Hashes a value with object keys in sorted order, so records that differ
+/// only in key order hash alike.
+fn hash_canonical(value: &Value, hasher: &mut DefaultHasher) {
+    match value {
+        Value::Null => 0u8.hash(hasher),
+        Value::Bool(b) => (1u8, b).hash(hasher),
+        Value::Number(n) => (2u8, n.to_string()).hash(hasher),
+        Value::String(s) => (3u8, s).hash(hasher),
+        Value::Array(arr) => {
+            (4u8, arr.len()).hash(hasher);
+            for item in arr {
+                hash_canonical(item, hasher);
+            }
+        }
+        Value::Object(obj) => {
+            (5u8, obj.len()).hash(hasher);
+            let mut keys: Vec<&String> = obj.keys().collect();
+            keys.sort();
+            for key in keys {
+                key.hash(hasher);
+                hash_canonical(&obj[key], hasher);
+            }
+        }
+    }
+}
+
+fn same_key(records: &[BiblioRecord], key_field: &str, progress: &mut ProgressFn) -> Vec<DuplicateCluster> {
+    if key_field.is_empty() {
+        return Vec::new();
+    }
+    let path = FieldPath::parse(key_field);
+    let mut sets = DisjointSets::new(records.len());
+    let mut first_with_key: HashMap<String, usize> = HashMap::new();
+    let mut keys: HashMap<usize, String> = HashMap::new();
+
+    for (idx, record) in records.iter().enumerate() {
+        if idx.is_multiple_of(RECORDS_PER_PROGRESS_UPDATE) && !progress(idx) {
+            return Vec::new();
+        }
+        for value in path.resolve(record) {
+            let items = match value {
+                Value::Array(arr) => arr.iter().collect(),
+                _ => vec![value],
+            };
+            for item in items {
+                let key = normalize_key(&extract_searchable_text(item));
+                if key.is_empty() {
+                    continue;
+                }
+                match first_with_key.get(&key) {
+                    Some(&first) => {
+                        sets.union(first, idx, 1.0);
+                    }
+                    None => {
+                        keys.insert(idx, extract_searchable_text(item));
+                        first_with_key.insert(key, idx);
+                    }
+                }
+            }
+        }
+    }
+
+    sets.clusters(|members| members.iter().find_map(|m| keys.get(m)).cloned().unwrap_or_default())
+}
+
+/// Lowercase letters and digits only, so `978-3-16-148410-0` and
+/// `9783161484100` are the same key.
+fn normalize_key(value: &str) -> String {
+    fold_to_ascii(&value.to_lowercase()).chars().filter(|c| c.is_alphanumeric()).collect()
+}
+
+/// The comparable parts of one record.
+struct Entry {
+    idx: usize,
+    title: String,
+    author: Option<String>,
+    year: Option<i32>,
+}
+
+/// Progress counts each record twice: once when its title is read, once
+/// when its block has been compared.
+fn similar(records: &[BiblioRecord], options: &DuplicateOptions, progress: &mut ProgressFn) -> Vec<DuplicateCluster> {
+    if options.title_field.is_empty() {
+        return Vec::new();
+    }
+    let title_path = FieldPath::parse(&options.title_field);
+    let author_path = Some(&options.author_field).filter(|f| !f.is_empty()).map(|f| FieldPath::parse(f));
+    let year_path = Some(&options.year_field).filter(|f| !f.is_empty()).map(|f| FieldPath::parse(f));
+
+    let mut blocks: HashMap<String, Vec<Entry>> = HashMap::new();
+    for (idx, record) in records.iter().enumerate() {
+        if idx.is_multiple_of(RECORDS_PER_PROGRESS_UPDATE) && !progress(idx / 2) {
+            return Vec::new();
+        }
+        let Some(title) = first_text(record, &title_path).map(|t| normalize_title(&t)) else { continue };
+        let block = block_key(&title, options.block_chars);
+        if block.is_empty() {
+            continue;
+        }
+        let author = author_path.as_ref().and_then(|p| first_text(record, p)).map(|a| normalize_text(&a));
+        let year = year_path.as_ref().and_then(|p| first_text(record, p)).and_then(|y| find_year(&y));
+        blocks.entry(block).or_default().push(Entry { idx, title, author, year });
+    }
+
+    let mut sets = DisjointSets::new(records.len());
+    let mut link = |a: &Entry, b: &Entry| {
+        let score = similarity(a, b);
+        if score >= options.threshold {
+            sets.union(a.idx, b.idx, score);
+        }
+    };
+    let (mut compared, mut next_report) = (0, 0);
+    for entries in blocks.values_mut() {
+        if compared >= next_report {
+            if !progress((records.len() + compared) / 2) {
+                return Vec::new();
+            }
+            next_report = compared + RECORDS_PER_PROGRESS_UPDATE;
+        }
+        compared += entries.len();
+        if entries.len() <= MAX_BLOCK {
+            for (i, a) in entries.iter().enumerate() {
+                for b in &entries[i + 1..] {
+                    link(a, b);
+                }
+            }
+        } else {
+            entries.sort_by(|a, b| a.title.cmp(&b.title));
+            for (i, a) in entries.iter().enumerate() {
+                for b in entries.iter().skip(i + 1).take(WINDOW) {
+                    link(a, b);
+                }
+            }
+        }
+    }
+
+    let titles: HashMap<usize, String> = blocks
+        .into_values()
+        .flatten()
+        .map(|e| (e.idx, e.title))
+        .collect();
+    sets.clusters(|members| titles.get(&members[0]).cloned().unwrap_or_default())
+}
+
+/// Weighted similarity of title, author and year. Author and year only count
+/// when both records have them.
+fn similarity(a: &Entry, b: &Entry) -> f32 {
+    let mut score = TITLE_WEIGHT * text_similarity(&a.title, &b.title);
+    let mut weight = TITLE_WEIGHT;
+    if let (Some(x), Some(y)) = (&a.author, &b.author) {
+        score += AUTHOR_WEIGHT * text_similarity(x, y);
+        weight += AUTHOR_WEIGHT;
+    }
+    if let (Some(x), Some(y)) = (a.year, b.year) {
+        let year_score = match (x - y).abs() {
+            0 => 1.0,
+            1 => 0.5,
+            _ => 0.0,
+        };
+        score += YEAR_WEIGHT * year_score;
+        weight += YEAR_WEIGHT;
+    }
+    score / weight
+}
+
+/// 1 minus the edit distance relative to the longer string.
+fn text_similarity(a: &str, b: &str) -> f32 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f32 / longest as f32
+}
+
+/// The first non-empty value at `path`, with arrays reduced to their first
+/// element.
+fn first_text(record: &BiblioRecord, path: &FieldPath) -> Option<String> {
+    path.resolve(record)
+        .into_iter()
+        .map(|value| match value {
+            Value::Array(arr) => arr.first().map(extract_searchable_text).unwrap_or_default(),
+            _ => extract_searchable_text(value),
+        })
+        .find(|text| !text.trim().is_empty())
+}
+
+/// Lowercase ASCII words without punctuation, single-spaced.
+fn normalize_text(value: &str) -> String {
+    let cleaned: String = fold_to_ascii(&value.to_lowercase())
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    cleaned.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A normalized title without its leading article.
+fn normalize_title(value: &str) -> String {
+    let title = normalize_text(value);
+    match title.split_once(' ') {
+        Some((first, rest)) if ARTICLES.contains(&first) => rest.to_string(),
+        _ => title,
+    }
+}
+
+/// The first `chars` letters of a normalized title, spaces removed.
+fn block_key(title: &str, chars: usize) -> String {
+    title.chars().filter(|c| *c != ' ').take(chars.max(1)).collect()
+}
+
+/// The first four-digit number in the text that looks like a year.
+fn find_year(text: &str) -> Option<i32> {
+    let digits: Vec<char> = text.chars().collect();
+    digits
+        .windows(4)
+        .enumerate()
+        .filter(|(i, w)| {
+            w.iter().all(|c| c.is_ascii_digit())
+                && (*i == 0 || !digits[i - 1].is_ascii_digit())
+                && digits.get(i + 4).is_none_or(|c| !c.is_ascii_digit())
+        })
+        .find_map(|(_, w)| w.iter().collect::<String>().parse().ok().filter(|year| (1000..=2100).contains(year)))
+}
+
+/// Union-find over record indices, tracking the weakest link of each set.
+struct DisjointSets {
+    parent: Vec<usize>,
+    score: Vec<f32>,
+}
+
+impl DisjointSets {
+    fn new(len: usize) -> Self {
+        Self { parent: (0..len).collect(), score: vec![1.0; len] }
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    fn union(&mut self, a: usize, b: usize, score: f32) {
+        let (ra, rb) = (self.find(a), self.find(b));
+        let root = ra.min(rb);
+        self.score[root] = self.score[ra].min(self.score[rb]).min(score);
+        if ra != rb {
+            self.parent[ra.max(rb)] = root;
+        }
+    }
+
+    /// Sets of two or more, each labelled by `key`.
+    fn clusters(mut self, key: impl Fn(&[usize]) -> String) -> Vec<DuplicateCluster> {
+        let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+        for idx in 0..self.parent.len() {
+            let root = self.find(idx);
+            members.entry(root).or_default().push(idx);
+        }
+        members
+            .into_iter()
+            .filter(|(_, records)| records.len() > 1)
+            .map(|(root, records)| DuplicateCluster {
+                key: key(&records),
+                score: self.score[root],
+                records,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn hash(value: &Value) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hash_canonical(value, &mut hasher);
+        hasher.finish()
+    }
+
+    fn clusters(records: &[Value], options: &DuplicateOptions) -> Vec<Vec<usize>> {
+        find_duplicates(records, options, &mut |_| true).into_iter().map(|c| c.records).collect()
+    }
+
+    fn similar_options() -> DuplicateOptions {
+        DuplicateOptions {
+            mode: DuplicateMode::Similar,
+            title_field: "title".to_string(),
+            author_field: "author".to_string(),
+            year_field: "year".to_string(),
+            ..DuplicateOptions::new()
+        }
+    }
+
+    #[test]
+    fn canonical_hash_ignores_key_order_only() {
+        let a = json!({"title": "Faust", "ids": [1, 2], "meta": {"x": 1, "y": null}});
+        let b = json!({"meta": {"y": null, "x": 1}, "ids": [1, 2], "title": "Faust"});
+        assert_eq!(hash(&a), hash(&b));
+        assert_ne!(hash(&a), hash(&json!({"title": "Faust", "ids": [2, 1], "meta": {"x": 1, "y": null}})));
+        assert_ne!(hash(&json!({"n": 1})), hash(&json!({"n": "1"})));
+        assert_ne!(hash(&json!([[1], 2])), hash(&json!([1, [2]])));
+    }
+
+    #[test]
+    fn identical_records_cluster() {
+        let records = [
+            json!({"a": 1, "b": 2}),
+            json!({"c": 3}),
+            json!({"b": 2, "a": 1}),
+            json!({"c": 3}),
+            json!({"a": 1, "b": 2}),
+        ];
+        assert_eq!(clusters(&records, &DuplicateOptions::new()), vec![vec![0, 2, 4], vec![1, 3]]);
+    }
+
+    #[test]
+    fn same_key_joins_records_through_shared_keys() {
+        let options = DuplicateOptions {
+            mode: DuplicateMode::SameKey,
+            key_field: "isbn".to_string(),
+            ..DuplicateOptions::new()
+        };
+        // 0 and 2 share a normalized ISBN, 2 and 3 share another one, so all
+        // three are one cluster; blank keys never match
+        let records = [
+            json!({"isbn": "978-3-16-148410-0"}),
+            json!({"isbn": "0-19-852663-6"}),
+            json!({"isbn": ["9783161484100", "3-16-148410-X"]}),
+            json!({"isbn": "3 16 148410 x"}),
+            json!({"isbn": " "}),
+            json!({"isbn": ""}),
+        ];
+        let found = find_duplicates(&records, &options, &mut |_| true);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].records, vec![0, 2, 3]);
+        assert_eq!(found[0].key, "978-3-16-148410-0");
+    }
+
+    #[test]
+    fn near_duplicates_within_a_block() {
+        let records = [
+            json!({"title": "The Great Gatsby", "author": "Fitzgerald, F. Scott", "year": 1925}),
+            json!({"title": "Great Gatsby.", "author": "Fitzgerald, Francis Scott", "year": "1925"}),
+            json!({"title": "Great Expectations", "author": "Dickens, Charles", "year": 1861}),
+            json!({"title": "The great gatsby", "author": "Fitzgerald", "year": 1953}),
+        ];
+        let found = find_duplicates(&records, &similar_options(), &mut |_| true);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].records, vec![0, 1]);
+        assert!(found[0].score >= 0.85 && found[0].score < 1.0);
+        assert_eq!(found[0].key, "great gatsby");
+    }
+
+    #[test]
+    fn blocking_keeps_titles_with_different_starts_apart() {
+        // One letter apart, but the typo falls in the block key
+        let records = [json!({"title": "Faust"}), json!({"title": "Foust"})];
+        assert!(clusters(&records, &similar_options()).is_empty());
+        let options = DuplicateOptions { block_chars: 1, threshold: 0.75, ..similar_options() };
+        assert_eq!(clusters(&records, &options), vec![vec![0, 1]]);
+    }
+
+    #[test]
+    fn similarity_weighs_only_shared_fields() {
+        let entry = |title: &str, author: Option<&str>, year: Option<i32>| Entry {
+            idx: 0,
+            title: title.to_string(),
+            author: author.map(str::to_string),
+            year,
+        };
+        let a = entry("faust", Some("goethe"), Some(1808));
+        assert_eq!(similarity(&a, &entry("faust", None, None)), 1.0);
+        assert_eq!(similarity(&a, &entry("faust", Some("goethe"), Some(1809))), (0.6 + 0.25 + 0.15 * 0.5) / 1.0);
+        assert!(similarity(&a, &entry("faust", Some("goethe"), Some(1900))) < 0.9);
+    }
+
+    #[test]
+    fn years_are_found_in_text() {
+        assert_eq!(find_year("1925"), Some(1925));
+        assert_eq!(find_year("c. 1890-1895"), Some(1890));
+        assert_eq!(find_year("No. 0042, printed 1750"), Some(1750));
+        assert_eq!(find_year("978316148410"), None);
+        assert_eq!(find_year("n.d."), None);
+    }
+
+    #[test]
+    fn cancelled_detection_stops() {
+        let records = vec![json!({"a": 1}); 10];
+        assert!(find_duplicates(&records, &DuplicateOptions::new(), &mut |_| false).is_empty());
+    }
+}
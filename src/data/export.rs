@@ -0,0 +1,625 @@
+//! Writing the filtered records back out as JSON, NDJSON or CSV.
+//!
+//! Records are either written whole or projected onto a list of field
+//! paths. When flattening, each projected value becomes one or more flat
+//! columns: nested objects expand into dotted leaf paths
+//! (`publisher.place`), array elements collect under `[]` paths
+//! (`authors[].name`) and are then joined, exploded into extra rows or kept
+//! as arrays. Every column under an array holds one value per element (null
+//! where an element lacks it), so `authors[].name` and `authors[].role` stay
+//! aligned.
+
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use super::{BiblioRecord, ProgressFn, RECORDS_PER_PROGRESS_UPDATE};
+use super::extjson::as_ext_scalar;
+use super::paths::{FieldPath, child_key_path, element_path};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Json,
+    Ndjson,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn all() -> [ExportFormat; 3] {
+        [ExportFormat::Json, ExportFormat::Ndjson, ExportFormat::Csv]
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            ExportFormat::Json => "JSON array",
+            ExportFormat::Ndjson => "NDJSON",
+            ExportFormat::Csv => "CSV",
+        }
+    }
+
+    pub fn extension(&self) -> &str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// What happens to the several values of an array once flattened.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArrayHandling {
+    /// One cell with the values joined by the delimiter
+    Join,
+    /// One row per element; fields outside arrays repeat on every row
+    Explode,
+    /// A JSON array (written as JSON text in CSV)
+    Keep,
+}
+
+impl ArrayHandling {
+    pub fn all() -> [ArrayHandling; 3] {
+        [ArrayHandling::Join, ArrayHandling::Explode, ArrayHandling::Keep]
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            ArrayHandling::Join => "Join with delimiter",
+            ArrayHandling::Explode => "Explode to rows",
+            ArrayHandling::Keep => "Keep as arrays",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// Field paths to export; `None` for whole records
+    pub columns: Option<Vec<String>>,
+    /// Flatten nested objects and arrays into leaf columns. CSV is always
+    /// flattened.
+    pub flatten: bool,
+    pub arrays: ArrayHandling,
+    pub delimiter: String,
+}
+
+impl ExportOptions {
+    pub fn new() -> Self {
+        Self {
+            format: ExportFormat::Json,
+            columns: None,
+            flatten: false,
+            arrays: ArrayHandling::Join,
+            delimiter: "; ".to_string(),
+        }
+    }
+
+    fn flattens(&self) -> bool {
+        self.flatten || self.format == ExportFormat::Csv
+    }
+
+    /// How often the records are walked: CSV collects its header first.
+    fn passes(&self) -> usize {
+        if self.format == ExportFormat::Csv { 2 } else { 1 }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportProgress {
+    /// CSV columns are still being collected; no row is written yet
+    pub collecting_columns: bool,
+    pub records_done: usize,
+    pub total_records: usize,
+}
+
+impl ExportProgress {
+    /// Progress of the current pass in `0.0..=1.0`.
+    pub fn fraction(&self) -> f32 {
+        if self.total_records > 0 {
+            (self.records_done as f32 / self.total_records as f32).min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+pub enum ExportMessage {
+    Progress(ExportProgress),
+    /// Rows written, or why the file could not be written
+    Finished(Result<usize, String>),
+    Cancelled,
+}
+
+/// Handle to an export running on a worker thread.
+pub struct ExportJob {
+    receiver: Receiver<ExportMessage>,
+    cancel: Arc<AtomicBool>,
+    pub progress: ExportProgress,
+    pub path: String,
+    pub record_count: usize,
+}
+
+impl ExportJob {
+    /// Starts writing the records at `indices` to `path` in the background.
+    /// `notify` is called whenever a new message is available.
+    pub fn spawn(
+        path: String,
+        records: Arc<Vec<BiblioRecord>>,
+        indices: Vec<usize>,
+        options: ExportOptions,
+        notify: impl Fn() + Send + 'static,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let progress = ExportProgress {
+            collecting_columns: options.passes() > 1,
+            records_done: 0,
+            total_records: indices.len(),
+        };
+
+        let mut reporter = Reporter {
+            sender,
+            cancel: cancel.clone(),
+            notify: Box::new(notify),
+            progress: progress.clone(),
+        };
+        let job_path = path.clone();
+        let record_count = indices.len();
+
+        thread::spawn(move || {
+            let passes = options.passes();
+            let written = export_to_file(&path, &records, &indices, &options, &mut |done| {
+                reporter.records_done(done, passes)
+            });
+            let message = match written {
+                Ok(rows) => ExportMessage::Finished(Ok(rows)),
+                Err(_) if reporter.is_cancelled() => ExportMessage::Cancelled,
+                Err(e) => ExportMessage::Finished(Err(e.to_string())),
+            };
+            reporter.send(message);
+        });
+
+        Self {
+            receiver,
+            cancel,
+            progress,
+            path: job_path,
+            record_count,
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelling(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Returns the next pending message without blocking.
+    pub fn try_recv(&self) -> Option<ExportMessage> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+struct Reporter {
+    sender: Sender<ExportMessage>,
+    cancel: Arc<AtomicBool>,
+    notify: Box<dyn Fn() + Send>,
+    progress: ExportProgress,
+}
+
+impl Reporter {
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    fn send(&self, message: ExportMessage) {
+        // The app may have dropped the job already; nothing to do then.
+        if self.sender.send(message).is_ok() {
+            (self.notify)();
+        }
+    }
+
+    /// The progress callback handed to the export; `done` counts over all
+    /// passes.
+    fn records_done(&mut self, done: usize, passes: usize) -> bool {
+        let total = self.progress.total_records;
+        self.progress.collecting_columns = passes > 1 && done < total;
+        self.progress.records_done = if self.progress.collecting_columns { done } else { done - (passes - 1) * total };
+        self.send(ExportMessage::Progress(self.progress.clone()));
+        !self.is_cancelled()
+    }
+}
+
+/// A flattened record: leaf column paths with their values in record order,
+/// and whether the values came from array elements.
+type FlatRecord = Vec<(String, Vec<Value>, bool)>;
+
+/// Writes the records at `indices` to `path`. Returns the number of rows
+/// written, which exceeds the record count when arrays are exploded. The
+/// partial file is removed if writing fails or is cancelled.
+pub fn export_to_file(
+    path: &str,
+    records: &[BiblioRecord],
+    indices: &[usize],
+    options: &ExportOptions,
+    progress: &mut ProgressFn,
+) -> io::Result<usize> {
+    let written = File::create(path).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        let rows = export_records(&mut writer, records, indices, options, progress)?;
+        writer.flush()?;
+        Ok(rows)
+    });
+    if written.is_err() {
+        let _ = std::fs::remove_file(path);
+    }
+    written
+}
+
+/// Streams the records at `indices` to `writer`. `progress` is called with
+/// the number of records handled over all passes and stops the export by
+/// returning false.
+pub fn export_records(
+    writer: &mut impl Write,
+    records: &[BiblioRecord],
+    indices: &[usize],
+    options: &ExportOptions,
+    progress: &mut ProgressFn,
+) -> io::Result<usize> {
+    let paths: Option<Vec<FieldPath>> = options
+        .columns
+        .as_ref()
+        .map(|columns| columns.iter().map(|c| FieldPath::parse(c)).collect());
+    let flatten = |record| flatten_record(record, options.columns.as_deref(), paths.as_deref());
+
+    if options.format == ExportFormat::Csv {
+        // The header is every column that occurs in any record, in order of
+        // first appearance. It is collected in a pass of its own so that the
+        // flattened records never have to be held all at once.
+        let mut header: Vec<String> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        for (done, &i) in indices.iter().enumerate() {
+            report(progress, done)?;
+            for (column, _, _) in flatten(&records[i]) {
+                if !seen.contains(&column) {
+                    seen.insert(column.clone());
+                    header.push(column);
+                }
+            }
+        }
+
+        write_csv_row(writer, header.iter().cloned())?;
+        let mut rows = 0;
+        for (done, &i) in indices.iter().enumerate() {
+            report(progress, indices.len() + done)?;
+            rows += write_csv_rows(writer, &header, &flatten(&records[i]), options)?;
+        }
+        return Ok(rows);
+    }
+
+    let mut rows = 0;
+    if options.format == ExportFormat::Json {
+        writer.write_all(b"[\n")?;
+    }
+    for (done, &i) in indices.iter().enumerate() {
+        report(progress, done)?;
+        let record = &records[i];
+        let objects = match (&paths, &options.columns) {
+            _ if options.flattens() => flat_rows(&flatten(record), options),
+            (Some(paths), Some(columns)) => vec![project(record, columns, paths)],
+            _ => vec![record.clone()],
+        };
+        for object in objects {
+            if options.format == ExportFormat::Json && rows > 0 {
+                writer.write_all(b",\n")?;
+            }
+            serde_json::to_writer(&mut *writer, &object)?;
+            if options.format == ExportFormat::Ndjson {
+                writer.write_all(b"\n")?;
+            }
+            rows += 1;
+        }
+    }
+    if options.format == ExportFormat::Json {
+        writer.write_all(b"\n]\n")?;
+    }
+    Ok(rows)
+}
+
+/// Reports every so many records; an error once the export was cancelled.
+fn report(progress: &mut ProgressFn, done: usize) -> io::Result<()> {
+    if done.is_multiple_of(RECORDS_PER_PROGRESS_UPDATE) && !progress(done) {
+        return Err(io::Error::new(io::ErrorKind::Interrupted, "Export cancelled"));
+    }
+    Ok(())
+}
+
+/// An object with one key per column holding the value at that path, or an
+/// array when the path resolves to several values.
+fn project(record: &BiblioRecord, columns: &[String], paths: &[FieldPath]) -> Value {
+    let mut object = Map::new();
+    for (column, path) in columns.iter().zip(paths) {
+        let mut values = path.resolve(record);
+        let value = match values.len() {
+            0 => continue,
+            1 => values.remove(0).clone(),
+            _ => Value::Array(values.into_iter().cloned().collect()),
+        };
+        object.insert(column.clone(), value);
+    }
+    Value::Object(object)
+}
+
+/// Flattens the whole record or, with `columns`, the projected values. The
+/// projection is copied into one pruned record first so that columns under
+/// the same array are padded together; the flat columns then follow the
+/// order of `columns`.
+fn flatten_record(record: &BiblioRecord, columns: Option<&[String]>, paths: Option<&[FieldPath]>) -> FlatRecord {
+    let mut flat = Vec::new();
+    match (columns, paths) {
+        (Some(columns), Some(paths)) => {
+            let mut pruned = Value::Object(Map::new());
+            for path in paths {
+                path.copy_into(record, &mut pruned);
+            }
+            flatten_value(&pruned, "", false, &mut flat);
+            flat.sort_by_key(|(column, _, _)| columns.iter().position(|c| is_within(column, c)));
+        }
+        _ => flatten_value(record, "", false, &mut flat),
+    }
+    flat
+}
+
+/// Whether the flat column `column` is `projected` itself or nested under it.
+fn is_within(column: &str, projected: &str) -> bool {
+    match column.strip_prefix(projected) {
+        Some(rest) => rest.is_empty() || rest.starts_with('.') || rest.starts_with('['),
+        None => false,
+    }
+}
+
+fn flatten_value(value: &Value, path: &str, in_array: bool, out: &mut FlatRecord) {
+    if as_ext_scalar(value).is_none() {
+        match value {
+            Value::Object(obj) => {
+                for (key, child) in obj {
+                    flatten_value(child, &child_key_path(path, key), in_array, out);
+                }
+                return;
+            }
+            Value::Array(arr) => {
+                flatten_elements(arr, &element_path(path), out);
+                return;
+            }
+            _ => {}
+        }
+    }
+
+    match out.iter_mut().find(|(column, _, _)| column == path) {
+        Some((_, values, _)) => values.push(value.clone()),
+        None => out.push((path.to_string(), vec![value.clone()], in_array)),
+    }
+}
+
+/// Flattens each element on its own and pads every column to the element's
+/// longest one, so that position `n` of every column under the array comes
+/// from the same element.
+fn flatten_elements(arr: &[Value], elements: &str, out: &mut FlatRecord) {
+    let mut columns: FlatRecord = Vec::new();
+    let mut rows = 0;
+    for item in arr {
+        let mut element = Vec::new();
+        flatten_value(item, elements, true, &mut element);
+        let height = element.iter().map(|(_, values, _)| values.len()).max().unwrap_or(0);
+        for (column, mut values, _) in element {
+            values.resize(height, Value::Null);
+            match columns.iter_mut().find(|(c, _, _)| *c == column) {
+                Some((_, existing, _)) => {
+                    existing.resize(rows, Value::Null);
+                    existing.extend(values);
+                }
+                None => {
+                    let mut padded = vec![Value::Null; rows];
+                    padded.extend(values);
+                    columns.push((column, padded, true));
+                }
+            }
+        }
+        rows += height;
+    }
+
+    for (column, mut values, _) in columns {
+        values.resize(rows, Value::Null);
+        match out.iter_mut().find(|(c, _, _)| *c == column) {
+            Some((_, existing, _)) => existing.extend(values),
+            None => out.push((column, values, true)),
+        }
+    }
+}
+
+/// The rows of one flattened record as objects keyed by column: a single
+/// row, or with exploded arrays as many rows as the longest column has
+/// values.
+fn flat_rows(record: &FlatRecord, options: &ExportOptions) -> Vec<Value> {
+    let row_count = match options.arrays {
+        ArrayHandling::Explode => record.iter().map(|(_, values, _)| values.len()).max().unwrap_or(1).max(1),
+        _ => 1,
+    };
+
+    (0..row_count)
+        .map(|row| {
+            let mut object = Map::new();
+            for (column, values, in_array) in record {
+                if let Some(value) = cell_value(values, *in_array, row, options) {
+                    object.insert(column.clone(), value);
+                }
+            }
+            Value::Object(object)
+        })
+        .collect()
+}
+
+fn cell_value(values: &[Value], in_array: bool, row: usize, options: &ExportOptions) -> Option<Value> {
+    match (options.arrays, values) {
+        (ArrayHandling::Explode, values) if in_array => values.get(row).cloned(),
+        (_, [value]) => Some(value.clone()),
+        (ArrayHandling::Join, values) => Some(Value::String(
+            values.iter().map(scalar_text).collect::<Vec<_>>().join(&options.delimiter),
+        )),
+        (ArrayHandling::Explode, values) => values.get(row).cloned(),
+        (ArrayHandling::Keep, values) => Some(Value::Array(values.to_vec())),
+    }
+}
+
+fn scalar_text(value: &Value) -> String {
+    if let Some(ext) = as_ext_scalar(value) {
+        return ext.text;
+    }
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// The rows of one flattened record, with cells in `header` order.
+fn write_csv_rows(writer: &mut impl Write, header: &[String], record: &FlatRecord, options: &ExportOptions) -> io::Result<usize> {
+    let rows = flat_rows(record, options);
+    for row in &rows {
+        let cells = header.iter().map(|column| match row.get(column) {
+            Some(value @ (Value::Array(_) | Value::Object(_))) if as_ext_scalar(value).is_none() => value.to_string(),
+            Some(value) => scalar_text(value),
+            None => String::new(),
+        });
+        write_csv_row(writer, cells)?;
+    }
+    Ok(rows.len())
+}
+
+fn write_csv_row(writer: &mut impl Write, cells: impl Iterator<Item = String>) -> io::Result<()> {
+    writer.write_all(csv_row(cells).as_bytes())
+}
+
+/// One CSV line (RFC 4180 quoting, CRLF terminated).
+pub fn csv_row(cells: impl Iterator<Item = String>) -> String {
+    let mut line = cells
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn export(records: &[Value], options: &ExportOptions) -> String {
+        let indices: Vec<usize> = (0..records.len()).collect();
+        let mut out = Vec::new();
+        export_records(&mut out, records, &indices, options, &mut |_| true).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn options(format: ExportFormat, arrays: ArrayHandling, columns: Option<&[&str]>) -> ExportOptions {
+        ExportOptions {
+            format,
+            columns: columns.map(|c| c.iter().map(|s| s.to_string()).collect()),
+            flatten: true,
+            arrays,
+            delimiter: "; ".to_string(),
+        }
+    }
+
+    fn rows(text: &str) -> Vec<Value> {
+        text.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn exploded_siblings_stay_on_their_element_row() {
+        let records = vec![json!({"id": 1, "authors": [{"name": "A"}, {"name": "B", "role": "ed."}, {"role": "tr."}]})];
+        let exported = export(&records, &options(ExportFormat::Ndjson, ArrayHandling::Explode, None));
+        assert_eq!(
+            rows(&exported),
+            vec![
+                json!({"id": 1, "authors[].name": "A", "authors[].role": null}),
+                json!({"id": 1, "authors[].name": "B", "authors[].role": "ed."}),
+                json!({"id": 1, "authors[].name": null, "authors[].role": "tr."}),
+            ]
+        );
+    }
+
+    #[test]
+    fn joined_siblings_keep_element_positions() {
+        let records = vec![json!({"authors": [{"name": "A"}, {"name": "B", "role": "ed."}]})];
+        let exported = export(&records, &options(ExportFormat::Csv, ArrayHandling::Join, None));
+        assert_eq!(exported, "authors[].name,authors[].role\r\nA; B,; ed.\r\n");
+
+        let kept = export(&records, &options(ExportFormat::Ndjson, ArrayHandling::Keep, None));
+        assert_eq!(rows(&kept), vec![json!({"authors[].name": ["A", "B"], "authors[].role": [null, "ed."]})]);
+    }
+
+    #[test]
+    fn projected_columns_are_aligned_and_keep_their_order() {
+        let records = vec![json!({
+            "title": "T",
+            "authors": [{"role": "ed.", "name": "A"}, {"name": "B"}, {"role": "tr."}],
+        })];
+        let columns: &[&str] = &["authors[].role", "title", "authors[].name"];
+        let exported = export(&records, &options(ExportFormat::Csv, ArrayHandling::Explode, Some(columns)));
+        assert_eq!(
+            exported,
+            "authors[].role,title,authors[].name\r\ned.,T,A\r\n,T,B\r\ntr.,T,\r\n"
+        );
+    }
+
+    #[test]
+    fn nested_arrays_pad_to_the_longest_column_of_each_element() {
+        let records = vec![json!({"authors": [{"name": "A", "ids": ["x", "y"]}, {"name": "B"}]})];
+        let exported = export(&records, &options(ExportFormat::Ndjson, ArrayHandling::Explode, None));
+        assert_eq!(
+            rows(&exported),
+            vec![
+                json!({"authors[].name": "A", "authors[].ids[]": "x"}),
+                json!({"authors[].name": null, "authors[].ids[]": "y"}),
+                json!({"authors[].name": "B", "authors[].ids[]": null}),
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_header_is_the_union_of_columns_in_order_of_appearance() {
+        let records = vec![json!({"b": 1}), json!({"a": "x,y", "b": 2})];
+        let exported = export(&records, &options(ExportFormat::Csv, ArrayHandling::Join, None));
+        assert_eq!(exported, "b,a\r\n1,\r\n2,\"x,y\"\r\n");
+    }
+
+    #[test]
+    fn cancelled_export_removes_the_partial_file() {
+        let records = vec![json!({"a": 1}); 3];
+        let path = std::env::temp_dir().join(format!("biblio-export-test-{}.csv", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let options = options(ExportFormat::Csv, ArrayHandling::Join, None);
+
+        let error = export_to_file(&path, &records, &[0, 1, 2], &options, &mut |_| false).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Interrupted);
+        assert!(!std::path::Path::new(&path).exists());
+
+        assert_eq!(export_to_file(&path, &records, &[0, 2], &options, &mut |_| true).unwrap(), 2);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\r\n1\r\n1\r\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+use chrono::{DateTime, SecondsFormat};
+use serde_json::{Map, Value};
+
+/// BSON types that MongoDB Extended JSON wraps in `{"$...": ...}` objects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExtType {
+    ObjectId,
+    DateTime,
+    Int32,
+    Int64,
+    Double,
+    Decimal,
+    Binary,
+    Uuid,
+    Timestamp,
+    Regex,
+    Symbol,
+    MinKey,
+    MaxKey,
+    Undefined,
+}
+
+impl ExtType {
+    pub fn name(&self) -> &str {
+        match self {
+            ExtType::ObjectId => "objectId",
+            ExtType::DateTime => "datetime",
+            ExtType::Int32 => "int32",
+            ExtType::Int64 => "int64",
+            ExtType::Double => "double",
+            ExtType::Decimal => "decimal",
+            ExtType::Binary => "binary",
+            ExtType::Uuid => "uuid",
+            ExtType::Timestamp => "timestamp",
+            ExtType::Regex => "regex",
+            ExtType::Symbol => "symbol",
+            ExtType::MinKey => "minKey",
+            ExtType::MaxKey => "maxKey",
+            ExtType::Undefined => "undefined",
+        }
+    }
+}
+
+/// An Extended JSON wrapper reduced to its type and a plain text rendering.
+#[derive(Debug, Clone)]
+pub struct ExtScalar {
+    pub ext_type: ExtType,
+    pub text: String,
+}
+
+impl ExtScalar {
+    fn new(ext_type: ExtType, text: impl Into<String>) -> Self {
+        Self { ext_type, text: text.into() }
+    }
+}
+
+/// Recognises canonical and relaxed Extended JSON (v2) wrappers, plus the
+/// legacy `$binary`/`$type` and `$regex`/`$options` forms still produced by
+/// older `mongoexport` versions. Returns `None` for ordinary objects.
+pub fn as_ext_scalar(value: &Value) -> Option<ExtScalar> {
+    let Value::Object(obj) = value else {
+        return None;
+    };
+    if obj.is_empty() || obj.len() > 2 || !obj.keys().all(|k| k.starts_with('$')) {
+        return None;
+    }
+
+    if obj.len() == 2 {
+        return parse_legacy_pair(obj);
+    }
+
+    let (key, inner) = obj.iter().next()?;
+    match key.as_str() {
+        "$oid" => inner.as_str().map(|s| ExtScalar::new(ExtType::ObjectId, s)),
+        "$date" => parse_date(inner).map(|s| ExtScalar::new(ExtType::DateTime, s)),
+        "$numberInt" => number_text(inner).map(|s| ExtScalar::new(ExtType::Int32, s)),
+        "$numberLong" => number_text(inner).map(|s| ExtScalar::new(ExtType::Int64, s)),
+        "$numberDouble" => number_text(inner).map(|s| ExtScalar::new(ExtType::Double, s)),
+        "$numberDecimal" => number_text(inner).map(|s| ExtScalar::new(ExtType::Decimal, s)),
+        "$uuid" => inner.as_str().map(|s| ExtScalar::new(ExtType::Uuid, s)),
+        "$symbol" => inner.as_str().map(|s| ExtScalar::new(ExtType::Symbol, s)),
+        "$binary" => {
+            let base64 = inner.get("base64").and_then(|v| v.as_str())?;
+            Some(ExtScalar::new(ExtType::Binary, base64))
+        }
+        "$timestamp" => {
+            let t = inner.get("t").and_then(|v| v.as_u64())?;
+            let i = inner.get("i").and_then(|v| v.as_u64())?;
+            Some(ExtScalar::new(ExtType::Timestamp, format!("{}:{}", t, i)))
+        }
+        "$regularExpression" => {
+            let pattern = inner.get("pattern").and_then(|v| v.as_str())?;
+            let options = inner.get("options").and_then(|v| v.as_str()).unwrap_or("");
+            Some(ExtScalar::new(ExtType::Regex, format!("/{}/{}", pattern, options)))
+        }
+        "$minKey" => Some(ExtScalar::new(ExtType::MinKey, "MinKey")),
+        "$maxKey" => Some(ExtScalar::new(ExtType::MaxKey, "MaxKey")),
+        "$undefined" => Some(ExtScalar::new(ExtType::Undefined, "undefined")),
+        _ => None,
+    }
+}
+
+fn parse_legacy_pair(obj: &Map<String, Value>) -> Option<ExtScalar> {
+    if let (Some(data), Some(_)) = (obj.get("$binary"), obj.get("$type")) {
+        return data.as_str().map(|s| ExtScalar::new(ExtType::Binary, s));
+    }
+    if let (Some(pattern), Some(options)) = (obj.get("$regex"), obj.get("$options")) {
+        let pattern = pattern.as_str()?;
+        let options = options.as_str().unwrap_or("");
+        return Some(ExtScalar::new(ExtType::Regex, format!("/{}/{}", pattern, options)));
+    }
+    None
+}
+
+/// Canonical numbers are strings (`"42"`, `"Infinity"`); relaxed output and
+/// some exporters use plain JSON numbers instead.
+fn number_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// `$date` is an ISO-8601 string in relaxed mode, `{"$numberLong": millis}` in
+/// canonical mode, and a bare millisecond number in legacy exports. All are
+/// rendered as ISO-8601 UTC.
+fn parse_date(value: &Value) -> Option<String> {
+    let millis = match value {
+        Value::String(s) => return Some(s.clone()),
+        Value::Number(n) => n.as_i64()?,
+        Value::Object(obj) => obj.get("$numberLong")?.as_str()?.parse().ok()?,
+        _ => return None,
+    };
+
+    DateTime::from_timestamp_millis(millis)
+        .map(|dt| dt.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
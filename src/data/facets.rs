@@ -1,25 +1,40 @@
 use serde_json::Value;
-use std::collections::HashMap;
-use super::{BiblioRecord, FacetAnalysis, FacetValue, extract_searchable_text};
+use std::collections::{HashMap, HashSet};
+use super::{
+    BiblioRecord, FacetAnalysis, FacetMode, FacetValue, HistogramBin, NumericFacet, TimeGranularity,
+    TimelineBucket, TimelineFacet, extract_searchable_text,
+};
+use super::dates::{DateInterval, DateKind, parse_date_value};
+use super::extjson::{ExtType, as_ext_scalar};
+use super::paths::FieldPath;
 
-pub fn analyze_field_facets(records: &[BiblioRecord], field_name: &str) -> FacetAnalysis {
+/// Counts the values found at `field_path` in each record. Counts are per
+/// record: a value appearing twice in one record's array is counted once, so
+/// percentages are relative to the records that have the field.
+pub fn analyze_field_facets(records: &[BiblioRecord], field_path: &str, mode: FacetMode) -> FacetAnalysis {
+    let path = FieldPath::parse(field_path);
     let mut value_counts: HashMap<String, usize> = HashMap::new();
     let mut null_count = 0;
     let mut total_values = 0;
+    let mut record_values: HashSet<String> = HashSet::new();
 
     for record in records {
-        if let Value::Object(obj) = record {
-            if let Some(field_value) = obj.get(field_name) {
-                total_values += 1;
-                
-                if field_value.is_null() {
-                    null_count += 1;
-                } else {
-                    // Extract text representation for counting
-                    let text = extract_field_text(field_value);
-                    *value_counts.entry(text).or_insert(0) += 1;
-                }
-            }
+        let found = path.resolve(record);
+        if found.is_empty() {
+            continue;
+        }
+        total_values += 1;
+
+        if found.iter().all(|v| v.is_null()) {
+            null_count += 1;
+            continue;
+        }
+
+        record_values.clear();
+        collect_facet_values(found, mode, &mut record_values);
+
+        for text in record_values.drain() {
+            *value_counts.entry(text).or_insert(0) += 1;
         }
     }
 
@@ -40,11 +55,17 @@ pub fn analyze_field_facets(records: &[BiblioRecord], field_name: &str) -> Facet
         })
         .collect();
 
-    // Sort by count descending
-    values.sort_by(|a, b| b.count.cmp(&a.count));
+    if mode == FacetMode::ArrayLength {
+        // Lengths read best in numeric order, non-arrays last
+        values.sort_by_key(|v| v.value.parse::<usize>().unwrap_or(usize::MAX));
+    } else {
+        // Sort by count descending
+        values.sort_by_key(|v| std::cmp::Reverse(v.count));
+    }
 
     FacetAnalysis {
-        field_name: field_name.to_string(),
+        field_name: field_path.to_string(),
+        mode,
         total_values,
         unique_values: values.len(),
         null_count,
@@ -52,7 +73,301 @@ pub fn analyze_field_facets(records: &[BiblioRecord], field_name: &str) -> Facet
     }
 }
 
-fn extract_field_text(value: &Value) -> String {
+/// Histograms never get more bars than this.
+const MAX_BINS: usize = 40;
+
+/// Min/max/mean/median, quantiles and a histogram of the numbers at
+/// `field_path`. Array elements count individually; numeric strings such as
+/// `"1984"` count as numbers.
+pub fn analyze_numeric_facet(records: &[BiblioRecord], field_path: &str) -> NumericFacet {
+    let path = FieldPath::parse(field_path);
+    let mut numbers = Vec::new();
+    let mut record_count = 0;
+    let mut non_numeric_count = 0;
+
+    for record in records {
+        let before = numbers.len();
+        for value in path.resolve(record) {
+            if !collect_numbers(value, &mut numbers) {
+                non_numeric_count += 1;
+            }
+        }
+        if numbers.len() > before {
+            record_count += 1;
+        }
+    }
+
+    numbers.sort_by(f64::total_cmp);
+    let (min, max) = match (numbers.first(), numbers.last()) {
+        (Some(&min), Some(&max)) => (min, max),
+        _ => (0.0, 0.0),
+    };
+    let mean = if numbers.is_empty() {
+        0.0
+    } else {
+        numbers.iter().sum::<f64>() / numbers.len() as f64
+    };
+
+    NumericFacet {
+        field_name: field_path.to_string(),
+        record_count,
+        value_count: numbers.len(),
+        non_numeric_count,
+        min,
+        max,
+        mean,
+        median: quantile(&numbers, 0.5),
+        quantiles: [0.05, 0.25, 0.75, 0.95].iter().map(|&q| (q, quantile(&numbers, q))).collect(),
+        integers: numbers.iter().all(|n| n.fract() == 0.0),
+        bins: histogram(&numbers, min, max),
+    }
+}
+
+/// Adds the numbers in `value` to `out`, descending into arrays. Returns
+/// false for a non-null scalar or object that is not a number.
+pub fn collect_numbers(value: &Value, out: &mut Vec<f64>) -> bool {
+    if let Some(ext) = as_ext_scalar(value) {
+        let numeric = matches!(ext.ext_type, ExtType::Int32 | ExtType::Int64 | ExtType::Double | ExtType::Decimal);
+        return match ext.text.parse::<f64>() {
+            Ok(n) if numeric && n.is_finite() => {
+                out.push(n);
+                true
+            }
+            _ => false,
+        };
+    }
+
+    match value {
+        Value::Number(n) => {
+            out.extend(n.as_f64());
+            true
+        }
+        Value::String(s) => match s.trim().parse::<f64>() {
+            Ok(n) if n.is_finite() => {
+                out.push(n);
+                true
+            }
+            _ => false,
+        },
+        Value::Array(arr) => {
+            let mut all_numeric = true;
+            for item in arr {
+                all_numeric &= collect_numbers(item, out);
+            }
+            all_numeric
+        }
+        Value::Null => true,
+        _ => false,
+    }
+}
+
+/// Shows whole numbers without a fractional part and others with at most
+/// three decimals.
+pub fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        let text = format!("{:.3}", n);
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    }
+}
+
+/// Linear interpolation between the closest ranks of sorted `numbers`.
+fn quantile(numbers: &[f64], q: f64) -> f64 {
+    if numbers.is_empty() {
+        return 0.0;
+    }
+    let pos = q * (numbers.len() - 1) as f64;
+    let lower = pos.floor() as usize;
+    let upper = pos.ceil() as usize;
+    numbers[lower] + (numbers[upper] - numbers[lower]) * (pos - lower as f64)
+}
+
+/// Integers spanning a small range get one bar per value (years, page
+/// counts); anything else gets about sqrt(n) equal-width bars.
+fn histogram(numbers: &[f64], min: f64, max: f64) -> Vec<HistogramBin> {
+    if numbers.is_empty() {
+        return Vec::new();
+    }
+
+    let integers = numbers.iter().all(|n| n.fract() == 0.0);
+    let (bin_count, width) = if integers && max - min < MAX_BINS as f64 {
+        ((max - min) as usize + 1, 1.0)
+    } else if max > min {
+        let count = ((numbers.len() as f64).sqrt().ceil() as usize).clamp(1, MAX_BINS);
+        (count, (max - min) / count as f64)
+    } else {
+        (1, 1.0)
+    };
+
+    let mut bins: Vec<HistogramBin> = (0..bin_count)
+        .map(|i| HistogramBin {
+            start: min + i as f64 * width,
+            end: min + (i + 1) as f64 * width,
+            count: 0,
+        })
+        .collect();
+    if !integers && max > min {
+        // Avoid rounding leaving the maximum just outside the last bin
+        bins[bin_count - 1].end = max;
+    }
+
+    for &n in numbers {
+        let index = (((n - min) / width) as usize).min(bin_count - 1);
+        bins[index].count += 1;
+    }
+    bins
+}
+
+/// Record counts over time for a date field. Each value (array elements
+/// individually) is parsed into a year interval; values that cannot be read
+/// as dates are listed separately.
+pub fn analyze_timeline_facet(records: &[BiblioRecord], field_path: &str, granularity: TimeGranularity) -> TimelineFacet {
+    let path = FieldPath::parse(field_path);
+    let size = granularity.years();
+    let bucket_of = |year: i32| year.div_euclid(size) * size;
+
+    let mut counts: HashMap<i32, (usize, usize)> = HashMap::new();
+    let mut kind_counts = HashMap::new();
+    let mut unparseable: HashMap<String, usize> = HashMap::new();
+    let mut record_count = 0;
+    let mut parsed_count = 0;
+    let mut parsed = Vec::new();
+    let mut unparsed = Vec::new();
+
+    for record in records {
+        parsed.clear();
+        unparsed.clear();
+        for value in path.resolve(record) {
+            collect_dates(value, &mut parsed, &mut unparsed);
+        }
+        if !parsed.is_empty() {
+            record_count += 1;
+        }
+        parsed_count += parsed.len();
+
+        for interval in &parsed {
+            *kind_counts.entry(interval.kind).or_insert(0) += 1;
+            let approximate = interval.kind == DateKind::Circa
+                || bucket_of(interval.start) != bucket_of(interval.end);
+            let bucket = counts.entry(bucket_of(interval.start + (interval.end - interval.start) / 2)).or_insert((0, 0));
+            if approximate {
+                bucket.1 += 1;
+            } else {
+                bucket.0 += 1;
+            }
+        }
+        for value in &unparsed {
+            *unparseable.entry(extract_field_text(value)).or_insert(0) += 1;
+        }
+    }
+
+    let buckets = match (counts.keys().min(), counts.keys().max()) {
+        (Some(&first), Some(&last)) => (first..=last)
+            .step_by(size as usize)
+            .map(|start| {
+                let (exact, approximate) = counts.get(&start).copied().unwrap_or((0, 0));
+                TimelineBucket { start, exact, approximate }
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut kind_counts: Vec<_> = kind_counts.into_iter().collect();
+    kind_counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+    let unparsed_total: usize = unparseable.values().sum();
+    let mut unparseable: Vec<FacetValue> = unparseable
+        .into_iter()
+        .map(|(value, count)| FacetValue {
+            value,
+            count,
+            percentage: count as f32 / unparsed_total.max(1) as f32 * 100.0,
+        })
+        .collect();
+    unparseable.sort_by_key(|v| std::cmp::Reverse(v.count));
+
+    TimelineFacet {
+        field_name: field_path.to_string(),
+        granularity,
+        record_count,
+        parsed_count,
+        kind_counts,
+        buckets,
+        unparseable,
+    }
+}
+
+/// Splits the dates in `value` (descending into arrays) into parsed
+/// intervals and values that are not dates. Nulls are skipped.
+pub fn collect_dates<'a>(value: &'a Value, parsed: &mut Vec<DateInterval>, unparsed: &mut Vec<&'a Value>) {
+    match value {
+        Value::Null => {}
+        Value::Array(arr) => {
+            for item in arr {
+                collect_dates(item, parsed, unparsed);
+            }
+        }
+        _ => match parse_date_value(value) {
+            Some(interval) => parsed.push(interval),
+            None => unparsed.push(value),
+        },
+    }
+}
+
+/// The distinct facet values a single record contributes at `path`, as used
+/// for counting. Filters built from facet choices match against this.
+pub fn record_facet_values(path: &FieldPath, record: &BiblioRecord, mode: FacetMode) -> HashSet<String> {
+    let mut values = HashSet::new();
+    collect_facet_values(path.resolve(record), mode, &mut values);
+    values
+}
+
+fn collect_facet_values(found: Vec<&Value>, mode: FacetMode, out: &mut HashSet<String>) {
+    for value in found.into_iter().filter(|v| !v.is_null()) {
+        match mode {
+            FacetMode::Values => {
+                out.insert(extract_field_text(value));
+            }
+            FacetMode::ExplodeArrays | FacetMode::Timeline => collect_exploded(value, out),
+            FacetMode::ArrayLength => {
+                out.insert(match value {
+                    Value::Array(arr) => arr.len().to_string(),
+                    _ => "(not an array)".to_string(),
+                });
+            }
+            FacetMode::Numeric => {
+                let mut numbers = Vec::new();
+                collect_numbers(value, &mut numbers);
+                out.extend(numbers.into_iter().map(format_number));
+            }
+        }
+    }
+}
+
+/// Adds each array element (recursively) as its own facet value.
+fn collect_exploded(value: &Value, out: &mut HashSet<String>) {
+    match value {
+        Value::Array(arr) if arr.is_empty() => {
+            out.insert("(empty array)".to_string());
+        }
+        Value::Array(arr) => {
+            for item in arr.iter().filter(|v| !v.is_null()) {
+                collect_exploded(item, out);
+            }
+        }
+        _ => {
+            out.insert(extract_field_text(value));
+        }
+    }
+}
+
+/// The text a single value is counted under in a facet.
+pub fn extract_field_text(value: &Value) -> String {
+    if let Some(ext) = as_ext_scalar(value) {
+        return ext.text;
+    }
+
     match value {
         Value::String(s) => s.clone(),
         Value::Number(n) => n.to_string(),
@@ -66,7 +381,7 @@ fn extract_field_text(value: &Value) -> String {
                 // Extract first few items for display
                 let items: Vec<String> = arr.iter()
                     .take(3)
-                    .map(|v| extract_searchable_text(v))
+                    .map(extract_searchable_text)
                     .collect();
                 if arr.len() > 3 {
                     format!("{} ... ({} items)", items.join(", "), arr.len())
@@ -77,4 +392,4 @@ fn extract_field_text(value: &Value) -> String {
         }
         Value::Object(_) => "(object)".to_string(),
     }
-}
\ No newline at end of file
+}
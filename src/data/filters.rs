@@ -0,0 +1,150 @@
+use super::{BiblioRecord, FacetMode, PatternType, get_value_type};
+use super::facets::{collect_dates, collect_numbers, format_number, record_facet_values};
+use super::index::{Candidates, SearchIndex};
+use super::paths::FieldPath;
+use super::hygiene::{HygieneProblem, text_problems};
+use super::patterns::classify_value;
+
+/// What a filter checks at its path.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterKind {
+    /// One of the record's facet values (computed in `mode`) equals `value`
+    FacetValue { mode: FacetMode, value: String },
+    /// One of the record's facet values is classified as `pattern`
+    Pattern { mode: FacetMode, pattern: PatternType },
+    /// One of the record's facet values has this text hygiene problem
+    Hygiene { mode: FacetMode, problem: HygieneProblem },
+    /// One of the values at the path has this JSON type
+    ValueType(String),
+    /// One of the numbers at the path lies in `min..=max`
+    NumericRange { min: f64, max: f64 },
+    /// One of the dates at the path overlaps the years `start..=end`
+    DateRange { start: i32, end: i32 },
+}
+
+/// A browse filter added from the Facets, Patterns or Schema tab.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordFilter {
+    pub path: String,
+    pub kind: FilterKind,
+}
+
+impl RecordFilter {
+    pub fn label(&self) -> String {
+        match &self.kind {
+            FilterKind::FacetValue { mode: FacetMode::ArrayLength, value } => {
+                format!("{} length = {}", self.path, value)
+            }
+            FilterKind::FacetValue { value, .. } => format!("{} = \"{}\"", self.path, value),
+            FilterKind::Pattern { pattern, .. } => format!("{} ~ {}", self.path, pattern.name()),
+            FilterKind::Hygiene { problem, .. } => format!("{} has {}", self.path, problem.name()),
+            FilterKind::ValueType(value_type) => format!("{} is {}", self.path, value_type),
+            FilterKind::NumericRange { min, max } => {
+                format!("{} in {} – {}", self.path, format_number(*min), format_number(*max))
+            }
+            FilterKind::DateRange { start, end } if start == end => format!("{} dated {}", self.path, start),
+            FilterKind::DateRange { start, end } => format!("{} dated {}–{}", self.path, start, end),
+        }
+    }
+
+    /// Facet values are looked up by their tokens. Placeholders such as
+    /// `(empty array)` or a summarized `a, b, c ... (5 items)` are not text
+    /// from the record, so those filters fall back to a full scan.
+    fn candidates(&self, index: &SearchIndex) -> Candidates {
+        match &self.kind {
+            FilterKind::FacetValue { mode: FacetMode::Values | FacetMode::ExplodeArrays, value }
+                if !is_placeholder(value) =>
+            {
+                index
+                    .lookup(Some(&self.path), &value.to_lowercase())
+                    .map(|c| Candidates { ids: c.ids, exact: false })
+                    .unwrap_or_else(Candidates::unknown)
+            }
+            _ => Candidates::unknown(),
+        }
+    }
+
+    fn matches(&self, path: &FieldPath, record: &BiblioRecord) -> bool {
+        match &self.kind {
+            FilterKind::FacetValue { mode, value } => {
+                record_facet_values(path, record, *mode).contains(value)
+            }
+            FilterKind::Pattern { mode, pattern } => {
+                record_facet_values(path, record, *mode)
+                    .iter()
+                    .any(|v| classify_value(v) == *pattern)
+            }
+            FilterKind::Hygiene { mode, problem } => {
+                record_facet_values(path, record, *mode)
+                    .iter()
+                    .any(|v| text_problems(v).contains(problem))
+            }
+            FilterKind::ValueType(value_type) => {
+                path.resolve(record).into_iter().any(|v| get_value_type(v) == *value_type)
+            }
+            FilterKind::NumericRange { min, max } => {
+                let mut numbers = Vec::new();
+                for value in path.resolve(record) {
+                    collect_numbers(value, &mut numbers);
+                }
+                numbers.iter().any(|n| (*min..=*max).contains(n))
+            }
+            FilterKind::DateRange { start, end } => {
+                let mut dates = Vec::new();
+                let mut unparsed = Vec::new();
+                for value in path.resolve(record) {
+                    collect_dates(value, &mut dates, &mut unparsed);
+                }
+                dates.iter().any(|d| d.overlaps(*start, *end))
+            }
+        }
+    }
+}
+
+/// Filters grouped by field: a record must match at least one filter of every
+/// field (OR within a field, AND across fields), like OpenRefine facets.
+pub struct FilterSet<'a> {
+    groups: Vec<(FieldPath, Vec<&'a RecordFilter>)>,
+}
+
+impl<'a> FilterSet<'a> {
+    pub fn new(filters: &'a [RecordFilter]) -> Self {
+        let mut groups: Vec<(&str, FieldPath, Vec<&RecordFilter>)> = Vec::new();
+        for filter in filters {
+            match groups.iter_mut().find(|(path, _, _)| *path == filter.path) {
+                Some((_, _, group)) => group.push(filter),
+                None => groups.push((&filter.path, FieldPath::parse(&filter.path), vec![filter])),
+            }
+        }
+
+        Self {
+            groups: groups.into_iter().map(|(_, path, group)| (path, group)).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// The records the index says may pass every group of filters.
+    pub fn candidates(&self, index: &SearchIndex) -> Candidates {
+        self.groups.iter().fold(Candidates::everything(), |acc, (_, group)| {
+            let group_candidates = group
+                .iter()
+                .map(|filter| filter.candidates(index))
+                .reduce(Candidates::or)
+                .unwrap_or_else(Candidates::unknown);
+            acc.and(group_candidates)
+        })
+    }
+
+    pub fn matches(&self, record: &BiblioRecord) -> bool {
+        self.groups.iter().all(|(path, group)| {
+            group.iter().any(|filter| filter.matches(path, record))
+        })
+    }
+}
+
+fn is_placeholder(value: &str) -> bool {
+    (value.starts_with('(') && value.ends_with(')')) || value.ends_with(" items)")
+}
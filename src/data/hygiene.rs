@@ -0,0 +1,390 @@
+//! Text hygiene: whitespace, Unicode and encoding problems that make values
+//! look equal on screen but differ as data ("ger " vs "ger", NFD vs NFC
+//! diacritics, "MÃ¼nchen" for "München").
+
+use serde_json::Value;
+use unicode_normalization::{UnicodeNormalization, is_nfc};
+use super::{BiblioRecord, FacetValue, IssueKind, ProgressFn, RECORDS_PER_PROGRESS_UPDATE, RecordIssue, Severity};
+use super::paths::{child_key_path, element_path};
+
+/// Hygiene issues beyond this many are dropped (and reported once).
+const MAX_HYGIENE_ISSUES: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HygieneProblem {
+    SurroundingWhitespace,
+    InternalWhitespace,
+    UnusualSpace,
+    NotNfc,
+    ControlCharacter,
+    Mojibake,
+    ReplacementCharacter,
+}
+
+impl HygieneProblem {
+    pub fn name(&self) -> &str {
+        match self {
+            HygieneProblem::SurroundingWhitespace => "Leading/Trailing Whitespace",
+            HygieneProblem::InternalWhitespace => "Doubled Spaces, Tabs or Line Breaks",
+            HygieneProblem::UnusualSpace => "Non-breaking or Zero-width Spaces",
+            HygieneProblem::NotNfc => "Decomposed Diacritics (not NFC)",
+            HygieneProblem::ControlCharacter => "Control Characters",
+            HygieneProblem::Mojibake => "Mojibake (UTF-8 read as Latin-1)",
+            HygieneProblem::ReplacementCharacter => "Replacement Character (�)",
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        match self {
+            HygieneProblem::SurroundingWhitespace => "Spaces before or after the text: 'ger ' faceted apart from 'ger'",
+            HygieneProblem::InternalWhitespace => "Two or more spaces, tabs or line breaks inside the text",
+            HygieneProblem::UnusualSpace => "U+00A0 no-break space, U+200B zero-width space, BOM and similar",
+            HygieneProblem::NotNfc => "Letters and combining accents stored separately (NFD), unequal to the composed form",
+            HygieneProblem::ControlCharacter => "Invisible control characters other than tab and line break",
+            HygieneProblem::Mojibake => "UTF-8 bytes decoded as Latin-1/Windows-1252: 'MÃ¼nchen' for 'München'",
+            HygieneProblem::ReplacementCharacter => "U+FFFD left by an earlier failed decode; the original character is lost",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            HygieneProblem::Mojibake | HygieneProblem::ReplacementCharacter => Severity::Error,
+            HygieneProblem::SurroundingWhitespace
+            | HygieneProblem::UnusualSpace
+            | HygieneProblem::NotNfc
+            | HygieneProblem::ControlCharacter => Severity::Warning,
+            HygieneProblem::InternalWhitespace => Severity::Info,
+        }
+    }
+}
+
+/// Values of a facet with one hygiene problem.
+#[derive(Debug, Clone)]
+pub struct HygieneGroup {
+    pub problem: HygieneProblem,
+    pub count: usize,
+    pub percentage: f32,
+    /// Up to five affected values, with invisible characters revealed
+    pub examples: Vec<String>,
+}
+
+/// Every hygiene problem of one string.
+pub fn text_problems(text: &str) -> Vec<HygieneProblem> {
+    let mut problems = Vec::new();
+    if text.is_empty() {
+        return problems;
+    }
+    if text.trim() != text {
+        problems.push(HygieneProblem::SurroundingWhitespace);
+    }
+    let inner = text.trim();
+    if inner.contains("  ") || inner.contains(['\t', '\n', '\r']) {
+        problems.push(HygieneProblem::InternalWhitespace);
+    }
+    if text.chars().any(is_bad_control) {
+        problems.push(HygieneProblem::ControlCharacter);
+    }
+    if text.is_ascii() {
+        return problems;
+    }
+    if text.chars().any(is_unusual_space) {
+        problems.push(HygieneProblem::UnusualSpace);
+    }
+    if !is_nfc(text) {
+        problems.push(HygieneProblem::NotNfc);
+    }
+    if repair_mojibake(text).is_some() {
+        problems.push(HygieneProblem::Mojibake);
+    }
+    if text.contains('\u{FFFD}') {
+        problems.push(HygieneProblem::ReplacementCharacter);
+    }
+    problems
+}
+
+/// The text with every repairable problem fixed: mojibake decoded, NFC,
+/// unusual spaces and control characters replaced, whitespace collapsed and
+/// trimmed.
+pub fn clean_text(text: &str) -> String {
+    let repaired = repair_mojibake(text).unwrap_or_else(|| text.to_string());
+    let spaced: String = repaired
+        .nfc()
+        .filter_map(|c| match c {
+            '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}' => None,
+            c if is_unusual_space(c) || c.is_whitespace() => Some(' '),
+            c if is_bad_control(c) => None,
+            c => Some(c),
+        })
+        .collect();
+    spaced.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The text with invisible characters made visible: surrounding spaces as
+/// `␣`, tabs and line breaks as `\t` `\n`, other spaces and controls as
+/// `\u{XXXX}`.
+pub fn reveal(text: &str) -> String {
+    let leading = text.len() - text.trim_start_matches(' ').len();
+    let trailing = text.len() - text.trim_end_matches(' ').len();
+    let mut out = String::with_capacity(text.len() + 8);
+    for (i, c) in text.char_indices() {
+        match c {
+            ' ' if i < leading || i >= text.len() - trailing => out.push('␣'),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c if is_unusual_space(c) || is_bad_control(c) => out.push_str(&format!("\\u{{{:04X}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// One issue per problem per string value, anywhere in the records.
+pub fn check_hygiene(records: &[BiblioRecord], progress: &mut ProgressFn) -> Vec<RecordIssue> {
+    let mut issues = Vec::new();
+    for (idx, record) in records.iter().enumerate() {
+        if idx.is_multiple_of(RECORDS_PER_PROGRESS_UPDATE) && !progress(idx) {
+            break;
+        }
+        walk(record, String::new(), idx, &mut issues);
+        if issues.len() >= MAX_HYGIENE_ISSUES {
+            issues.truncate(MAX_HYGIENE_ISSUES);
+            issues.push(RecordIssue {
+                kind: IssueKind::Hygiene,
+                severity: Severity::Info,
+                source: "Hygiene check limited".to_string(),
+                record_index: None,
+                path: None,
+                value: None,
+                description: format!("Stopped after {} text hygiene issues (at record #{})", MAX_HYGIENE_ISSUES, idx + 1),
+                suggestion: Some("Use the Patterns tab to see hygiene problems per field".to_string()),
+            });
+            break;
+        }
+    }
+    issues
+}
+
+fn walk(value: &Value, path: String, idx: usize, issues: &mut Vec<RecordIssue>) {
+    match value {
+        Value::String(text) => {
+            for problem in text_problems(text) {
+                let suggestion = match problem {
+                    HygieneProblem::ReplacementCharacter => "Recover the text from the source record".to_string(),
+                    _ => format!("Change to \"{}\"", clean_text(text)),
+                };
+                issues.push(RecordIssue {
+                    kind: IssueKind::Hygiene,
+                    severity: problem.severity(),
+                    source: problem.name().to_string(),
+                    record_index: Some(idx),
+                    path: Some(path.clone()).filter(|p| !p.is_empty()),
+                    value: Some(format!("\"{}\"", reveal(&text.chars().take(120).collect::<String>()))),
+                    description: format!("{} in {}", problem.name(), if path.is_empty() { "record" } else { &path }),
+                    suggestion: Some(suggestion),
+                });
+            }
+        }
+        Value::Array(arr) => {
+            let item_path = element_path(&path);
+            for item in arr {
+                walk(item, item_path.clone(), idx, issues);
+            }
+        }
+        Value::Object(obj) => {
+            for (key, child) in obj {
+                walk(child, child_key_path(&path, key), idx, issues);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Hygiene problems among the values of a facet, most common first. A value
+/// can have several problems, so the groups overlap.
+pub fn hygiene_groups(values: &[FacetValue], total_values: usize) -> Vec<HygieneGroup> {
+    let mut groups: Vec<HygieneGroup> = Vec::new();
+    for value in values {
+        for problem in text_problems(&value.value) {
+            let group = match groups.iter_mut().position(|g| g.problem == problem) {
+                Some(i) => &mut groups[i],
+                None => {
+                    groups.push(HygieneGroup { problem, count: 0, percentage: 0.0, examples: Vec::new() });
+                    groups.last_mut().expect("just pushed")
+                }
+            };
+            group.count += value.count;
+            if group.examples.len() < 5 {
+                group.examples.push(reveal(&value.value));
+            }
+        }
+    }
+    for group in &mut groups {
+        group.percentage = if total_values > 0 {
+            group.count as f32 / total_values as f32 * 100.0
+        } else {
+            0.0
+        };
+    }
+    groups.sort_by_key(|g| std::cmp::Reverse(g.count));
+    groups
+}
+
+fn is_unusual_space(c: char) -> bool {
+    matches!(c,
+        '\u{00A0}' | '\u{1680}' | '\u{180E}' | '\u{2000}'..='\u{200D}'
+        | '\u{202F}' | '\u{205F}' | '\u{2060}' | '\u{3000}' | '\u{FEFF}')
+}
+
+fn is_bad_control(c: char) -> bool {
+    c.is_control() && !matches!(c, '\t' | '\n' | '\r')
+}
+
+/// Reverses UTF-8 text that was decoded as Windows-1252 (or Latin-1): maps
+/// every character back to its byte and decodes the bytes as UTF-8. `None`
+/// unless that succeeds and changes the text.
+///
+/// Pairs like "Â©" or "Â±" decode too but are as likely to be meant as
+/// written, so a repair also needs a decoded letter ("Ã¼" for "ü") or a
+/// Windows-1252 character outside Latin-1 ("â€™" for "’").
+fn repair_mojibake(text: &str) -> Option<String> {
+    if text.is_ascii() {
+        return None;
+    }
+    let bytes = text.chars().map(windows_1252_byte).collect::<Option<Vec<u8>>>()?;
+    let repaired = String::from_utf8(bytes).ok()?;
+    let telling = text.chars().any(|c| c > '\u{00FF}' || ('\u{0080}'..='\u{009F}').contains(&c))
+        || repaired.chars().any(|c| !c.is_ascii() && c.is_alphabetic());
+    (telling && repaired != text).then_some(repaired)
+}
+
+fn windows_1252_byte(c: char) -> Option<u8> {
+    let byte = match c {
+        '\u{0000}'..='\u{00FF}' => c as u8,
+        '€' => 0x80,
+        '‚' => 0x82,
+        'ƒ' => 0x83,
+        '„' => 0x84,
+        '…' => 0x85,
+        '†' => 0x86,
+        '‡' => 0x87,
+        'ˆ' => 0x88,
+        '‰' => 0x89,
+        'Š' => 0x8A,
+        '‹' => 0x8B,
+        'Œ' => 0x8C,
+        'Ž' => 0x8E,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '˜' => 0x98,
+        '™' => 0x99,
+        'š' => 0x9A,
+        '›' => 0x9B,
+        'œ' => 0x9C,
+        'ž' => 0x9E,
+        'Ÿ' => 0x9F,
+        _ => return None,
+    };
+    Some(byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn repairs_real_mojibake() {
+        let cases = [
+            ("MÃ¼nchen", "München"),
+            ("Ã©tude", "étude"),
+            ("Itâ€™s", "It’s"),
+            ("ZÃ¼rich â€“ GenÃ¨ve", "Zürich – Genève"),
+            ("FranÃ§ais", "Français"),
+        ];
+        for (broken, fixed) in cases {
+            assert_eq!(repair_mojibake(broken).as_deref(), Some(fixed), "{:?}", broken);
+            assert_eq!(text_problems(broken), vec![HygieneProblem::Mojibake], "{:?}", broken);
+            assert_eq!(clean_text(broken), fixed);
+        }
+    }
+
+    #[test]
+    fn correct_non_ascii_text_is_not_flagged() {
+        for text in ["Â©", "Â© 2001", "Ã", "São Paulo", "SÃO PAULO", "Café", "naïve", "Москва", "東京", "½ × ±", "’"] {
+            assert_eq!(repair_mojibake(text), None, "{:?}", text);
+            assert!(text_problems(text).is_empty(), "{:?}: {:?}", text, text_problems(text));
+            assert_eq!(clean_text(text), text);
+        }
+        // Decodes to a letter, so it is taken for mojibake
+        assert_eq!(repair_mojibake("Ã±").as_deref(), Some("ñ"));
+    }
+
+    #[test]
+    fn decomposed_diacritics_are_normalized() {
+        let nfd = "Mu\u{0308}nchen";
+        assert_eq!(text_problems(nfd), vec![HygieneProblem::NotNfc]);
+        assert_eq!(clean_text(nfd), "München");
+        assert!(text_problems("München").is_empty());
+    }
+
+    #[test]
+    fn whitespace_and_invisible_characters() {
+        assert_eq!(text_problems(" ger "), vec![HygieneProblem::SurroundingWhitespace]);
+        assert_eq!(text_problems("a  b"), vec![HygieneProblem::InternalWhitespace]);
+        assert_eq!(text_problems("a\tb"), vec![HygieneProblem::InternalWhitespace]);
+        assert_eq!(text_problems("a\u{00A0}b"), vec![HygieneProblem::UnusualSpace]);
+        assert_eq!(text_problems("\u{FEFF}title"), vec![HygieneProblem::UnusualSpace]);
+        assert_eq!(text_problems("a\u{0007}b"), vec![HygieneProblem::ControlCharacter]);
+        assert_eq!(text_problems("M\u{FFFD}nchen"), vec![HygieneProblem::ReplacementCharacter]);
+        assert_eq!(
+            text_problems(" a\u{200B}  b"),
+            vec![HygieneProblem::SurroundingWhitespace, HygieneProblem::InternalWhitespace, HygieneProblem::UnusualSpace]
+        );
+        assert!(text_problems("").is_empty());
+        assert!(text_problems("plain text").is_empty());
+    }
+
+    #[test]
+    fn clean_text_collapses_and_drops_invisible_characters() {
+        assert_eq!(clean_text(" a\u{00A0} b\u{200B}c\t\nd "), "a bc d");
+        assert_eq!(clean_text("\u{FEFF}Title\u{0007}"), "Title");
+        assert_eq!(clean_text("  MÃ¼nchen  "), "München");
+        assert_eq!(clean_text("M\u{FFFD}nchen"), "M\u{FFFD}nchen");
+    }
+
+    #[test]
+    fn reveal_shows_invisible_characters() {
+        assert_eq!(reveal("  ger\t\n "), "␣␣ger\\t\\n␣");
+        assert_eq!(reveal("a b"), "a b");
+        assert_eq!(reveal("   "), "␣␣␣");
+        assert_eq!(reveal("a\u{00A0}b\u{0007}"), "a\\u{00A0}b\\u{0007}");
+        assert_eq!(reveal("\r"), "\\r");
+    }
+
+    #[test]
+    fn check_hygiene_reports_each_problem_with_its_path() {
+        let records = vec![
+            json!({"lang": "ger ", "authors": [{"name": "MÃ¼ller"}], "year": 1999}),
+            json!({"lang": "ger", "title": "Clean"}),
+        ];
+        let issues = check_hygiene(&records, &mut |_| true);
+        let found: Vec<(Option<usize>, Option<&str>, &str)> = issues
+            .iter()
+            .map(|i| (i.record_index, i.path.as_deref(), i.suggestion.as_deref().unwrap_or("")))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Some(0), Some("authors[].name"), "Change to \"Müller\""),
+                (Some(0), Some("lang"), "Change to \"ger\""),
+            ]
+        );
+        assert_eq!(issues[1].value.as_deref(), Some("\"ger␣\""));
+    }
+}
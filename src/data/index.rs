@@ -0,0 +1,478 @@
+//! Inverted token index over all records, built once after loading so that
+//! searches and facet filters only have to look at records that can match.
+//!
+//! Text is tokenized exactly as the search sees it: the lowercased
+//! searchable text split into runs of alphanumeric characters. A search
+//! string that is a single such run occurs in a record if and only if it is
+//! a substring of one of the record's tokens, so those lookups are exact;
+//! anything else yields a superset that the caller still has to verify.
+//! The tokens containing a run are found with a prefix range lookup in the
+//! sorted suffixes of the vocabulary.
+
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use super::{BiblioRecord, extract_searchable_text};
+use super::extjson::as_ext_scalar;
+use super::paths::{child_key_path, element_path};
+
+/// Token id to sorted record ids.
+type Postings = HashMap<u32, Vec<u32>>;
+
+pub struct SearchIndex {
+    record_count: usize,
+    /// Every token ever indexed, addressed by its id. Tokens that no longer
+    /// occur after an edit stay here with no postings.
+    vocabulary: Vec<String>,
+    token_ids: HashMap<String, u32>,
+    /// (token id, byte offset) of every suffix of every token, sorted by the
+    /// suffix text, so the tokens containing a string form one range
+    suffixes: Vec<(u32, u32)>,
+    /// Token to record ids, over whole records
+    all: Postings,
+    /// The same per leaf field path (`authors[].name`)
+    fields: HashMap<String, Postings>,
+}
+
+/// What the index can tell about a query or filter: the records that may
+/// match (`None` for any record) and whether exactly those records match.
+#[derive(Debug, Clone)]
+pub struct Candidates {
+    pub ids: Option<Vec<u32>>,
+    pub exact: bool,
+}
+
+impl Candidates {
+    /// Every record matches, e.g. for an empty query.
+    pub fn everything() -> Self {
+        Self { ids: None, exact: true }
+    }
+
+    /// The index cannot help; every record has to be checked.
+    pub fn unknown() -> Self {
+        Self { ids: None, exact: false }
+    }
+
+    pub fn and(self, other: Candidates) -> Candidates {
+        let ids = match (self.ids, other.ids) {
+            (Some(a), Some(b)) => Some(intersect(&a, &b)),
+            (Some(ids), None) | (None, Some(ids)) => Some(ids),
+            (None, None) => None,
+        };
+        Candidates { ids, exact: self.exact && other.exact }
+    }
+
+    pub fn or(self, other: Candidates) -> Candidates {
+        let exact = self.exact && other.exact;
+        match (self.ids, other.ids) {
+            (Some(a), Some(b)) => Candidates { ids: Some(union(&a, &b)), exact },
+            _ => Candidates { ids: None, exact },
+        }
+    }
+
+    /// Only an exact set can be complemented; anything else says nothing.
+    pub fn not(self, record_count: usize) -> Candidates {
+        if !self.exact {
+            return Candidates::unknown();
+        }
+        let ids = match self.ids {
+            None => Vec::new(),
+            Some(ids) => {
+                let mut excluded = vec![false; record_count];
+                for id in ids {
+                    excluded[id as usize] = true;
+                }
+                (0..record_count as u32).filter(|&id| !excluded[id as usize]).collect()
+            }
+        };
+        Candidates { ids: Some(ids), exact: true }
+    }
+}
+
+impl SearchIndex {
+    pub fn build(records: &[BiblioRecord]) -> SearchIndex {
+        let mut index = SearchIndex {
+            record_count: records.len(),
+            vocabulary: Vec::new(),
+            token_ids: HashMap::new(),
+            suffixes: Vec::new(),
+            all: HashMap::new(),
+            fields: HashMap::new(),
+        };
+        for (id, record) in records.iter().enumerate() {
+            for (path, token) in record_tokens(record) {
+                let token = index.intern(token);
+                push_id(index.all.entry(token).or_default(), id as u32);
+                push_id(index.fields.entry(path).or_default().entry(token).or_default(), id as u32);
+            }
+        }
+
+        let vocabulary = &index.vocabulary;
+        index.suffixes = vocabulary
+            .iter()
+            .enumerate()
+            .flat_map(|(token, text)| text.char_indices().map(move |(offset, _)| (token as u32, offset as u32)))
+            .collect();
+        index.suffixes.sort_unstable_by(|&a, &b| suffix(vocabulary, a).cmp(suffix(vocabulary, b)));
+        index
+    }
+
+    pub fn record_count(&self) -> usize {
+        self.record_count
+    }
+
+    /// Re-indexes record `id` after it was edited from `old` to `new`.
+    pub fn update_record(&mut self, id: usize, old: &BiblioRecord, new: &BiblioRecord) {
+        let id = id as u32;
+        let old_tokens = record_tokens(old);
+        let new_tokens = record_tokens(new);
+        let old_all: HashSet<&String> = old_tokens.iter().map(|(_, t)| t).collect();
+        let new_all: HashSet<&String> = new_tokens.iter().map(|(_, t)| t).collect();
+
+        for token in old_all.difference(&new_all) {
+            if let Some(&token) = self.token_ids.get(*token) {
+                remove_id(&mut self.all, token, id);
+            }
+        }
+        for token in new_all.difference(&old_all) {
+            let token = self.intern_and_index((*token).clone());
+            insert_id(self.all.entry(token).or_default(), id);
+        }
+        for (path, token) in old_tokens.difference(&new_tokens) {
+            if let (Some(postings), Some(&token)) = (self.fields.get_mut(path), self.token_ids.get(token)) {
+                remove_id(postings, token, id);
+            }
+        }
+        for (path, token) in new_tokens.difference(&old_tokens) {
+            let token = self.intern_and_index(token.clone());
+            insert_id(self.fields.entry(path.clone()).or_default().entry(token).or_default(), id);
+        }
+    }
+
+    /// Records whose searchable text contains `needle` (already lowercased),
+    /// over the whole record or, with `field`, the value at that path
+    /// including everything nested under it. `None` if the index cannot
+    /// answer, e.g. for a needle without letters or digits or a path that
+    /// was never seen.
+    pub fn lookup(&self, field: Option<&str>, needle: &str) -> Option<Candidates> {
+        let runs: Vec<&str> = tokenize(needle).collect();
+        if runs.is_empty() {
+            return None;
+        }
+        let exact = runs.len() == 1 && runs[0] == needle;
+
+        let maps: Vec<&Postings> = match field {
+            None => vec![&self.all],
+            Some(path) => {
+                let maps: Vec<&Postings> = self
+                    .fields
+                    .iter()
+                    .filter(|(leaf, _)| is_within(leaf, path))
+                    .map(|(_, postings)| postings)
+                    .collect();
+                if maps.is_empty() {
+                    return None;
+                }
+                maps
+            }
+        };
+
+        let mut ids: Option<Vec<u32>> = None;
+        for run in runs {
+            let mut run_ids = Vec::new();
+            for token in self.tokens_containing(run) {
+                for postings in &maps {
+                    if let Some(token_ids) = postings.get(&token) {
+                        run_ids.extend_from_slice(token_ids);
+                    }
+                }
+            }
+            run_ids.sort_unstable();
+            run_ids.dedup();
+
+            let run_ids = match ids {
+                None => run_ids,
+                Some(previous) => intersect(&previous, &run_ids),
+            };
+            let done = run_ids.is_empty();
+            ids = Some(run_ids);
+            if done {
+                break;
+            }
+        }
+
+        Some(Candidates { ids, exact })
+    }
+
+    /// Ids of the tokens `run` is a substring of, ascending.
+    fn tokens_containing(&self, run: &str) -> Vec<u32> {
+        let start = self.suffixes.partition_point(|&s| suffix(&self.vocabulary, s) < run);
+        let mut tokens: Vec<u32> = self.suffixes[start..]
+            .iter()
+            .take_while(|&&s| suffix(&self.vocabulary, s).starts_with(run))
+            .map(|&(token, _)| token)
+            .collect();
+        tokens.sort_unstable();
+        tokens.dedup();
+        tokens
+    }
+
+    /// The id of `token`, adding it to the vocabulary if it is new. The
+    /// suffixes of new tokens are left to the caller.
+    fn intern(&mut self, token: String) -> u32 {
+        if let Some(&id) = self.token_ids.get(&token) {
+            return id;
+        }
+        let id = self.vocabulary.len() as u32;
+        self.token_ids.insert(token.clone(), id);
+        self.vocabulary.push(token);
+        id
+    }
+
+    /// Like [`Self::intern`], but also files the suffixes of a new token.
+    fn intern_and_index(&mut self, token: String) -> u32 {
+        let known = self.vocabulary.len();
+        let id = self.intern(token);
+        if id as usize >= known {
+            let offsets: Vec<u32> = self.vocabulary[id as usize].char_indices().map(|(o, _)| o as u32).collect();
+            for offset in offsets {
+                let text = suffix(&self.vocabulary, (id, offset));
+                let pos = self.suffixes.partition_point(|&s| suffix(&self.vocabulary, s) < text);
+                self.suffixes.insert(pos, (id, offset));
+            }
+        }
+        id
+    }
+}
+
+fn suffix(vocabulary: &[String], (token, offset): (u32, u32)) -> &str {
+    &vocabulary[token as usize][offset as usize..]
+}
+
+/// Whether the leaf path `leaf` is `path` itself or nested under it.
+fn is_within(leaf: &str, path: &str) -> bool {
+    match leaf.strip_prefix(path) {
+        Some(rest) => rest.is_empty() || rest.starts_with('.') || rest.starts_with('['),
+        None => false,
+    }
+}
+
+fn tokenize(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric()).filter(|t| !t.is_empty())
+}
+
+/// Distinct (leaf path, token) pairs of a record.
+fn record_tokens(record: &BiblioRecord) -> HashSet<(String, String)> {
+    let mut tokens = HashSet::new();
+    collect_tokens(record, "", &mut tokens);
+    tokens
+}
+
+fn collect_tokens(value: &Value, path: &str, out: &mut HashSet<(String, String)>) {
+    if as_ext_scalar(value).is_none() {
+        match value {
+            Value::Object(obj) => {
+                for (key, child) in obj {
+                    collect_tokens(child, &child_key_path(path, key), out);
+                }
+                return;
+            }
+            Value::Array(arr) => {
+                let elements = element_path(path);
+                for item in arr {
+                    collect_tokens(item, &elements, out);
+                }
+                return;
+            }
+            _ => {}
+        }
+    }
+
+    let text = extract_searchable_text(value).to_lowercase();
+    for token in tokenize(&text) {
+        out.insert((path.to_string(), token.to_string()));
+    }
+}
+
+fn push_id(ids: &mut Vec<u32>, id: u32) {
+    if ids.last() != Some(&id) {
+        ids.push(id);
+    }
+}
+
+fn insert_id(ids: &mut Vec<u32>, id: u32) {
+    if let Err(pos) = ids.binary_search(&id) {
+        ids.insert(pos, id);
+    }
+}
+
+fn remove_id(postings: &mut Postings, token: u32, id: u32) {
+    if let Some(ids) = postings.get_mut(&token) {
+        if let Ok(pos) = ids.binary_search(&id) {
+            ids.remove(pos);
+        }
+        if ids.is_empty() {
+            postings.remove(&token);
+        }
+    }
+}
+
+fn intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::new();
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                out.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    out
+}
+
+fn union(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::with_capacity(a.len().max(b.len()));
+    while i < a.len() || j < b.len() {
+        if j >= b.len() || (i < a.len() && a[i] < b[j]) {
+            out.push(a[i]);
+            i += 1;
+        } else if i >= a.len() || b[j] < a[i] {
+            out.push(b[j]);
+            j += 1;
+        } else {
+            out.push(a[i]);
+            i += 1;
+            j += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::query::Query;
+    use serde_json::json;
+
+    fn sample_records() -> Vec<BiblioRecord> {
+        vec![
+            json!({"title": "War and Peace", "authors": [{"name": "Tolstoy, Leo"}], "year": 1869}),
+            json!({"title": "Peace Treaties", "authors": [{"name": "Warburg, A."}], "lang": "eng"}),
+            json!({"title": "Anna Karenina", "authors": [{"name": "Tolstoy, Leo"}], "lang": "rus"}),
+            json!({"title": "Der Krieg", "subtitle": "war-time letters", "id": {"$oid": "5f1a"}}),
+            json!({"title": "Ångström units", "notes": null}),
+        ]
+    }
+
+    /// Records the search would show using the index, verifying only what it
+    /// cannot decide exactly, and whether the index was exact.
+    fn indexed(index: &SearchIndex, records: &[BiblioRecord], query: &Query) -> (Vec<usize>, bool) {
+        let candidates = query.candidates(index);
+        let ids: Vec<usize> = match candidates.ids {
+            Some(ids) => ids.into_iter().map(|i| i as usize).collect(),
+            None => (0..records.len()).collect(),
+        };
+        let found = if candidates.exact {
+            ids
+        } else {
+            ids.into_iter().filter(|&i| query.matches(&records[i])).collect()
+        };
+        (found, candidates.exact)
+    }
+
+    fn scanned(records: &[BiblioRecord], query: &Query) -> Vec<usize> {
+        (0..records.len()).filter(|&i| query.matches(&records[i])).collect()
+    }
+
+    fn assert_agrees(index: &SearchIndex, records: &[BiblioRecord], input: &str, exact: bool) {
+        let query = Query::parse(input).unwrap();
+        let (found, was_exact) = indexed(index, records, &query);
+        assert_eq!(found, scanned(records, &query), "results for {:?}", input);
+        assert_eq!(was_exact, exact, "exactness of {:?}", input);
+    }
+
+    #[test]
+    fn single_runs_are_exact_substring_lookups() {
+        let records = sample_records();
+        let index = SearchIndex::build(&records);
+        assert_eq!(index.lookup(None, "war").unwrap().ids, Some(vec![0, 1, 3]));
+        assert_eq!(index.lookup(None, "olsto").unwrap().ids, Some(vec![0, 2]));
+        assert_eq!(index.lookup(None, "ångström").unwrap().ids, Some(vec![4]));
+        assert_eq!(index.lookup(None, "zzz").unwrap().ids, Some(vec![]));
+        assert!(index.lookup(None, "war").unwrap().exact);
+        assert!(!index.lookup(None, "war and").unwrap().exact);
+        assert!(index.lookup(None, " - ").is_none());
+        assert!(index.lookup(Some("publisher"), "war").is_none());
+    }
+
+    #[test]
+    fn field_lookups_include_nested_values() {
+        let records = sample_records();
+        let index = SearchIndex::build(&records);
+        assert_eq!(index.lookup(Some("authors"), "tolstoy").unwrap().ids, Some(vec![0, 2]));
+        assert_eq!(index.lookup(Some("authors[].name"), "war").unwrap().ids, Some(vec![1]));
+        assert_eq!(index.lookup(Some("title"), "war").unwrap().ids, Some(vec![0]));
+        // `id` is an Extended JSON wrapper and indexed as its value
+        assert_eq!(index.lookup(Some("id"), "5f1a").unwrap().ids, Some(vec![3]));
+    }
+
+    #[test]
+    fn index_matches_full_scan() {
+        let records = sample_records();
+        let index = SearchIndex::build(&records);
+        let cases = [
+            ("war", true),
+            ("war tolstoy", true),
+            ("war OR karenina", true),
+            ("NOT war", true),
+            ("-tolstoy peace", true),
+            ("title:peace", true),
+            ("authors[].name:leo", true),
+            ("\"war and\"", false),
+            ("war-time", false),
+            ("\"war and\" OR anna", false),
+            ("peace NOT \"and peace\"", false),
+            ("lang=\"eng\"", false),
+            ("year>=1800", false),
+            ("has:lang", false),
+            ("/^der/i", false),
+            ("ångström", true),
+        ];
+        for (input, exact) in cases {
+            assert_agrees(&index, &records, input, exact);
+        }
+    }
+
+    #[test]
+    fn updated_records_are_found_by_their_new_tokens_only() {
+        let mut records = sample_records();
+        let mut index = SearchIndex::build(&records);
+
+        let old = records[2].clone();
+        records[2]["title"] = json!("Anna Karenina: a novel of warfare");
+        records[2]["authors"][0]["name"] = json!("Толстой, Лев");
+        index.update_record(2, &old, &records[2]);
+
+        let old = records[1].clone();
+        records[1]["authors"] = json!([]);
+        index.update_record(1, &old, &records[1]);
+
+        assert_eq!(index.lookup(None, "warf").unwrap().ids, Some(vec![2]));
+        assert_eq!(index.lookup(Some("authors"), "war").unwrap().ids, Some(vec![]));
+        assert_eq!(index.lookup(Some("authors[].name"), "толст").unwrap().ids, Some(vec![2]));
+        for (input, exact) in [
+            ("war", true),
+            ("tolstoy", true),
+            ("authors[].name:tolstoy OR title:novel", true),
+            ("warburg", true),
+            ("NOT karenina", true),
+            ("\"of warfare\" anna", false),
+        ] {
+            assert_agrees(&index, &records, input, exact);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Grouping, filtering and sorting of issues for the Issues tab.
+
+use std::collections::{HashMap, HashSet};
+use super::{IssueKind, RecordIssue, Severity};
+
+/// Order of the issue groups.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IssueSort {
+    /// Most severe first, then largest
+    Severity,
+    /// Largest first
+    Count,
+    /// Alphabetically by source
+    Source,
+}
+
+impl IssueSort {
+    pub const ALL: [IssueSort; 3] = [IssueSort::Severity, IssueSort::Count, IssueSort::Source];
+
+    pub fn label(&self) -> &str {
+        match self {
+            IssueSort::Severity => "Severity",
+            IssueSort::Count => "Count",
+            IssueSort::Source => "Source",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IssueFilter {
+    /// Severities that are shown
+    pub severities: HashSet<Severity>,
+    /// Only this kind, or every kind
+    pub kind: Option<IssueKind>,
+    /// Case-insensitive text matched against source, path, value and
+    /// description
+    pub text: String,
+}
+
+impl IssueFilter {
+    pub fn new() -> Self {
+        Self {
+            severities: Severity::ALL.into_iter().collect(),
+            kind: None,
+            text: String::new(),
+        }
+    }
+
+    fn matches(&self, issue: &RecordIssue, needle: &str) -> bool {
+        self.severities.contains(&issue.severity)
+            && self.kind.is_none_or(|kind| kind == issue.kind)
+            && (needle.is_empty()
+                || [Some(&issue.source), issue.path.as_ref(), issue.value.as_ref(), Some(&issue.description)]
+                    .into_iter()
+                    .flatten()
+                    .any(|text| text.to_lowercase().contains(needle)))
+    }
+}
+
+/// Issues of one kind from one source, e.g. every violation of one rule.
+#[derive(Debug, Clone)]
+pub struct IssueGroup {
+    pub kind: IssueKind,
+    pub source: String,
+    /// The most severe issue in the group
+    pub severity: Severity,
+    /// Indices into the issue list, in record order
+    pub issues: Vec<usize>,
+    /// Distinct records affected
+    pub record_count: usize,
+}
+
+/// Groups the issues that pass `filter` by kind and source.
+pub fn group_issues(issues: &[RecordIssue], filter: &IssueFilter, sort: IssueSort) -> Vec<IssueGroup> {
+    let needle = filter.text.trim().to_lowercase();
+    let mut groups: Vec<IssueGroup> = Vec::new();
+    let mut group_index: HashMap<(IssueKind, &str), usize> = HashMap::new();
+
+    for (i, issue) in issues.iter().enumerate() {
+        if !filter.matches(issue, &needle) {
+            continue;
+        }
+        let g = *group_index.entry((issue.kind, &issue.source)).or_insert_with(|| {
+            groups.push(IssueGroup {
+                kind: issue.kind,
+                source: issue.source.clone(),
+                severity: issue.severity,
+                issues: Vec::new(),
+                record_count: 0,
+            });
+            groups.len() - 1
+        });
+        let group = &mut groups[g];
+        group.severity = group.severity.min(issue.severity);
+        group.issues.push(i);
+    }
+
+    for group in &mut groups {
+        group.issues.sort_by_key(|&i| issues[i].record_index.unwrap_or(usize::MAX));
+        let records: HashSet<usize> = group.issues.iter().filter_map(|&i| issues[i].record_index).collect();
+        group.record_count = records.len();
+    }
+
+    match sort {
+        IssueSort::Severity => groups.sort_by(|a, b| {
+            a.severity.cmp(&b.severity).then_with(|| b.issues.len().cmp(&a.issues.len()))
+        }),
+        IssueSort::Count => groups.sort_by_key(|g| std::cmp::Reverse(g.issues.len())),
+        IssueSort::Source => groups.sort_by(|a, b| a.source.cmp(&b.source)),
+    }
+    groups
+}
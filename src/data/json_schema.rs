@@ -0,0 +1,302 @@
+//! Draft 2020-12 JSON Schema inferred from the loaded records.
+//!
+//! Every value is walked once, collecting per path the JSON types seen, how
+//! often each property is present, the distinct strings (up to a cap) and
+//! whether all strings share a format. Extended JSON wrappers such as
+//! `{"$oid": ...}` are described as the strings they stand for, with a
+//! `format` naming the wrapped type (`date-time` for `$date`).
+
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+use super::BiblioRecord;
+use super::extjson::{ExtType, as_ext_scalar};
+use super::patterns::{PatternType, classify_value};
+
+pub const SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+#[derive(Debug, Clone)]
+pub struct SchemaOptions {
+    /// A property is required when present in at least this percentage of
+    /// the objects at its path
+    pub required_coverage: f32,
+    /// Strings with at most this many distinct values become an `enum`
+    /// (0 disables enums)
+    pub max_enum_values: usize,
+    pub detect_formats: bool,
+    /// Emit `"additionalProperties": false` on every object
+    pub closed_objects: bool,
+}
+
+impl SchemaOptions {
+    pub fn new() -> Self {
+        Self {
+            required_coverage: 100.0,
+            max_enum_values: 10,
+            detect_formats: true,
+            closed_objects: false,
+        }
+    }
+}
+
+/// `format` keywords the pattern classifier can recognize, plus the types of
+/// Extended JSON wrappers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StringFormat {
+    Date,
+    DateTime,
+    Uri,
+    Email,
+    Extended(ExtType),
+}
+
+impl StringFormat {
+    /// `$date` shares `date-time` with plain ISO strings so that a field
+    /// mixing both still gets a format.
+    fn of_ext(ext_type: ExtType) -> StringFormat {
+        match ext_type {
+            ExtType::DateTime => StringFormat::DateTime,
+            other => StringFormat::Extended(other),
+        }
+    }
+
+    fn detect(value: &str) -> Option<StringFormat> {
+        match classify_value(value) {
+            PatternType::IsoDate if value.len() == 10 => {
+                chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().map(|_| StringFormat::Date)
+            }
+            PatternType::IsoDate => {
+                chrono::DateTime::parse_from_rfc3339(value).ok().map(|_| StringFormat::DateTime)
+            }
+            PatternType::UrlLike if !value.contains(char::is_whitespace) => Some(StringFormat::Uri),
+            PatternType::EmailLike => {
+                let (local, domain) = value.split_once('@')?;
+                let valid = !local.is_empty()
+                    && domain.contains('.')
+                    && !domain.contains('@')
+                    && !value.contains(char::is_whitespace);
+                valid.then_some(StringFormat::Email)
+            }
+            _ => None,
+        }
+    }
+
+    fn keyword(&self) -> &str {
+        match self {
+            StringFormat::Date => "date",
+            StringFormat::DateTime => "date-time",
+            StringFormat::Uri => "uri",
+            StringFormat::Email => "email",
+            StringFormat::Extended(ExtType::Uuid) => "uuid",
+            StringFormat::Extended(ExtType::Regex) => "regex",
+            StringFormat::Extended(other) => other.name(),
+        }
+    }
+}
+
+/// What was seen at one path.
+#[derive(Default)]
+struct Node {
+    /// Values here, including nulls; for a property, the objects having it
+    count: usize,
+    types: BTreeMap<&'static str, usize>,
+    objects: usize,
+    properties: BTreeMap<String, Node>,
+    items: Option<Box<Node>>,
+    /// Distinct strings, until there are more than the enum limit
+    strings: BTreeSet<String>,
+    too_many_strings: bool,
+    /// `None` before the first string, then the format all strings share
+    format: Option<Option<StringFormat>>,
+}
+
+impl Node {
+    fn add(&mut self, value: &Value, options: &SchemaOptions) {
+        if let Some(ext) = as_ext_scalar(value) {
+            self.add_ext(&ext.text, StringFormat::of_ext(ext.ext_type), options);
+            return;
+        }
+
+        self.count += 1;
+        let json_type = match value {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+        *self.types.entry(json_type).or_insert(0) += 1;
+
+        match value {
+            Value::Object(obj) => {
+                self.objects += 1;
+                for (key, child) in obj {
+                    self.properties.entry(key.clone()).or_default().add(child, options);
+                }
+            }
+            Value::Array(arr) => {
+                let items = self.items.get_or_insert_with(Box::default);
+                for item in arr {
+                    items.add(item, options);
+                }
+            }
+            Value::String(s) => {
+                self.remember_string(s, options);
+                self.merge_format(options.detect_formats.then(|| StringFormat::detect(s)).flatten());
+            }
+            _ => {}
+        }
+    }
+
+    /// An Extended JSON wrapper, counted as a string of a known format.
+    fn add_ext(&mut self, text: &str, format: StringFormat, options: &SchemaOptions) {
+        self.count += 1;
+        *self.types.entry("string").or_insert(0) += 1;
+        self.remember_string(text, options);
+        self.merge_format(Some(format));
+    }
+
+    /// Keeps distinct strings for an `enum` until there are too many.
+    fn remember_string(&mut self, s: &str, options: &SchemaOptions) {
+        if !self.too_many_strings {
+            self.strings.insert(s.to_string());
+            self.too_many_strings = self.strings.len() > options.max_enum_values;
+            if self.too_many_strings {
+                self.strings.clear();
+            }
+        }
+    }
+
+    /// `None` once two strings disagree on their format.
+    fn merge_format(&mut self, format: Option<StringFormat>) {
+        self.format = match self.format {
+            None => Some(format),
+            Some(previous) if previous == format => Some(previous),
+            Some(_) => Some(None),
+        };
+    }
+
+    fn to_schema(&self, options: &SchemaOptions) -> Map<String, Value> {
+        let mut schema = Map::new();
+
+        // "integer" is a subset of "number"
+        let mut types: Vec<&str> = self.types.keys().copied().collect();
+        if types.contains(&"number") {
+            types.retain(|t| *t != "integer");
+        }
+        match types.as_slice() {
+            [] => {}
+            [single] => {
+                schema.insert("type".into(), json!(single));
+            }
+            several => {
+                schema.insert("type".into(), json!(several));
+            }
+        }
+
+        if self.objects > 0 {
+            let properties: Map<String, Value> = self
+                .properties
+                .iter()
+                .map(|(key, child)| (key.clone(), Value::Object(child.to_schema(options))))
+                .collect();
+            let required: Vec<&String> = self
+                .properties
+                .iter()
+                .filter(|(_, child)| child.count as f32 >= self.objects as f32 * options.required_coverage / 100.0)
+                .map(|(key, _)| key)
+                .collect();
+
+            schema.insert("properties".into(), Value::Object(properties));
+            if !required.is_empty() {
+                schema.insert("required".into(), json!(required));
+            }
+            if options.closed_objects {
+                schema.insert("additionalProperties".into(), Value::Bool(false));
+            }
+        }
+
+        if let Some(items) = &self.items {
+            if items.count > 0 {
+                schema.insert("items".into(), Value::Object(items.to_schema(options)));
+            }
+        }
+
+        let string_count = self.types.get("string").copied().unwrap_or(0);
+        if string_count > 0 {
+            if let Some(Some(format)) = self.format {
+                schema.insert("format".into(), json!(format.keyword()));
+            }
+
+            // Only an enum when strings are the sole non-null type and values
+            // repeat; a handful of unique titles is not a vocabulary
+            let only_strings = types.iter().all(|t| *t == "string" || *t == "null");
+            let repeats = string_count > self.strings.len();
+            if options.max_enum_values > 0 && !self.too_many_strings && only_strings && repeats {
+                let mut values: Vec<Value> = self.strings.iter().map(|s| json!(s)).collect();
+                if self.types.contains_key("null") {
+                    values.push(Value::Null);
+                }
+                schema.insert("enum".into(), Value::Array(values));
+            }
+        }
+
+        schema
+    }
+}
+
+/// Infers a schema describing every record; `title` names the dataset.
+pub fn infer_json_schema(records: &[BiblioRecord], title: &str, options: &SchemaOptions) -> Value {
+    let mut root = Node::default();
+    for record in records {
+        root.add(record, options);
+    }
+
+    let mut schema = Map::new();
+    schema.insert("$schema".into(), json!(SCHEMA_DIALECT));
+    schema.insert("title".into(), json!(title));
+    schema.extend(root.to_schema(options));
+    Value::Object(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn property(schema: &Value, name: &str) -> Value {
+        schema["properties"][name].clone()
+    }
+
+    #[test]
+    fn extended_json_wrappers_become_formatted_strings() {
+        let records = vec![
+            json!({"_id": {"$oid": "5f1a2b3c4d5e6f7a8b9c0d1e"}, "added": {"$date": "2020-01-01T00:00:00Z"}, "n": {"$numberLong": "7"}}),
+            json!({"_id": {"$oid": "5f1a2b3c4d5e6f7a8b9c0d1f"}, "added": {"$date": {"$numberLong": "0"}}, "n": {"$numberLong": "8"}}),
+        ];
+        let schema = infer_json_schema(&records, "test", &SchemaOptions::new());
+        assert_eq!(property(&schema, "_id"), json!({"type": "string", "format": "objectId"}));
+        assert_eq!(property(&schema, "added"), json!({"type": "string", "format": "date-time"}));
+        assert_eq!(property(&schema, "n"), json!({"type": "string", "format": "int64"}));
+    }
+
+    #[test]
+    fn dates_share_a_format_with_plain_iso_strings() {
+        let records = vec![
+            json!({"added": {"$date": "2020-01-01T00:00:00Z"}, "id": {"$uuid": "00000000-0000-0000-0000-000000000000"}}),
+            json!({"added": "2021-06-30T12:00:00+02:00", "id": "plain"}),
+        ];
+        let schema = infer_json_schema(&records, "test", &SchemaOptions::new());
+        assert_eq!(property(&schema, "added")["format"], json!("date-time"));
+        assert_eq!(property(&schema, "id"), json!({"type": "string"}));
+    }
+
+    #[test]
+    fn ordinary_objects_keep_their_properties() {
+        let records = vec![json!({"author": {"name": "Tolstoy", "$note": "x"}})];
+        let schema = infer_json_schema(&records, "test", &SchemaOptions::new());
+        let author = property(&schema, "author");
+        assert_eq!(author["type"], json!("object"));
+        assert_eq!(author["required"], json!(["$note", "name"]));
+    }
+}
@@ -0,0 +1,603 @@
+use flate2::read::MultiGzDecoder;
+use serde::de::{self, DeserializeSeed, SeqAccess, Visitor};
+use serde_json::Value;
+use std::cell::Cell;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use super::{BiblioRecord, FieldInfo, IssueKind, RECORDS_PER_PROGRESS_UPDATE, RecordIssue, Severity};
+use super::analysis::analyze_schema;
+use super::duplicates::{DuplicateCluster, DuplicateOptions, duplicate_issues, find_duplicates};
+use super::hygiene::check_hygiene;
+use super::rules::RuleSet;
+use super::validation::ValidationSchema;
+use super::compression::{Compression, inner_file_name};
+use super::index::SearchIndex;
+use super::locator::{ArrayCandidate, PointerSeed, find_record_arrays, parse_pointer};
+
+const READ_BUFFER_SIZE: usize = 1024 * 1024;
+const CANCELLED_MESSAGE: &str = "loading cancelled";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputFormat {
+    Auto,
+    JsonArray,
+    JsonLines,
+    JsonDocument,
+}
+
+impl InputFormat {
+    pub fn all() -> [InputFormat; 4] {
+        [InputFormat::Auto, InputFormat::JsonArray, InputFormat::JsonLines, InputFormat::JsonDocument]
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            InputFormat::Auto => "Auto-detect",
+            InputFormat::JsonArray => "JSON array",
+            InputFormat::JsonLines => "JSON Lines / NDJSON",
+            InputFormat::JsonDocument => "Wrapped JSON document",
+        }
+    }
+
+    /// Resolves `Auto` from the file extension, falling back to the first
+    /// non-whitespace byte of `head`: a top-level `[` means a JSON array, a `{`
+    /// is either one document per line or a single wrapping document.
+    fn resolve(self, path: &str, head: &[u8], has_pointer: bool) -> InputFormat {
+        if self != InputFormat::Auto {
+            return self;
+        }
+
+        let lower = path.to_lowercase();
+        if lower.ends_with(".jsonl") || lower.ends_with(".ndjson") {
+            return InputFormat::JsonLines;
+        }
+
+        match head.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'[') | None => InputFormat::JsonArray,
+            Some(b'{') if has_pointer || !looks_like_json_lines(head) => InputFormat::JsonDocument,
+            Some(_) => InputFormat::JsonLines,
+        }
+    }
+}
+
+/// JSON Lines if the first line is a complete object and more lines follow.
+fn looks_like_json_lines(head: &[u8]) -> bool {
+    let Some(newline) = head.iter().position(|&b| b == b'\n') else {
+        return false;
+    };
+    let first_line_is_object = serde_json::from_slice::<Value>(&head[..newline])
+        .map(|v| v.is_object())
+        .unwrap_or(false);
+    let more_lines = head[newline..].iter().any(|b| !b.is_ascii_whitespace());
+
+    first_line_is_object && more_lines
+}
+
+#[derive(Clone)]
+pub struct LoadOptions {
+    pub format: InputFormat,
+    /// JSON Pointer to the records array inside a wrapped document. `None`
+    /// picks the largest array of objects found.
+    pub records_pointer: Option<String>,
+    /// Rule pack evaluated over every record; `None` for the built-in rules
+    pub rule_pack: Option<String>,
+    /// JSON Schema every record is validated against, if any
+    pub validation: Option<Arc<ValidationSchema>>,
+    /// Duplicate detection settings last used for the file; `None` looks
+    /// for identical records, with fields guessed from the schema
+    pub duplicates: Option<DuplicateOptions>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoadPhase {
+    Parsing,
+    AnalyzingSchema,
+    AnalyzingQuality,
+    Indexing,
+}
+
+impl LoadPhase {
+    pub fn label(&self) -> &str {
+        match self {
+            LoadPhase::Parsing => "Parsing records",
+            LoadPhase::AnalyzingSchema => "Analyzing schema",
+            LoadPhase::AnalyzingQuality => "Checking data quality",
+            LoadPhase::Indexing => "Building search index",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadProgress {
+    pub phase: LoadPhase,
+    pub bytes_read: u64,
+    pub total_bytes: u64,
+    pub records_parsed: usize,
+}
+
+impl LoadProgress {
+    /// Overall progress in `0.0..=1.0`. Parsing covers most of the bar by bytes
+    /// read; analysis and indexing share the rest.
+    pub fn fraction(&self) -> f32 {
+        let read_fraction = if self.total_bytes > 0 {
+            (self.bytes_read as f32 / self.total_bytes as f32).min(1.0)
+        } else {
+            0.0
+        };
+
+        match self.phase {
+            LoadPhase::Parsing => 0.85 * read_fraction,
+            LoadPhase::AnalyzingSchema => 0.85,
+            LoadPhase::AnalyzingQuality => 0.9,
+            LoadPhase::Indexing => 0.95,
+        }
+    }
+}
+
+/// Everything produced by a successful load, swapped into the app in one go
+/// so that a failed or cancelled load never touches the current dataset.
+pub struct LoadedDataset {
+    pub path: String,
+    pub records: Vec<BiblioRecord>,
+    pub field_schema: Vec<FieldInfo>,
+    pub top_level_fields: Vec<String>,
+    pub issues: Vec<RecordIssue>,
+    /// The rules the issues were found with
+    pub rules: RuleSet,
+    /// Why the rule pack could not be used; the built-in rules ran instead
+    pub rule_pack_error: Option<String>,
+    /// Duplicates found while checking quality, and the settings used
+    pub duplicates: Vec<DuplicateCluster>,
+    pub duplicate_options: DuplicateOptions,
+    pub search_index: SearchIndex,
+    pub records_pointer: Option<String>,
+    pub pointer_candidates: Vec<ArrayCandidate>,
+}
+
+pub enum LoadMessage {
+    Progress(LoadProgress),
+    Finished(Box<LoadedDataset>),
+    Failed(String),
+    Cancelled,
+}
+
+/// Handle to a load running on a worker thread.
+pub struct LoadJob {
+    receiver: Receiver<LoadMessage>,
+    cancel: Arc<AtomicBool>,
+    pub progress: LoadProgress,
+}
+
+impl LoadJob {
+    /// Starts loading `path` in the background. `notify` is called whenever a
+    /// new message is available (the UI uses it to request a repaint).
+    pub fn spawn(path: String, options: LoadOptions, notify: impl Fn() + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let progress = LoadProgress {
+            phase: LoadPhase::Parsing,
+            bytes_read: 0,
+            total_bytes: 0,
+            records_parsed: 0,
+        };
+
+        let mut reporter = Reporter {
+            sender,
+            cancel: cancel.clone(),
+            notify: Box::new(notify),
+            progress: progress.clone(),
+        };
+
+        thread::spawn(move || {
+            let message = match run_load(&path, &options, &mut reporter) {
+                Ok(dataset) => LoadMessage::Finished(Box::new(dataset)),
+                Err(_) if reporter.is_cancelled() => LoadMessage::Cancelled,
+                Err(e) => LoadMessage::Failed(e),
+            };
+            reporter.send(message);
+        });
+
+        Self {
+            receiver,
+            cancel,
+            progress,
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelling(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Returns the next pending message without blocking.
+    pub fn try_recv(&self) -> Option<LoadMessage> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+struct Reporter {
+    sender: Sender<LoadMessage>,
+    cancel: Arc<AtomicBool>,
+    notify: Box<dyn Fn() + Send>,
+    progress: LoadProgress,
+}
+
+impl Reporter {
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    fn send(&self, message: LoadMessage) {
+        // The app may have dropped the job already; nothing to do then.
+        if self.sender.send(message).is_ok() {
+            (self.notify)();
+        }
+    }
+
+    fn report(&self) {
+        self.send(LoadMessage::Progress(self.progress.clone()));
+    }
+
+    fn set_phase(&mut self, phase: LoadPhase) -> Result<(), String> {
+        if self.is_cancelled() {
+            return Err(CANCELLED_MESSAGE.to_string());
+        }
+        self.progress.phase = phase;
+        self.report();
+        Ok(())
+    }
+}
+
+fn run_load(path: &str, options: &LoadOptions, reporter: &mut Reporter) -> Result<LoadedDataset, String> {
+    let ParsedInput { records, mut issues, records_pointer, pointer_candidates } =
+        parse_file(path, options, reporter)?;
+
+    reporter.set_phase(LoadPhase::AnalyzingSchema)?;
+    let (field_schema, top_level_fields) = analyze_schema(&records);
+
+    reporter.set_phase(LoadPhase::AnalyzingQuality)?;
+    let (rules, rule_pack_error) = match &options.rule_pack {
+        Some(pack) => match RuleSet::load(pack) {
+            Ok(rules) => (rules, None),
+            Err(error) => (RuleSet::builtin(), Some(error)),
+        },
+        None => (RuleSet::builtin(), None),
+    };
+    issues.extend(rules.evaluate(&records, &mut |_| !reporter.is_cancelled()));
+    if let Some(schema) = &options.validation {
+        issues.extend(schema.validate(&records, &mut |_| !reporter.is_cancelled()));
+    }
+    issues.extend(check_hygiene(&records, &mut |_| !reporter.is_cancelled()));
+    let duplicate_options = options.duplicates.clone().unwrap_or_else(|| {
+        let paths: Vec<String> = field_schema.iter().map(|f| f.name.clone()).collect();
+        DuplicateOptions::for_fields(&paths)
+    });
+    let duplicates = find_duplicates(&records, &duplicate_options, &mut |_| !reporter.is_cancelled());
+    issues.extend(duplicate_issues(&duplicates, &duplicate_options));
+
+    reporter.set_phase(LoadPhase::Indexing)?;
+    let search_index = SearchIndex::build(&records);
+
+    if reporter.is_cancelled() {
+        return Err(CANCELLED_MESSAGE.to_string());
+    }
+
+    Ok(LoadedDataset {
+        path: path.to_string(),
+        records,
+        field_schema,
+        top_level_fields,
+        issues,
+        rules,
+        rule_pack_error,
+        duplicates,
+        duplicate_options,
+        search_index,
+        records_pointer,
+        pointer_candidates,
+    })
+}
+
+struct ParsedInput {
+    records: Vec<BiblioRecord>,
+    issues: Vec<RecordIssue>,
+    records_pointer: Option<String>,
+    pointer_candidates: Vec<ArrayCandidate>,
+}
+
+/// Streams records straight from the file, so the raw text is never held in
+/// memory next to the parsed records. Compressed files are decoded on the fly.
+fn parse_file(
+    path: &str,
+    options: &LoadOptions,
+    reporter: &mut Reporter,
+) -> Result<ParsedInput, String> {
+    let read_error = |e: io::Error| format!("Error reading file: {}", e);
+
+    let file = File::open(path).map_err(read_error)?;
+    reporter.progress.total_bytes = file.metadata().map_err(read_error)?.len();
+    reporter.report();
+
+    // Progress is measured on the bytes taken from disk, so it stays accurate
+    // for compressed input as well.
+    let position = Rc::new(Cell::new(0));
+    let counting = CountingReader { inner: file, position: position.clone() };
+    let mut reader = BufReader::with_capacity(READ_BUFFER_SIZE, counting);
+
+    let compression = Compression::detect(reader.fill_buf().map_err(read_error)?);
+    let decode_error = |e: io::Error| format!("Error decompressing {} file: {}", compression.label(), e);
+    let name = inner_file_name(path);
+
+    let mut parsed = match compression {
+        Compression::None => parse_stream(reader, name, options, &position, reporter)?,
+        Compression::Gzip => {
+            let decoder = BufReader::with_capacity(READ_BUFFER_SIZE, MultiGzDecoder::new(reader));
+            parse_stream(decoder, name, options, &position, reporter)?
+        }
+        Compression::Zstd => {
+            let decoder = zstd::Decoder::with_buffer(reader).map_err(decode_error)?;
+            let decoder = BufReader::with_capacity(READ_BUFFER_SIZE, decoder);
+            parse_stream(decoder, name, options, &position, reporter)?
+        }
+        Compression::Zip => parse_zip_entry(&mut reader, options, &position, reporter)?,
+    };
+
+    reporter.progress.records_parsed = parsed.records.len();
+    reporter.progress.bytes_read = reporter.progress.total_bytes;
+    reporter.report();
+
+    parsed.records.shrink_to_fit();
+    Ok(parsed)
+}
+
+/// Reads the first file in a zip archive, streaming it from the local headers
+/// so the archive does not need to be seekable.
+fn parse_zip_entry<R: Read>(
+    reader: &mut R,
+    options: &LoadOptions,
+    position: &Rc<Cell<u64>>,
+    reporter: &mut Reporter,
+) -> Result<ParsedInput, String> {
+    let zip_error = |e: zip::result::ZipError| format!("Error decompressing zip file: {}", e);
+
+    loop {
+        let Some(entry) = zip::read::read_zipfile_from_stream(reader).map_err(zip_error)? else {
+            return Err("Error decompressing zip file: the archive contains no files".to_string());
+        };
+        if entry.is_dir() {
+            continue;
+        }
+
+        let name = entry.name().to_string();
+        let entry = BufReader::with_capacity(READ_BUFFER_SIZE, entry);
+        return parse_stream(entry, &name, options, position, reporter);
+    }
+}
+
+/// Detects the input format from the start of the (decompressed) stream and
+/// parses it accordingly. `name` is only used for extension-based detection.
+fn parse_stream<R: BufRead>(
+    mut reader: R,
+    name: &str,
+    options: &LoadOptions,
+    position: &Rc<Cell<u64>>,
+    reporter: &mut Reporter,
+) -> Result<ParsedInput, String> {
+    let head = reader.fill_buf().map_err(|e| format!("Error reading file: {}", e))?;
+    let format = options.format.resolve(name, head, options.records_pointer.is_some());
+    let mut parsed = ParsedInput {
+        records: Vec::new(),
+        issues: Vec::new(),
+        records_pointer: None,
+        pointer_candidates: Vec::new(),
+    };
+
+    match format {
+        InputFormat::JsonLines => {
+            (parsed.records, parsed.issues) = parse_json_lines(reader, position, reporter)?;
+        }
+        InputFormat::JsonDocument => {
+            let pointer = options.records_pointer.as_deref();
+            let (records, pointer, candidates) = parse_json_document(reader, position, pointer, reporter)?;
+            parsed.records = records;
+            parsed.records_pointer = Some(pointer);
+            parsed.pointer_candidates = candidates;
+        }
+        _ => parsed.records = parse_json_array(reader, position, reporter)?,
+    }
+
+    Ok(parsed)
+}
+
+fn parse_json_array<R: Read>(
+    reader: R,
+    position: &Rc<Cell<u64>>,
+    reporter: &mut Reporter,
+) -> Result<Vec<BiblioRecord>, String> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    RecordsSeed { reporter, position }
+        .deserialize(&mut deserializer)
+        .and_then(|records| deserializer.end().map(|_| records))
+        .map_err(|e| format!("Error parsing JSON: {}", e))
+}
+
+/// Extracts the records array from a wrapping document. With a known pointer
+/// the array is streamed directly; otherwise the document is parsed whole and
+/// the largest array of objects is used, with all candidates returned so the
+/// user can pick another one.
+fn parse_json_document<R: Read>(
+    reader: R,
+    position: &Rc<Cell<u64>>,
+    pointer: Option<&str>,
+    reporter: &mut Reporter,
+) -> Result<(Vec<BiblioRecord>, String, Vec<ArrayCandidate>), String> {
+    if let Some(pointer) = pointer {
+        let tokens = parse_pointer(pointer)?;
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        let records = PointerSeed { pointer, tokens: &tokens, inner: RecordsSeed { reporter, position } }
+            .deserialize(&mut deserializer)
+            .and_then(|records| deserializer.end().map(|_| records))
+            .map_err(|e| format!("Error parsing JSON: {}", e))?;
+
+        let candidates = vec![ArrayCandidate { pointer: pointer.to_string(), len: records.len() }];
+        return Ok((records, pointer.to_string(), candidates));
+    }
+
+    let reader = ProgressReader { inner: reader, position, reporter, next_report: 0 };
+    let mut document: Value = serde_json::from_reader(reader)
+        .map_err(|e| format!("Error parsing JSON: {}", e))?;
+
+    let candidates = find_record_arrays(&document);
+    let Some(best) = candidates.first() else {
+        return Err("Error locating records: the document contains no array of objects".to_string());
+    };
+    let records = match document.pointer_mut(&best.pointer).map(Value::take) {
+        Some(Value::Array(records)) => records,
+        _ => return Err(format!("Error locating records: no array at {}", best.pointer)),
+    };
+
+    Ok((records, best.pointer.clone(), candidates))
+}
+
+/// Parses one JSON document per line. Blank lines are skipped and lines that
+/// fail to parse become issues instead of aborting the whole load.
+fn parse_json_lines<R: BufRead>(
+    mut reader: R,
+    position: &Rc<Cell<u64>>,
+    reporter: &mut Reporter,
+) -> Result<(Vec<BiblioRecord>, Vec<RecordIssue>), String> {
+    let mut records = Vec::new();
+    let mut issues = Vec::new();
+    let mut line = Vec::new();
+    let mut line_number = 0usize;
+
+    loop {
+        line.clear();
+        let n = reader.read_until(b'\n', &mut line)
+            .map_err(|e| format!("Error reading file: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        line_number += 1;
+
+        let content = line.trim_ascii_end();
+        if content.iter().all(|b| b.is_ascii_whitespace()) {
+            continue;
+        }
+
+        match serde_json::from_slice::<Value>(content) {
+            Ok(record) => records.push(record),
+            Err(e) => issues.push(RecordIssue {
+                kind: IssueKind::MalformedLine,
+                severity: Severity::Error,
+                source: "Invalid JSON".to_string(),
+                record_index: None,
+                path: None,
+                value: Some(String::from_utf8_lossy(content).chars().take(120).collect()),
+                description: format!("Line {}: {}", line_number, e),
+                suggestion: Some(format!("Fix or remove line {} and reload", line_number)),
+            }),
+        }
+
+        if line_number.is_multiple_of(RECORDS_PER_PROGRESS_UPDATE) {
+            if reporter.is_cancelled() {
+                return Err(CANCELLED_MESSAGE.to_string());
+            }
+            reporter.progress.records_parsed = records.len();
+            reporter.progress.bytes_read = position.get();
+            reporter.report();
+        }
+    }
+
+    Ok((records, issues))
+}
+
+/// Tracks how many bytes have been pulled from the underlying file.
+struct CountingReader<R> {
+    inner: R,
+    position: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position.set(self.position.get() + n as u64);
+        Ok(n)
+    }
+}
+
+/// Reports byte progress and honours cancellation for parses that cannot be
+/// observed record by record.
+struct ProgressReader<'a, R> {
+    inner: R,
+    position: &'a Rc<Cell<u64>>,
+    reporter: &'a mut Reporter,
+    next_report: u64,
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        let position = self.position.get();
+        if position >= self.next_report {
+            if self.reporter.is_cancelled() {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, CANCELLED_MESSAGE));
+            }
+            self.reporter.progress.bytes_read = position;
+            self.reporter.report();
+            self.next_report = position + READ_BUFFER_SIZE as u64;
+        }
+        Ok(n)
+    }
+}
+
+/// Deserializes the records array one element at a time so progress can be
+/// reported and cancellation honoured while parsing.
+struct RecordsSeed<'a> {
+    reporter: &'a mut Reporter,
+    position: &'a Rc<Cell<u64>>,
+}
+
+impl<'de> DeserializeSeed<'de> for RecordsSeed<'_> {
+    type Value = Vec<BiblioRecord>;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for RecordsSeed<'_> {
+    type Value = Vec<BiblioRecord>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON array of records")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut records = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+
+        while let Some(record) = seq.next_element::<Value>()? {
+            records.push(record);
+
+            if records.len().is_multiple_of(RECORDS_PER_PROGRESS_UPDATE) {
+                if self.reporter.is_cancelled() {
+                    return Err(de::Error::custom(CANCELLED_MESSAGE));
+                }
+                self.reporter.progress.records_parsed = records.len();
+                self.reporter.progress.bytes_read = self.position.get();
+                self.reporter.report();
+            }
+        }
+
+        Ok(records)
+    }
+}
@@ -0,0 +1,207 @@
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::Value;
+use std::fmt;
+
+/// Arrays nested deeper than this are not offered as record candidates.
+const MAX_SEARCH_DEPTH: usize = 8;
+
+/// An array of objects found inside a wrapped JSON document, addressed by its
+/// JSON Pointer (RFC 6901).
+#[derive(Debug, Clone)]
+pub struct ArrayCandidate {
+    pub pointer: String,
+    pub len: usize,
+}
+
+impl ArrayCandidate {
+    pub fn label(&self) -> String {
+        let pointer = if self.pointer.is_empty() { "(root)" } else { &self.pointer };
+        format!("{} ({} records)", pointer, self.len)
+    }
+}
+
+/// Splits a JSON Pointer into unescaped reference tokens.
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !pointer.starts_with('/') {
+        return Err(format!("Invalid JSON Pointer \"{}\": must start with '/'", pointer));
+    }
+
+    Ok(pointer[1..]
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+fn escape_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Finds every non-empty array that is mostly made of objects, largest first.
+pub fn find_record_arrays(document: &Value) -> Vec<ArrayCandidate> {
+    let mut candidates = Vec::new();
+    collect_record_arrays(document, String::new(), 0, &mut candidates);
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.len));
+    candidates
+}
+
+fn collect_record_arrays(value: &Value, pointer: String, depth: usize, out: &mut Vec<ArrayCandidate>) {
+    if depth > MAX_SEARCH_DEPTH {
+        return;
+    }
+
+    match value {
+        Value::Array(arr) => {
+            let objects = arr.iter().filter(|v| v.is_object()).count();
+            if !arr.is_empty() && objects * 2 >= arr.len() {
+                out.push(ArrayCandidate { pointer, len: arr.len() });
+            }
+        }
+        Value::Object(obj) => {
+            for (key, child) in obj {
+                let child_pointer = format!("{}/{}", pointer, escape_token(key));
+                collect_record_arrays(child, child_pointer, depth + 1, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Walks down to the value addressed by `tokens` while streaming, skipping
+/// everything else, and hands that value to `inner`.
+pub struct PointerSeed<'p, S> {
+    pub pointer: &'p str,
+    pub tokens: &'p [String],
+    pub inner: S,
+}
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for PointerSeed<'_, S> {
+    type Value = S::Value;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        if self.tokens.is_empty() {
+            self.inner.deserialize(deserializer)
+        } else {
+            deserializer.deserialize_any(self)
+        }
+    }
+}
+
+impl<'de, S: DeserializeSeed<'de>> Visitor<'de> for PointerSeed<'_, S> {
+    type Value = S::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an object or array containing {}", self.pointer)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let token = &self.tokens[0];
+        let mut next = Some(PointerSeed {
+            pointer: self.pointer,
+            tokens: &self.tokens[1..],
+            inner: self.inner,
+        });
+        let mut found = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match next.take() {
+                Some(seed) if key == *token => found = Some(map.next_value_seed(seed)?),
+                other => {
+                    next = other;
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        found.ok_or_else(|| de::Error::custom(format!("no value at {}", self.pointer)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let missing = || de::Error::custom(format!("no value at {}", self.pointer));
+        let index: usize = self.tokens[0].parse().map_err(|_| missing())?;
+
+        for _ in 0..index {
+            if seq.next_element::<IgnoredAny>()?.is_none() {
+                return Err(missing());
+            }
+        }
+
+        let found = seq.next_element_seed(PointerSeed {
+            pointer: self.pointer,
+            tokens: &self.tokens[1..],
+            inner: self.inner,
+        })?;
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+
+        found.ok_or_else(missing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::marker::PhantomData;
+
+    fn stream(document: &str, pointer: &str) -> Result<Value, String> {
+        let tokens = parse_pointer(pointer)?;
+        let seed = PointerSeed { pointer, tokens: &tokens, inner: PhantomData::<Value> };
+        let mut deserializer = serde_json::Deserializer::from_str(document);
+        seed.deserialize(&mut deserializer).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn parse_pointer_unescapes_tokens() {
+        assert_eq!(parse_pointer("").unwrap(), Vec::<String>::new());
+        assert_eq!(parse_pointer("/").unwrap(), vec![""]);
+        assert_eq!(parse_pointer("/a~1b/c~0d").unwrap(), vec!["a/b", "c~d"]);
+        // "~01" is an escaped "~" followed by "1", not an escaped "/"
+        assert_eq!(parse_pointer("/~01").unwrap(), vec!["~1"]);
+        assert_eq!(parse_pointer("/~10").unwrap(), vec!["/0"]);
+        assert!(parse_pointer("records").is_err());
+    }
+
+    #[test]
+    fn escape_round_trips() {
+        for key in ["plain", "a/b", "c~d", "~1", "/~0/"] {
+            let pointer = format!("/{}", escape_token(key));
+            assert_eq!(parse_pointer(&pointer).unwrap(), vec![key]);
+        }
+    }
+
+    #[test]
+    fn streams_into_nested_array() {
+        let document = r#"{"meta": {"count": 2}, "data": {"items": [{"a": 1}, {"a": 2}], "next": null}}"#;
+        assert_eq!(stream(document, "/data/items").unwrap(), json!([{"a": 1}, {"a": 2}]));
+        assert_eq!(stream(document, "/data/items/1/a").unwrap(), json!(2));
+        assert_eq!(stream(document, "").unwrap()["meta"]["count"], json!(2));
+    }
+
+    #[test]
+    fn streams_through_escaped_keys() {
+        let document = r#"{"a/b": {"c~d": [{"x": true}]}, "a": {"b": []}}"#;
+        assert_eq!(stream(document, "/a~1b/c~0d").unwrap(), json!([{"x": true}]));
+        assert_eq!(stream(document, "/a/b").unwrap(), json!([]));
+    }
+
+    #[test]
+    fn missing_values_are_errors() {
+        let document = r#"{"data": [{"a": 1}]}"#;
+        assert!(stream(document, "/records").unwrap_err().contains("no value at /records"));
+        assert!(stream(document, "/data/5").is_err());
+        assert!(stream(document, "/data/first").is_err());
+    }
+
+    #[test]
+    fn finds_record_arrays_largest_first() {
+        let document = json!({
+            "tags": ["a", "b", "c"],
+            "response": {"docs": [{}, {}, {}], "facets": [{}]},
+            "odd~key": [{}, {}],
+        });
+        let pointers: Vec<String> = find_record_arrays(&document).into_iter().map(|c| c.pointer).collect();
+        assert_eq!(pointers, vec!["/response/docs", "/odd~0key", "/response/facets"]);
+    }
+}
@@ -0,0 +1,208 @@
+//! Field paths address values inside nested records: `publisher.place` walks
+//! into an object, `authors[]` stands for every element of an array and
+//! `authors[].name` for the `name` key of each element. Keys that themselves
+//! contain `.`, `[` or `\` are escaped with a backslash.
+
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Elements,
+}
+
+/// A parsed field path that can be resolved against many records.
+#[derive(Debug, Clone)]
+pub struct FieldPath {
+    segments: Vec<Segment>,
+}
+
+impl FieldPath {
+    pub fn parse(path: &str) -> FieldPath {
+        let mut segments = Vec::new();
+        let mut key = String::new();
+        let mut chars = path.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    if let Some(escaped) = chars.next() {
+                        key.push(escaped);
+                    }
+                }
+                '.' => {
+                    if !key.is_empty() {
+                        segments.push(Segment::Key(std::mem::take(&mut key)));
+                    }
+                }
+                '[' if chars.peek() == Some(&']') => {
+                    chars.next();
+                    if !key.is_empty() {
+                        segments.push(Segment::Key(std::mem::take(&mut key)));
+                    }
+                    segments.push(Segment::Elements);
+                }
+                _ => key.push(c),
+            }
+        }
+        if !key.is_empty() {
+            segments.push(Segment::Key(key));
+        }
+
+        FieldPath { segments }
+    }
+
+    /// Every value the path reaches in `record`. Missing keys simply yield
+    /// nothing; `[]` fans out over array elements.
+    pub fn resolve<'a>(&self, record: &'a Value) -> Vec<&'a Value> {
+        let mut current = vec![record];
+
+        for segment in &self.segments {
+            let mut next = Vec::new();
+            for value in current {
+                match (segment, value) {
+                    (Segment::Key(key), Value::Object(obj)) => next.extend(obj.get(key)),
+                    (Segment::Elements, Value::Array(arr)) => next.extend(arr.iter()),
+                    _ => {}
+                }
+            }
+            if next.is_empty() {
+                return next;
+            }
+            current = next;
+        }
+
+        current
+    }
+
+    /// Calls `f` on every value the path reaches, allowing it to be modified.
+    pub fn for_each_mut(&self, record: &mut Value, f: &mut dyn FnMut(&mut Value)) {
+        visit_mut(&self.segments, record, f);
+    }
+
+    /// Copies the values the path reaches in `source` into `target`,
+    /// creating the objects and arrays on the way. Array elements keep their
+    /// positions (elements without the value stay empty), so several paths
+    /// copied into one target line up element by element.
+    pub fn copy_into(&self, source: &Value, target: &mut Value) {
+        copy_segments(&self.segments, source, target);
+    }
+}
+
+fn copy_segments(segments: &[Segment], source: &Value, target: &mut Value) {
+    match segments.split_first() {
+        None => *target = source.clone(),
+        Some((Segment::Key(key), rest)) => {
+            let Some(child) = source.as_object().and_then(|obj| obj.get(key)) else {
+                return;
+            };
+            if !target.is_object() {
+                *target = Value::Object(Map::new());
+            }
+            if let Value::Object(obj) = target {
+                let slot = obj.entry(key.clone()).or_insert_with(|| empty_like(rest));
+                copy_segments(rest, child, slot);
+            }
+        }
+        Some((Segment::Elements, rest)) => {
+            let Value::Array(items) = source else {
+                return;
+            };
+            if !matches!(target, Value::Array(slots) if slots.len() == items.len()) {
+                *target = Value::Array(vec![empty_like(rest); items.len()]);
+            }
+            if let Value::Array(slots) = target {
+                for (item, slot) in items.iter().zip(slots) {
+                    copy_segments(rest, item, slot);
+                }
+            }
+        }
+    }
+}
+
+/// The empty container the remaining `segments` will fill in.
+fn empty_like(segments: &[Segment]) -> Value {
+    match segments.first() {
+        Some(Segment::Elements) => Value::Array(Vec::new()),
+        _ => Value::Object(Map::new()),
+    }
+}
+
+fn visit_mut(segments: &[Segment], value: &mut Value, f: &mut dyn FnMut(&mut Value)) {
+    match segments.split_first() {
+        None => f(value),
+        Some((Segment::Key(key), rest)) => {
+            if let Some(child) = value.as_object_mut().and_then(|obj| obj.get_mut(key)) {
+                visit_mut(rest, child, f);
+            }
+        }
+        Some((Segment::Elements, rest)) => {
+            if let Value::Array(arr) = value {
+                for item in arr {
+                    visit_mut(rest, item, f);
+                }
+            }
+        }
+    }
+}
+
+/// Path of `key` inside the object at `parent` (top level if `parent` is empty).
+pub fn child_key_path(parent: &str, key: &str) -> String {
+    let key = escape_key(key);
+    if parent.is_empty() {
+        key
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+/// Path of the elements of the array at `parent`.
+pub fn element_path(parent: &str) -> String {
+    format!("{}[]", parent)
+}
+
+/// The field path (`authors[].name`) of the value a JSON Pointer
+/// (`/authors/0/name`) points to in `record`. Tokens past the end of the
+/// record are taken as object keys.
+pub fn pointer_to_path(record: &Value, pointer: &str) -> String {
+    let mut path = String::new();
+    let mut current = Some(record);
+    for token in pointer.split('/').skip(1) {
+        let token = token.replace("~1", "/").replace("~0", "~");
+        match current {
+            Some(Value::Array(arr)) => {
+                path = element_path(&path);
+                current = token.parse::<usize>().ok().and_then(|i| arr.get(i));
+            }
+            other => {
+                path = child_key_path(&path, &token);
+                current = other.and_then(|v| v.get(&token));
+            }
+        }
+    }
+    path
+}
+
+/// The last segment of a path as shown in tree views: `name` for
+/// `authors[].name`, `[]` for `authors[]`.
+pub fn last_segment<'a>(path: &'a str, parent: Option<&str>) -> &'a str {
+    match parent {
+        Some(parent) => path[parent.len()..].trim_start_matches('.'),
+        None => path,
+    }
+}
+
+fn escape_key(key: &str) -> String {
+    if key.contains(['.', '[', '\\']) {
+        let mut escaped = String::with_capacity(key.len() + 2);
+        for c in key.chars() {
+            if matches!(c, '.' | '[' | '\\') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    } else {
+        key.to_string()
+    }
+}
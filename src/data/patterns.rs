@@ -1,6 +1,6 @@
-use serde_json::Value;
 use std::collections::HashMap;
-use super::{BiblioRecord, FacetAnalysis, FacetValue};
+use super::{FacetAnalysis, FacetMode};
+use super::hygiene::{HygieneGroup, hygiene_groups};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum PatternType {
@@ -15,6 +15,7 @@ pub enum PatternType {
     UrlLike,
     AllCaps,
     NumericWithPunctuation,
+    ObjectId,
     
     // Bibliographic domain patterns
     FuzzyDate,
@@ -42,6 +43,7 @@ impl PatternType {
             PatternType::UrlLike => "URL-like",
             PatternType::AllCaps => "All Uppercase",
             PatternType::NumericWithPunctuation => "Numbers with Punctuation",
+            PatternType::ObjectId => "MongoDB ObjectId",
             PatternType::FuzzyDate => "Fuzzy Dates (circa, ca., ~)",
             PatternType::DateRange => "Date Ranges",
             PatternType::CenturyNotation => "Century Notation",
@@ -65,6 +67,7 @@ impl PatternType {
             PatternType::UrlLike => "Starts with http:// or https://",
             PatternType::AllCaps => "All uppercase letters (acronyms, codes)",
             PatternType::NumericWithPunctuation => "Numbers with commas, periods, or hyphens",
+            PatternType::ObjectId => "24-character hexadecimal identifiers",
             PatternType::FuzzyDate => "Approximate dates: 'circa 1800', 'ca. 1850', '~1900'",
             PatternType::DateRange => "Date ranges: '1800-1850', '1999/2000'",
             PatternType::CenturyNotation => "Century references: '18th century', '19. Jahrhundert'",
@@ -88,8 +91,12 @@ pub struct PatternGroup {
 #[derive(Debug, Clone)]
 pub struct PatternAnalysis {
     pub field_name: String,
+    pub mode: FacetMode,
     pub total_values: usize,
     pub pattern_groups: Vec<PatternGroup>,
+    /// Whitespace, Unicode and encoding problems; unlike patterns these
+    /// overlap
+    pub hygiene_groups: Vec<HygieneGroup>,
 }
 
 pub fn analyze_patterns(facets: &FacetAnalysis) -> PatternAnalysis {
@@ -126,16 +133,18 @@ pub fn analyze_patterns(facets: &FacetAnalysis) -> PatternAnalysis {
         .collect();
     
     // Sort by count descending
-    pattern_groups.sort_by(|a, b| b.count.cmp(&a.count));
+    pattern_groups.sort_by_key(|g| std::cmp::Reverse(g.count));
     
     PatternAnalysis {
         field_name: facets.field_name.clone(),
+        mode: facets.mode,
         total_values: facets.total_values,
         pattern_groups,
+        hygiene_groups: hygiene_groups(&facets.values, facets.total_values),
     }
 }
 
-fn classify_value(value: &str) -> PatternType {
+pub fn classify_value(value: &str) -> PatternType {
     let trimmed = value.trim();
     
     // Early returns for obvious cases
@@ -196,6 +205,11 @@ fn classify_value(value: &str) -> PatternType {
         return PatternType::IsoLanguageCode;
     }
     
+    // MongoDB ObjectId (24 hex digits)
+    if trimmed.len() == 24 && trimmed.chars().all(|c| c.is_ascii_hexdigit()) {
+        return PatternType::ObjectId;
+    }
+    
     // Numeric checks
     if trimmed.chars().all(|c| c.is_ascii_digit()) {
         return PatternType::PureNumeric;
@@ -233,7 +247,7 @@ fn is_four_digit_year(value: &str) -> bool {
     }
     
     if let Ok(year) = value.parse::<i32>() {
-        year >= 1000 && year <= 2999
+        (1000..=2999).contains(&year)
     } else {
         false
     }
@@ -247,12 +261,11 @@ fn is_iso_date(value: &str) -> bool {
     
     // Check for basic pattern: YYYY-MM-DD
     let parts: Vec<&str> = value.split('-').collect();
-    if parts.len() >= 3 {
-        if parts[0].len() == 4 && parts[0].chars().all(|c| c.is_ascii_digit()) &&
-           parts[1].len() == 2 && parts[1].chars().all(|c| c.is_ascii_digit()) &&
-           parts[2].starts_with(|c: char| c.is_ascii_digit()) {
-            return true;
-        }
+    if parts.len() >= 3 &&
+       parts[0].len() == 4 && parts[0].chars().all(|c| c.is_ascii_digit()) &&
+       parts[1].len() == 2 && parts[1].chars().all(|c| c.is_ascii_digit()) &&
+       parts[2].starts_with(|c: char| c.is_ascii_digit()) {
+        return true;
     }
     
     false
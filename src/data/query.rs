@@ -0,0 +1,736 @@
+//! The search box query language.
+//!
+//! ```text
+//! war peace                 both words anywhere in the record
+//! "war and peace"           a phrase anywhere in the record
+//! title:war                 `war` in the title (case-insensitive)
+//! authors[].name:"Tolstoy"  a phrase in a nested field
+//! lang="eng"                the whole value equals `eng`
+//! year>=1800  pages<100     numeric comparisons (also >, <, <=, =)
+//! title:/^the\b/i  /\d{4}/  regular expressions, `i` for case-insensitive
+//! has:isbn  missing:date    the field has / lacks a non-empty value
+//! a OR b   NOT c   -c   (a OR b) AND c
+//! ```
+//!
+//! Terms next to each other are ANDed; AND binds tighter than OR.
+
+use regex::{Regex, RegexBuilder};
+use serde_json::Value;
+use std::cell::OnceCell;
+use std::fmt;
+use super::{BiblioRecord, extract_searchable_text};
+use super::facets::collect_numbers;
+use super::index::{Candidates, SearchIndex};
+use super::paths::FieldPath;
+
+/// A syntax error with the character column (0-based) it was found at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (at column {})", self.message, self.position + 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug)]
+enum Term {
+    Text(String),
+    Regex(Regex),
+    FieldText { field: String, path: FieldPath, text: String },
+    FieldRegex(FieldPath, Regex),
+    FieldEquals { field: String, path: FieldPath, text: String },
+    FieldCompare(FieldPath, Comparison, f64),
+    Has(FieldPath),
+    Missing(FieldPath),
+}
+
+#[derive(Debug)]
+enum Node {
+    Term(Term),
+    Not(Box<Node>),
+    And(Vec<Node>),
+    Or(Vec<Node>),
+}
+
+/// A parsed query. The empty query matches every record.
+#[derive(Debug)]
+pub struct Query {
+    root: Option<Node>,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Query, QueryError> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Ok(Query { root: None });
+        }
+
+        let mut parser = Parser { tokens, pos: 0, end: input.chars().count() };
+        let root = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(QueryError {
+                message: match token.kind {
+                    TokenKind::RParen => "Unmatched ')'".to_string(),
+                    _ => "Unexpected input".to_string(),
+                },
+                position: token.position,
+            });
+        }
+        Ok(Query { root: Some(root) })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// The records the index says may match; see [`SearchIndex`].
+    pub fn candidates(&self, index: &SearchIndex) -> Candidates {
+        match &self.root {
+            None => Candidates::everything(),
+            Some(node) => node.candidates(index),
+        }
+    }
+
+    pub fn matches(&self, record: &BiblioRecord) -> bool {
+        match &self.root {
+            None => true,
+            Some(node) => {
+                let context = MatchContext { record, text: OnceCell::new() };
+                node.matches(&context)
+            }
+        }
+    }
+}
+
+/// A record being matched; its lowercased full text is built at most once.
+struct MatchContext<'a> {
+    record: &'a BiblioRecord,
+    text: OnceCell<String>,
+}
+
+impl MatchContext<'_> {
+    fn text(&self) -> &str {
+        self.text.get_or_init(|| extract_searchable_text(self.record).to_lowercase())
+    }
+}
+
+impl Node {
+    fn candidates(&self, index: &SearchIndex) -> Candidates {
+        match self {
+            Node::Term(term) => term.candidates(index),
+            Node::Not(node) => node.candidates(index).not(index.record_count()),
+            Node::And(nodes) => nodes
+                .iter()
+                .map(|n| n.candidates(index))
+                .fold(Candidates::everything(), Candidates::and),
+            Node::Or(nodes) => nodes
+                .iter()
+                .map(|n| n.candidates(index))
+                .reduce(Candidates::or)
+                .unwrap_or_else(Candidates::unknown),
+        }
+    }
+
+    fn matches(&self, context: &MatchContext) -> bool {
+        match self {
+            Node::Term(term) => term.matches(context),
+            Node::Not(node) => !node.matches(context),
+            Node::And(nodes) => nodes.iter().all(|n| n.matches(context)),
+            Node::Or(nodes) => nodes.iter().any(|n| n.matches(context)),
+        }
+    }
+}
+
+impl Term {
+    fn candidates(&self, index: &SearchIndex) -> Candidates {
+        let found = match self {
+            Term::Text(text) => index.lookup(None, text),
+            Term::FieldText { field, text, .. } => index.lookup(Some(field), text),
+            // Equal values contain the same tokens, but so do longer values
+            Term::FieldEquals { field, text, .. } => index
+                .lookup(Some(field), text)
+                .map(|c| Candidates { ids: c.ids, exact: false }),
+            _ => None,
+        };
+        found.unwrap_or_else(Candidates::unknown)
+    }
+
+    fn matches(&self, context: &MatchContext) -> bool {
+        let record = context.record;
+        match self {
+            Term::Text(text) => context.text().contains(text.as_str()),
+            Term::Regex(regex) => regex.is_match(&extract_searchable_text(record)),
+            Term::FieldText { path, text, .. } => path
+                .resolve(record)
+                .into_iter()
+                .any(|v| extract_searchable_text(v).to_lowercase().contains(text.as_str())),
+            Term::FieldRegex(path, regex) => path
+                .resolve(record)
+                .into_iter()
+                .any(|v| leaf_texts(v).iter().any(|t| regex.is_match(t))),
+            Term::FieldEquals { path, text, .. } => path
+                .resolve(record)
+                .into_iter()
+                .any(|v| leaf_texts(v).iter().any(|t| t.to_lowercase() == *text)),
+            Term::FieldCompare(path, comparison, number) => {
+                let mut numbers = Vec::new();
+                for value in path.resolve(record) {
+                    collect_numbers(value, &mut numbers);
+                }
+                numbers.iter().any(|n| match comparison {
+                    Comparison::Eq => n == number,
+                    Comparison::Lt => n < number,
+                    Comparison::Le => n <= number,
+                    Comparison::Gt => n > number,
+                    Comparison::Ge => n >= number,
+                })
+            }
+            Term::Has(path) => path.resolve(record).into_iter().any(is_present),
+            Term::Missing(path) => !path.resolve(record).into_iter().any(is_present),
+        }
+    }
+}
+
+/// Texts of a value with arrays split into their elements, so `lang="eng"`
+/// also matches `["eng", "ger"]`.
+fn leaf_texts(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(arr) => arr.iter().flat_map(leaf_texts).collect(),
+        Value::Null => Vec::new(),
+        _ => vec![extract_searchable_text(value)],
+    }
+}
+
+fn is_present(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::String(s) => !s.trim().is_empty(),
+        Value::Array(arr) => !arr.is_empty(),
+        _ => true,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    /// A complete term; parsed by the tokenizer because its parts are not
+    /// separated by spaces (`year>=1800`, `title:"war and peace"`)
+    Term(TermSpec),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TermSpec {
+    Text(String),
+    Regex(String, String),
+    Field(String, FieldValue),
+    Has(String),
+    Missing(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum FieldValue {
+    Text(String),
+    Regex(String, String),
+    Compare(Comparison, String),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut lexer = Lexer { chars, pos: 0 };
+    let mut tokens = Vec::new();
+
+    while let Some(c) = lexer.peek() {
+        let position = lexer.pos;
+        let kind = match c {
+            c if c.is_whitespace() => {
+                lexer.pos += 1;
+                continue;
+            }
+            '(' => {
+                lexer.pos += 1;
+                TokenKind::LParen
+            }
+            ')' => {
+                lexer.pos += 1;
+                TokenKind::RParen
+            }
+            '-' if lexer.peek_at(1).is_some_and(|c| !c.is_whitespace()) => {
+                lexer.pos += 1;
+                TokenKind::Not
+            }
+            '"' => TokenKind::Term(TermSpec::Text(lexer.quoted()?.to_lowercase())),
+            '/' => {
+                let (pattern, flags) = lexer.regex()?;
+                TokenKind::Term(TermSpec::Regex(pattern, flags))
+            }
+            _ => lexer.word_or_field()?,
+        };
+        tokens.push(Token { kind, position });
+    }
+
+    Ok(tokens)
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Lexer {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn error(&self, message: &str, position: usize) -> QueryError {
+        QueryError { message: message.to_string(), position }
+    }
+
+    /// A double-quoted string; `\"` and `\\` are escapes.
+    fn quoted(&mut self) -> Result<String, QueryError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '"' => return Ok(text),
+                '\\' => {
+                    if let Some(escaped) = self.peek() {
+                        text.push(escaped);
+                        self.pos += 1;
+                    }
+                }
+                _ => text.push(c),
+            }
+        }
+        Err(self.error("Unterminated quote", start))
+    }
+
+    /// `/pattern/flags`; `\/` inside the pattern is a literal slash.
+    fn regex(&mut self) -> Result<(String, String), QueryError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut pattern = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("Unterminated regular expression", start)),
+                Some('/') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some('\\') if self.peek_at(1) == Some('/') => {
+                    pattern.push('/');
+                    self.pos += 2;
+                }
+                Some(c) => {
+                    pattern.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        let mut flags = String::new();
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_alphabetic()) {
+            flags.push(c);
+            self.pos += 1;
+        }
+        Ok((pattern, flags))
+    }
+
+    fn word_or_field(&mut self) -> Result<TokenKind, QueryError> {
+        let start = self.pos;
+        let mut word = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                '\\' => {
+                    // Keep the escape: field paths use it for literal dots
+                    word.push(c);
+                    self.pos += 1;
+                    if let Some(escaped) = self.peek() {
+                        word.push(escaped);
+                        self.pos += 1;
+                    }
+                }
+                ':' | '<' | '>' | '=' | '"' | '(' | ')' => break,
+                c if c.is_whitespace() => break,
+                _ => {
+                    word.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+
+        match self.peek() {
+            Some(':') => {
+                self.pos += 1;
+                if word.is_empty() {
+                    return Err(self.error("Missing field name before ':'", start));
+                }
+                match word.as_str() {
+                    "has" => Ok(TokenKind::Term(TermSpec::Has(self.path_operand(start)?))),
+                    "missing" => Ok(TokenKind::Term(TermSpec::Missing(self.path_operand(start)?))),
+                    _ => {
+                        let value = self.field_value()?;
+                        Ok(TokenKind::Term(TermSpec::Field(word, value)))
+                    }
+                }
+            }
+            Some('<' | '>' | '=') => {
+                if word.is_empty() {
+                    return Err(self.error("Missing field name before comparison", start));
+                }
+                let value = self.field_value()?;
+                Ok(TokenKind::Term(TermSpec::Field(word, value)))
+            }
+            _ if word.is_empty() => Err(self.error("Unexpected character", start)),
+            _ => Ok(match word.as_str() {
+                "AND" => TokenKind::And,
+                "OR" => TokenKind::Or,
+                "NOT" => TokenKind::Not,
+                _ => TokenKind::Term(TermSpec::Text(word.to_lowercase())),
+            }),
+        }
+    }
+
+    fn path_operand(&mut self, start: usize) -> Result<String, QueryError> {
+        let mut path = String::new();
+        while let Some(c) = self.peek().filter(|c| !c.is_whitespace() && *c != '(' && *c != ')') {
+            path.push(c);
+            self.pos += 1;
+        }
+        if path.is_empty() {
+            return Err(self.error("Missing field name", start));
+        }
+        Ok(path)
+    }
+
+    /// What follows `field:` or the field name in a comparison.
+    fn field_value(&mut self) -> Result<FieldValue, QueryError> {
+        let comparison = match (self.peek(), self.peek_at(1)) {
+            (Some('>'), Some('=')) => Some((Comparison::Ge, 2)),
+            (Some('<'), Some('=')) => Some((Comparison::Le, 2)),
+            (Some('>'), _) => Some((Comparison::Gt, 1)),
+            (Some('<'), _) => Some((Comparison::Lt, 1)),
+            (Some('='), _) => Some((Comparison::Eq, 1)),
+            _ => None,
+        };
+        if let Some((comparison, len)) = comparison {
+            self.pos += len;
+            let position = self.pos;
+            let operand = self.operand()?;
+            if operand.is_empty() {
+                return Err(self.error("Missing value after comparison", position));
+            }
+            return Ok(FieldValue::Compare(comparison, operand));
+        }
+
+        let position = self.pos;
+        match self.peek() {
+            Some('/') => {
+                let (pattern, flags) = self.regex()?;
+                Ok(FieldValue::Regex(pattern, flags))
+            }
+            _ => {
+                let text = self.operand()?;
+                if text.is_empty() {
+                    return Err(self.error("Missing value after ':'", position));
+                }
+                Ok(FieldValue::Text(text))
+            }
+        }
+    }
+
+    /// A quoted string or a bare word.
+    fn operand(&mut self) -> Result<String, QueryError> {
+        if self.peek() == Some('"') {
+            return self.quoted();
+        }
+        let mut text = String::new();
+        while let Some(c) = self.peek().filter(|c| !c.is_whitespace() && *c != '(' && *c != ')') {
+            text.push(c);
+            self.pos += 1;
+        }
+        Ok(text)
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Input length, reported for errors at the end of the query
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn parse_or(&mut self) -> Result<Node, QueryError> {
+        let mut nodes = vec![self.parse_and()?];
+        while self.peek().is_some_and(|t| t.kind == TokenKind::Or) {
+            self.pos += 1;
+            nodes.push(self.parse_and()?);
+        }
+        Ok(if nodes.len() == 1 { nodes.remove(0) } else { Node::Or(nodes) })
+    }
+
+    fn parse_and(&mut self) -> Result<Node, QueryError> {
+        let mut nodes = vec![self.parse_unary()?];
+        loop {
+            match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::And) => {
+                    self.pos += 1;
+                    nodes.push(self.parse_unary()?);
+                }
+                Some(TokenKind::Or | TokenKind::RParen) | None => break,
+                Some(_) => nodes.push(self.parse_unary()?),
+            }
+        }
+        Ok(if nodes.len() == 1 { nodes.remove(0) } else { Node::And(nodes) })
+    }
+
+    fn parse_unary(&mut self) -> Result<Node, QueryError> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            return Err(QueryError { message: "Query ends where a term was expected".to_string(), position: self.end });
+        };
+        self.pos += 1;
+
+        match token.kind {
+            TokenKind::Not => Ok(Node::Not(Box::new(self.parse_unary()?))),
+            TokenKind::LParen => {
+                let node = self.parse_or()?;
+                match self.peek() {
+                    Some(Token { kind: TokenKind::RParen, .. }) => {
+                        self.pos += 1;
+                        Ok(node)
+                    }
+                    _ => Err(QueryError { message: "Unclosed '('".to_string(), position: token.position }),
+                }
+            }
+            TokenKind::Term(spec) => build_term(spec, token.position).map(Node::Term),
+            TokenKind::RParen => Err(QueryError { message: "Unmatched ')'".to_string(), position: token.position }),
+            TokenKind::And | TokenKind::Or => Err(QueryError {
+                message: "AND/OR needs a term on both sides".to_string(),
+                position: token.position,
+            }),
+        }
+    }
+}
+
+fn build_term(spec: TermSpec, position: usize) -> Result<Term, QueryError> {
+    let regex = |pattern: &str, flags: &str| {
+        if let Some(flag) = flags.chars().find(|c| *c != 'i') {
+            return Err(QueryError { message: format!("Unknown regex flag '{}'", flag), position });
+        }
+        RegexBuilder::new(pattern)
+            .case_insensitive(flags.contains('i'))
+            .build()
+            .map_err(|e| QueryError { message: format!("Invalid regex: {}", regex_error_summary(&e.to_string())), position })
+    };
+
+    Ok(match spec {
+        TermSpec::Text(text) => Term::Text(text),
+        TermSpec::Regex(pattern, flags) => Term::Regex(regex(&pattern, &flags)?),
+        TermSpec::Has(path) => Term::Has(FieldPath::parse(&path)),
+        TermSpec::Missing(path) => Term::Missing(FieldPath::parse(&path)),
+        TermSpec::Field(field, value) => {
+            let path = FieldPath::parse(&field);
+            match value {
+                FieldValue::Text(text) => Term::FieldText { field, path, text: text.to_lowercase() },
+                FieldValue::Regex(pattern, flags) => Term::FieldRegex(path, regex(&pattern, &flags)?),
+                FieldValue::Compare(comparison, operand) => match operand.trim().parse::<f64>() {
+                    Ok(number) => Term::FieldCompare(path, comparison, number),
+                    Err(_) if comparison == Comparison::Eq => {
+                        Term::FieldEquals { field, path, text: operand.to_lowercase() }
+                    }
+                    Err(_) => {
+                        return Err(QueryError {
+                            message: format!("'{}' is not a number", operand),
+                            position,
+                        })
+                    }
+                },
+            }
+        }
+    })
+}
+
+/// The regex crate's errors span several lines with an ASCII-art pointer;
+/// the last line holds the actual message.
+fn regex_error_summary(message: &str) -> &str {
+    let last = message.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or(message).trim();
+    last.strip_prefix("error: ").unwrap_or(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// The query tree as a compact s-expression, terms by kind and text.
+    fn shape(input: &str) -> String {
+        fn node(n: &Node) -> String {
+            match n {
+                Node::Term(term) => match term {
+                    Term::Text(text) => format!("{:?}", text),
+                    Term::Regex(regex) => format!("/{}/", regex.as_str()),
+                    Term::FieldText { field, text, .. } => format!("{}:{:?}", field, text),
+                    Term::FieldRegex(_, regex) => format!("field:/{}/", regex.as_str()),
+                    Term::FieldEquals { field, text, .. } => format!("{}={:?}", field, text),
+                    Term::FieldCompare(_, comparison, number) => format!("{:?} {}", comparison, number),
+                    Term::Has(_) => "has".to_string(),
+                    Term::Missing(_) => "missing".to_string(),
+                },
+                Node::Not(inner) => format!("(not {})", node(inner)),
+                Node::And(nodes) => format!("(and {})", nodes.iter().map(node).collect::<Vec<_>>().join(" ")),
+                Node::Or(nodes) => format!("(or {})", nodes.iter().map(node).collect::<Vec<_>>().join(" ")),
+            }
+        }
+        match Query::parse(input).unwrap().root {
+            None => String::new(),
+            Some(root) => node(&root),
+        }
+    }
+
+    fn error(input: &str) -> (String, usize) {
+        let e = Query::parse(input).unwrap_err();
+        (e.message, e.position)
+    }
+
+    fn matches(input: &str, record: &Value) -> bool {
+        Query::parse(input).unwrap().matches(record)
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(shape("a b OR c"), r#"(or (and "a" "b") "c")"#);
+        assert_eq!(shape("a OR b c"), r#"(or "a" (and "b" "c"))"#);
+        assert_eq!(shape("a AND b OR c AND d"), r#"(or (and "a" "b") (and "c" "d"))"#);
+        assert_eq!(shape("(a OR b) c"), r#"(and (or "a" "b") "c")"#);
+        assert_eq!(shape("NOT a b"), r#"(and (not "a") "b")"#);
+        assert_eq!(shape("NOT (a OR b)"), r#"(not (or "a" "b"))"#);
+        assert_eq!(shape("NOT NOT a"), r#"(not (not "a"))"#);
+        assert_eq!(shape("   "), "");
+    }
+
+    #[test]
+    fn minus_negates_only_when_attached() {
+        assert_eq!(shape("war -peace"), r#"(and "war" (not "peace"))"#);
+        assert_eq!(shape("-(a OR b)"), r#"(not (or "a" "b"))"#);
+        assert_eq!(shape("war - peace"), r#"(and "war" "-" "peace")"#);
+        assert_eq!(shape("war-time"), r#""war-time""#);
+    }
+
+    #[test]
+    fn quoted_phrases_keep_spaces_and_escapes() {
+        assert_eq!(shape(r#""War and Peace""#), r#""war and peace""#);
+        assert_eq!(shape(r#""say \"hi\"""#), r#""say \"hi\"""#);
+        assert_eq!(shape(r#"title:"War OR Peace""#), r#"title:"war or peace""#);
+        assert_eq!(shape(r#""AND""#), r#""and""#);
+        assert!(matches(r#""war and peace""#, &json!({"title": "War and Peace"})));
+        assert!(!matches(r#""war peace""#, &json!({"title": "War and Peace"})));
+    }
+
+    #[test]
+    fn regexes_take_flags_and_escaped_slashes() {
+        assert_eq!(shape(r"/^the\b/i"), r"/^the\b/");
+        assert_eq!(shape(r"/a\/b/"), "/a/b/");
+        assert_eq!(shape(r"title:/\d{4}/"), r"field:/\d{4}/");
+        assert!(matches("/^the/i", &json!({"title": "The End"})));
+        assert!(!matches("/^the/", &json!({"title": "The End"})));
+        assert!(matches(r"title:/^\d{4}$/", &json!({"title": ["x", "1999"]})));
+    }
+
+    #[test]
+    fn comparisons_and_equality() {
+        assert_eq!(shape("year>=1800"), "Ge 1800");
+        assert_eq!(shape("year<=1800 pages>10 pages<20.5"), "(and Le 1800 Gt 10 Lt 20.5)");
+        assert_eq!(shape("year=1800"), "Eq 1800");
+        assert_eq!(shape(r#"lang="ENG""#), r#"lang="eng""#);
+        assert_eq!(shape("lang=eng"), r#"lang="eng""#);
+
+        let record = json!({"year": 1869, "pages": "1225", "lang": ["eng", "rus"]});
+        assert!(matches("year>=1800 year<1900", &record));
+        assert!(!matches("year>1869", &record));
+        assert!(matches("year=1869", &record));
+        assert!(matches("lang=rus", &record));
+        assert!(!matches("lang=ru", &record));
+        assert!(matches("lang:ru", &record));
+    }
+
+    #[test]
+    fn has_and_missing_ignore_empty_values() {
+        assert_eq!(shape("has:isbn missing:date"), "(and has missing)");
+        let record = json!({"isbn": "978-0", "date": "  ", "tags": [], "notes": null, "count": 0});
+        assert!(matches("has:isbn", &record));
+        assert!(matches("has:count", &record));
+        for field in ["date", "tags", "notes", "absent"] {
+            assert!(matches(&format!("missing:{}", field), &record), "missing:{}", field);
+            assert!(!matches(&format!("has:{}", field), &record), "has:{}", field);
+        }
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_column() {
+        let cases = [
+            (r#"title:"war"#, "Unterminated quote", 6),
+            ("a /abc", "Unterminated regular expression", 2),
+            ("a AND", "Query ends where a term was expected", 5),
+            ("a OR ", "Query ends where a term was expected", 5),
+            ("x (a OR b", "Unclosed '('", 2),
+            ("a ) b", "Unmatched ')'", 2),
+            ("é ) b", "Unmatched ')'", 2),
+            ("OR a", "AND/OR needs a term on both sides", 0),
+            ("a AND OR b", "AND/OR needs a term on both sides", 6),
+            ("x :foo", "Missing field name before ':'", 2),
+            ("=eng", "Missing field name before comparison", 0),
+            ("year>", "Missing value after comparison", 5),
+            ("a title:", "Missing value after ':'", 8),
+            ("has: x", "Missing field name", 0),
+            ("a year>abc", "'abc' is not a number", 2),
+            ("title:/a/x", "Unknown regex flag 'x'", 0),
+            ("a\"", "Unterminated quote", 1),
+        ];
+        for (input, message, position) in cases {
+            assert_eq!(error(input), (message.to_string(), position), "error for {:?}", input);
+        }
+
+        let (message, position) = error("b /(/");
+        assert!(message.starts_with("Invalid regex: "), "{}", message);
+        assert!(!message.contains('\n'));
+        assert_eq!(position, 2);
+    }
+
+    #[test]
+    fn error_display_counts_columns_from_one() {
+        let e = Query::parse("a )").unwrap_err();
+        assert_eq!(e.to_string(), "Unmatched ')' (at column 3)");
+    }
+}
@@ -0,0 +1,193 @@
+//! Facet, pattern and schema analyses as CSV, JSON or Markdown reports that
+//! can be attached to tickets or diffed between harvests.
+
+use serde_json::{Value, json};
+use super::{FacetAnalysis, FieldInfo, PatternAnalysis};
+use super::export::csv_row;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+    Markdown,
+}
+
+impl ReportFormat {
+    pub fn label(&self) -> &str {
+        match self {
+            ReportFormat::Csv => "CSV",
+            ReportFormat::Json => "JSON",
+            ReportFormat::Markdown => "Markdown",
+        }
+    }
+
+    pub fn extension(&self) -> &str {
+        match self {
+            ReportFormat::Csv => "csv",
+            ReportFormat::Json => "json",
+            ReportFormat::Markdown => "md",
+        }
+    }
+}
+
+/// Value counts of a facet, most common first.
+pub fn facet_report(analysis: &FacetAnalysis, format: ReportFormat) -> String {
+    let header = ["value", "count", "percentage"];
+    let rows = analysis.values.iter().map(|v| {
+        vec![v.value.clone(), v.count.to_string(), format!("{:.2}", v.percentage)]
+    });
+
+    match format {
+        ReportFormat::Csv => csv_table(&header, rows),
+        ReportFormat::Markdown => {
+            let title = format!("# Facet: {} ({})\n\n{} values, {} unique, {} null\n\n",
+                analysis.field_name, analysis.mode.label(), analysis.total_values,
+                analysis.unique_values, analysis.null_count);
+            title + &markdown_table(&header, rows)
+        }
+        ReportFormat::Json => pretty(json!({
+            "field": analysis.field_name,
+            "mode": analysis.mode.label(),
+            "total_values": analysis.total_values,
+            "unique_values": analysis.unique_values,
+            "null_count": analysis.null_count,
+            "values": analysis.values.iter().map(|v| json!({
+                "value": v.value,
+                "count": v.count,
+                "percentage": round2(v.percentage),
+            })).collect::<Vec<_>>(),
+        })),
+    }
+}
+
+pub fn pattern_report(analysis: &PatternAnalysis, format: ReportFormat) -> String {
+    let header = ["pattern", "description", "count", "percentage", "examples"];
+    let rows = analysis.pattern_groups.iter().map(|g| {
+        vec![
+            g.pattern_type.name().to_string(),
+            g.pattern_type.description().to_string(),
+            g.count.to_string(),
+            format!("{:.2}", g.percentage),
+            g.examples.join("; "),
+        ]
+    }).chain(analysis.hygiene_groups.iter().map(|g| {
+        vec![
+            format!("Hygiene: {}", g.problem.name()),
+            g.problem.description().to_string(),
+            g.count.to_string(),
+            format!("{:.2}", g.percentage),
+            g.examples.join("; "),
+        ]
+    }));
+
+    match format {
+        ReportFormat::Csv => csv_table(&header, rows),
+        ReportFormat::Markdown => {
+            let title = format!("# Patterns: {} ({})\n\n{} values\n\n",
+                analysis.field_name, analysis.mode.label(), analysis.total_values);
+            title + &markdown_table(&header, rows)
+        }
+        ReportFormat::Json => pretty(json!({
+            "field": analysis.field_name,
+            "mode": analysis.mode.label(),
+            "total_values": analysis.total_values,
+            "patterns": analysis.pattern_groups.iter().map(|g| json!({
+                "pattern": g.pattern_type.name(),
+                "description": g.pattern_type.description(),
+                "count": g.count,
+                "percentage": round2(g.percentage),
+                "examples": g.examples,
+            })).collect::<Vec<_>>(),
+            "hygiene": analysis.hygiene_groups.iter().map(|g| json!({
+                "problem": g.problem.name(),
+                "description": g.problem.description(),
+                "count": g.count,
+                "percentage": round2(g.percentage),
+                "examples": g.examples,
+            })).collect::<Vec<_>>(),
+        })),
+    }
+}
+
+/// One row per field path with its types, presence and coverage.
+pub fn schema_report(fields: &[FieldInfo], record_count: usize, format: ReportFormat) -> String {
+    let coverage = |field: &FieldInfo| {
+        if record_count > 0 {
+            field.sample_count as f32 / record_count as f32 * 100.0
+        } else {
+            0.0
+        }
+    };
+    let type_breakdown = |field: &FieldInfo| {
+        field.type_counts.iter()
+            .map(|(t, count)| format!("{}: {}", t, count))
+            .collect::<Vec<_>>()
+            .join("; ")
+    };
+
+    let header = ["path", "type", "types", "present_in", "null_count", "value_count", "coverage"];
+    let rows = fields.iter().map(|f| {
+        vec![
+            f.name.clone(),
+            f.field_type.clone(),
+            type_breakdown(f),
+            f.sample_count.to_string(),
+            f.null_count.to_string(),
+            f.value_count.to_string(),
+            format!("{:.2}", coverage(f)),
+        ]
+    });
+
+    match format {
+        ReportFormat::Csv => csv_table(&header, rows),
+        ReportFormat::Markdown => {
+            let title = format!("# Schema\n\n{} fields across {} records\n\n", fields.len(), record_count);
+            title + &markdown_table(&header, rows)
+        }
+        ReportFormat::Json => pretty(json!({
+            "record_count": record_count,
+            "fields": fields.iter().map(|f| json!({
+                "path": f.name,
+                "type": f.field_type,
+                "type_counts": f.type_counts.iter()
+                    .map(|(t, count)| (t.clone(), Value::from(*count)))
+                    .collect::<serde_json::Map<_, _>>(),
+                "present_in": f.sample_count,
+                "null_count": f.null_count,
+                "value_count": f.value_count,
+                "coverage": round2(coverage(f)),
+            })).collect::<Vec<_>>(),
+        })),
+    }
+}
+
+fn csv_table(header: &[&str], rows: impl Iterator<Item = Vec<String>>) -> String {
+    let mut out = csv_row(header.iter().map(|h| h.to_string()));
+    for row in rows {
+        out.push_str(&csv_row(row.into_iter()));
+    }
+    out
+}
+
+fn markdown_table(header: &[&str], rows: impl Iterator<Item = Vec<String>>) -> String {
+    let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+    let mut out = line(header.iter().map(|h| h.to_string()).collect());
+    out.push_str(&line(header.iter().map(|_| "---".to_string()).collect()));
+    for row in rows {
+        out.push_str(&line(row.iter().map(|cell| markdown_cell(cell)).collect()));
+    }
+    out
+}
+
+/// Escapes pipes and keeps multi-line values on one table row.
+fn markdown_cell(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+fn round2(value: f32) -> f64 {
+    (value as f64 * 100.0).round() / 100.0
+}
+
+fn pretty(value: Value) -> String {
+    serde_json::to_string_pretty(&value).unwrap_or_default()
+}
@@ -0,0 +1,501 @@
+//! Declarative data-quality rules, evaluated over every record.
+//!
+//! A rule pack is a JSON file:
+//!
+//! ```json
+//! {
+//!   "name": "Catalogue basics",
+//!   "rules": [
+//!     { "id": "title-required", "field": "title", "check": "required", "severity": "error" },
+//!     { "id": "language-code", "field": "language", "check": "allowed_values", "values": ["eng", "ger"] },
+//!     { "id": "isbn-format", "field": "isbn", "check": "pattern", "regex": "^97[89][0-9]{10}$" },
+//!     { "id": "title-length", "field": "title", "check": "length", "min": 3, "max": 500 },
+//!     { "id": "year-range", "field": "year", "check": "range", "min": 1450, "max": 2030 },
+//!     { "id": "years-ordered", "field": "end_year", "check": "compare", "op": ">=", "other": "start_year" }
+//!   ]
+//! }
+//! ```
+//!
+//! Fields are paths as everywhere else (`authors[].name`); array values are
+//! checked element by element. Checks other than `required` skip records
+//! without a value. Every rule may also set `severity` (`error`, `warning`,
+//! the default, or `info`), a `message` and a suggested `fix`.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use super::{
+    BiblioRecord, IssueKind, ProgressFn, RECORDS_PER_PROGRESS_UPDATE, RecordIssue, Severity, extract_searchable_text,
+    get_display_value,
+};
+use super::facets::collect_numbers;
+use super::paths::{FieldPath, element_path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CompareOp {
+    #[serde(rename = "<")]
+    Less,
+    #[serde(rename = "<=")]
+    LessOrEqual,
+    #[serde(rename = "==")]
+    Equal,
+    #[serde(rename = "!=")]
+    NotEqual,
+    #[serde(rename = ">=")]
+    GreaterOrEqual,
+    #[serde(rename = ">")]
+    Greater,
+}
+
+impl CompareOp {
+    fn symbol(&self) -> &str {
+        match self {
+            CompareOp::Less => "<",
+            CompareOp::LessOrEqual => "<=",
+            CompareOp::Equal => "==",
+            CompareOp::NotEqual => "!=",
+            CompareOp::GreaterOrEqual => ">=",
+            CompareOp::Greater => ">",
+        }
+    }
+
+    fn holds(&self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::Less => ordering.is_lt(),
+            CompareOp::LessOrEqual => ordering.is_le(),
+            CompareOp::Equal => ordering.is_eq(),
+            CompareOp::NotEqual => ordering.is_ne(),
+            CompareOp::GreaterOrEqual => ordering.is_ge(),
+            CompareOp::Greater => ordering.is_gt(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "check", rename_all = "snake_case")]
+pub enum Check {
+    /// The value (or without a `field`, the record itself) must be a JSON
+    /// object
+    Object,
+    /// A non-null, non-empty value must be present
+    Required,
+    AllowedValues { values: Vec<Value> },
+    /// Values (as text) must match the regular expression
+    Pattern { regex: String },
+    /// Character count of strings, element count of arrays
+    Length {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<usize>,
+    },
+    /// Values must be numbers (or numeric strings) within the bounds
+    Range {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<f64>,
+    },
+    /// `field op other`, numerically when both sides are numbers
+    Compare { op: CompareOp, other: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// Shown instead of the generated description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Shown instead of the generated suggestion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+    #[serde(flatten)]
+    pub check: Check,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RulePackFile {
+    #[serde(default)]
+    name: String,
+    rules: Vec<Rule>,
+}
+
+/// A rule ready to run: paths parsed and regexes compiled.
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    rule: Rule,
+    path: Option<FieldPath>,
+    other: Option<FieldPath>,
+    regex: Option<Regex>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RuleSet {
+    pub name: String,
+    /// File the pack was read from; `None` for the built-in pack
+    pub path: Option<String>,
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleSet {
+    /// The checks that run when no rule pack is configured.
+    pub fn builtin() -> RuleSet {
+        let rules = vec![Rule {
+            id: "record-is-object".to_string(),
+            field: None,
+            message: Some("Record is not a JSON object".to_string()),
+            severity: Severity::Error,
+            fix: None,
+            check: Check::Object,
+        }];
+        RuleSet::compile("Built-in checks".to_string(), None, rules).expect("built-in rules compile")
+    }
+
+    pub fn load(path: &str) -> Result<RuleSet, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        let pack: RulePackFile = serde_json::from_str(&text)
+            .map_err(|e| format!("{} is not a valid rule pack: {}", path, e))?;
+        let name = if pack.name.is_empty() { path.to_string() } else { pack.name };
+        RuleSet::compile(name, Some(path.to_string()), pack.rules)
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+
+    fn compile(name: String, path: Option<String>, rules: Vec<Rule>) -> Result<RuleSet, String> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let needs_field = !matches!(rule.check, Check::Object);
+                if needs_field && rule.field.is_none() {
+                    return Err(format!("rule '{}' needs a field", rule.id));
+                }
+                let regex = match &rule.check {
+                    Check::Pattern { regex } => Some(
+                        Regex::new(regex).map_err(|e| format!("rule '{}': invalid regex: {}", rule.id, e))?,
+                    ),
+                    _ => None,
+                };
+                let other = match &rule.check {
+                    Check::Compare { other, .. } => Some(FieldPath::parse(other)),
+                    _ => None,
+                };
+                Ok(CompiledRule {
+                    path: rule.field.as_deref().map(FieldPath::parse),
+                    other,
+                    regex,
+                    rule,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(RuleSet { name, path, rules })
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// A starter pack with the built-in check and one example of each
+    /// kind of rule, as JSON.
+    pub fn example_pack() -> String {
+        let example = |id: &str, field: &str, check: Check| Rule {
+            id: id.to_string(),
+            field: Some(field.to_string()),
+            message: None,
+            severity: Severity::Warning,
+            fix: None,
+            check,
+        };
+        let mut rules = RuleSet::builtin().rules.into_iter().map(|c| c.rule).collect::<Vec<_>>();
+        rules.extend([
+            Rule { severity: Severity::Error, ..example("title-required", "title", Check::Required) },
+            example("language-code", "language", Check::AllowedValues {
+                values: vec![Value::from("eng"), Value::from("ger"), Value::from("fre")],
+            }),
+            example("isbn-format", "isbn", Check::Pattern { regex: "^97[89][0-9]{10}$".to_string() }),
+            example("title-length", "title", Check::Length { min: Some(3), max: Some(500) }),
+            example("year-range", "year", Check::Range { min: Some(1450.0), max: Some(2030.0) }),
+            example("years-ordered", "end_year", Check::Compare {
+                op: CompareOp::GreaterOrEqual,
+                other: "start_year".to_string(),
+            }),
+        ]);
+        let pack = RulePackFile { name: "My rules".to_string(), rules };
+        serde_json::to_string_pretty(&pack).unwrap_or_default()
+    }
+
+    /// Runs every rule over every record.
+    pub fn evaluate(&self, records: &[BiblioRecord], progress: &mut ProgressFn) -> Vec<RecordIssue> {
+        let mut issues = Vec::new();
+        for (idx, record) in records.iter().enumerate() {
+            if idx.is_multiple_of(RECORDS_PER_PROGRESS_UPDATE) && !progress(idx) {
+                break;
+            }
+            for compiled in &self.rules {
+                let rule = &compiled.rule;
+                for problem in compiled.check(record) {
+                    issues.push(RecordIssue {
+                        kind: IssueKind::Rule,
+                        severity: rule.severity,
+                        source: rule.id.clone(),
+                        record_index: Some(idx),
+                        path: problem.path,
+                        value: problem.value.map(shown),
+                        description: rule.message.clone().unwrap_or(problem.description),
+                        suggestion: rule.fix.clone().or(problem.suggestion),
+                    });
+                }
+            }
+        }
+        issues
+    }
+}
+
+/// One failed check inside a record.
+struct Problem<'a> {
+    path: Option<String>,
+    value: Option<&'a Value>,
+    description: String,
+    suggestion: Option<String>,
+}
+
+impl<'a> Problem<'a> {
+    fn new(path: &str, value: Option<&'a Value>, description: String, suggestion: String) -> Problem<'a> {
+        Problem { path: Some(path.to_string()), value, description, suggestion: Some(suggestion) }
+    }
+}
+
+impl CompiledRule {
+    /// What is wrong with `record` under this rule, one entry per problem.
+    fn check<'a>(&self, record: &'a BiblioRecord) -> Vec<Problem<'a>> {
+        let field = self.rule.field.as_deref().unwrap_or_default();
+        let Some(path) = &self.path else {
+            return match self.rule.check {
+                Check::Object if !record.is_object() => vec![Problem {
+                    path: None,
+                    value: None,
+                    description: "record is not an object".to_string(),
+                    suggestion: Some("Wrap the record in an object or remove it".to_string()),
+                }],
+                _ => Vec::new(),
+            };
+        };
+
+        let values = path.resolve(record);
+        if let Check::Required = self.rule.check {
+            return if values.iter().any(|v| is_present(v)) {
+                Vec::new()
+            } else {
+                vec![Problem::new(field, values.first().copied(),
+                    format!("{} is missing or empty", field),
+                    format!("Add a value for {}", field))]
+            };
+        }
+
+        let mut problems = Vec::new();
+        match &self.rule.check {
+            Check::Object => {
+                for value in &values {
+                    if !value.is_object() {
+                        problems.push(Problem::new(field, Some(value),
+                            format!("{} = {} is not an object", field, shown(value)),
+                            format!("Turn {} into an object", field)));
+                    }
+                }
+            }
+            Check::Required => {}
+            Check::AllowedValues { values: allowed } => {
+                for (path, value) in elements(field, &values) {
+                    let text = extract_searchable_text(value);
+                    let is_allowed = allowed.iter().any(|a| a == value || a.as_str() == Some(text.as_str()));
+                    if !is_allowed {
+                        problems.push(Problem::new(&path, Some(value),
+                            format!("{} = {} is not an allowed value", field, shown(value)),
+                            closest_allowed(&text, allowed)));
+                    }
+                }
+            }
+            Check::Pattern { regex } => {
+                let compiled = self.regex.as_ref().expect("pattern rules have a regex");
+                for (path, value) in elements(field, &values) {
+                    let text = extract_searchable_text(value);
+                    if !compiled.is_match(&text) {
+                        let suggestion = if compiled.is_match(text.trim()) {
+                            "Trim the surrounding whitespace".to_string()
+                        } else {
+                            format!("Change the value to match /{}/", regex)
+                        };
+                        problems.push(Problem::new(&path, Some(value),
+                            format!("{} = {} does not match /{}/", field, shown(value), regex),
+                            suggestion));
+                    }
+                }
+            }
+            Check::Length { min, max } => {
+                for value in &values {
+                    let length = match value {
+                        Value::String(s) => s.chars().count(),
+                        Value::Array(arr) => arr.len(),
+                        _ => continue,
+                    };
+                    if min.is_some_and(|min| length < min) || max.is_some_and(|max| length > max) {
+                        let direction = if min.is_some_and(|min| length < min) { "Lengthen" } else { "Shorten" };
+                        problems.push(Problem::new(field, Some(value),
+                            format!("{} has length {} (allowed {})", field, length, bounds(*min, *max)),
+                            format!("{} {} to {}", direction, field, bounds(*min, *max))));
+                    }
+                }
+            }
+            Check::Range { min, max } => {
+                for (path, value) in elements(field, &values) {
+                    let mut numbers = Vec::new();
+                    if !collect_numbers(value, &mut numbers) {
+                        problems.push(Problem::new(&path, Some(value),
+                            format!("{} = {} is not a number", field, shown(value)),
+                            format!("Use a number in {}", bounds(*min, *max))));
+                        continue;
+                    }
+                    for n in numbers {
+                        if min.is_some_and(|min| n < min) || max.is_some_and(|max| n > max) {
+                            problems.push(Problem::new(&path, Some(value),
+                                format!("{} = {} is out of range ({})", field, n, bounds(*min, *max)),
+                                format!("Use a number in {}", bounds(*min, *max))));
+                        }
+                    }
+                }
+            }
+            Check::Compare { op, other } => {
+                let other_values = self.other.as_ref().map(|p| p.resolve(record)).unwrap_or_default();
+                if let (Some(left), Some(right)) = (values.first(), other_values.first()) {
+                    if compare(left, right).is_some_and(|ordering| !op.holds(ordering)) {
+                        problems.push(Problem::new(field, Some(left),
+                            format!("{} = {} is not {} {} = {}",
+                                field, shown(left), op.symbol(), other, shown(right)),
+                            format!("Check {} and {}; one of them is probably wrong", field, other)));
+                    }
+                }
+            }
+        }
+        problems
+    }
+}
+
+/// Present means not null, not an empty or blank string, not an empty array.
+fn is_present(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::String(s) => !s.trim().is_empty(),
+        Value::Array(arr) => arr.iter().any(is_present),
+        _ => true,
+    }
+}
+
+/// The values with arrays opened up into their elements, each with its path.
+fn elements<'a>(field: &str, values: &[&'a Value]) -> Vec<(String, &'a Value)> {
+    let mut out = Vec::new();
+    for value in values {
+        match value {
+            Value::Array(arr) => {
+                out.extend(arr.iter().filter(|item| !item.is_null()).map(|item| (element_path(field), item)))
+            }
+            Value::Null => {}
+            _ => out.push((field.to_string(), *value)),
+        }
+    }
+    out
+}
+
+/// Suggests the allowed value closest to `text`: same text ignoring case
+/// and surrounding whitespace, else the list of allowed values.
+fn closest_allowed(text: &str, allowed: &[Value]) -> String {
+    let normalized = text.trim().to_lowercase();
+    let texts: Vec<String> = allowed.iter().map(extract_searchable_text).collect();
+    if let Some(exact) = texts.iter().find(|t| t.trim().to_lowercase() == normalized) {
+        return format!("Change to \"{}\"", exact);
+    }
+    let mut listed = texts.iter().take(8).cloned().collect::<Vec<_>>().join(", ");
+    if texts.len() > 8 {
+        listed.push_str(", ...");
+    }
+    format!("Use one of: {}", listed)
+}
+
+/// Orders two values, numerically when both are numbers. `None` when either
+/// side is null or empty: a missing value is neither smaller nor larger, and
+/// `required` is the check for it.
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    if !is_present(left) || !is_present(right) {
+        return None;
+    }
+    let (mut a, mut b) = (Vec::new(), Vec::new());
+    if collect_numbers(left, &mut a) && collect_numbers(right, &mut b) {
+        if let (Some(a), Some(b)) = (a.first(), b.first()) {
+            return Some(a.total_cmp(b));
+        }
+    }
+    Some(extract_searchable_text(left).cmp(&extract_searchable_text(right)))
+}
+
+fn bounds<T: std::fmt::Display>(min: Option<T>, max: Option<T>) -> String {
+    match (min, max) {
+        (Some(min), Some(max)) => format!("{}..{}", min, max),
+        (Some(min), None) => format!(">= {}", min),
+        (None, Some(max)) => format!("<= {}", max),
+        (None, None) => "any".to_string(),
+    }
+}
+
+fn shown(value: &Value) -> String {
+    format!("\"{}\"", get_display_value(value, 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn failures(check: Check, records: &[Value]) -> Vec<usize> {
+        let rule = Rule {
+            id: "test".to_string(),
+            field: Some("end_year".to_string()),
+            message: None,
+            severity: Severity::Warning,
+            fix: None,
+            check,
+        };
+        let rules = RuleSet::compile("test".to_string(), None, vec![rule]).unwrap();
+        rules.evaluate(records, &mut |_| true).into_iter().filter_map(|issue| issue.record_index).collect()
+    }
+
+    #[test]
+    fn compare_skips_missing_and_null_values() {
+        let check = || Check::Compare { op: CompareOp::GreaterOrEqual, other: "start_year".to_string() };
+        let records = [
+            json!({"start_year": 1900, "end_year": 1850}),
+            json!({"start_year": 1900, "end_year": 1950}),
+            json!({"start_year": 1900, "end_year": null}),
+            json!({"start_year": null, "end_year": 1850}),
+            json!({"start_year": 1900, "end_year": ""}),
+            json!({"start_year": 1900}),
+            json!({"start_year": "1900", "end_year": "999"}),
+        ];
+        assert_eq!(failures(check(), &records), vec![0, 6]);
+    }
+
+    #[test]
+    fn compare_falls_back_to_text() {
+        let check = Check::Compare { op: CompareOp::Less, other: "start_year".to_string() };
+        let records = [json!({"start_year": "b", "end_year": "a"}), json!({"start_year": "a", "end_year": "b"})];
+        assert_eq!(failures(check, &records), vec![1]);
+    }
+
+    #[test]
+    fn range_skips_null_elements() {
+        let check = Check::Range { min: Some(1450.0), max: Some(2030.0) };
+        let records = [json!({"end_year": [1500, null]}), json!({"end_year": null}), json!({"end_year": [1400, "x"]})];
+        assert_eq!(failures(check, &records), vec![2, 2]);
+    }
+}
@@ -0,0 +1,154 @@
+//! Validation of every record against a user-supplied JSON Schema.
+
+use jsonschema::{ValidationError, Validator};
+use serde_json::Value;
+use super::{BiblioRecord, IssueKind, ProgressFn, RECORDS_PER_PROGRESS_UPDATE, RecordIssue, Severity};
+use super::paths::pointer_to_path;
+
+/// Violations beyond this many are dropped (and reported once).
+const MAX_VIOLATIONS: usize = 100_000;
+
+pub struct ValidationSchema {
+    pub path: String,
+    schema: Value,
+    validator: Validator,
+}
+
+impl ValidationSchema {
+    /// Reads and compiles the schema at `path`. Formats (`date`, `email`,
+    /// ...) are asserted rather than only annotated.
+    pub fn load(path: &str) -> Result<ValidationSchema, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        let schema: Value = serde_json::from_str(&text).map_err(|e| format!("{} is not valid JSON: {}", path, e))?;
+        let validator = jsonschema::options()
+            .should_validate_formats(true)
+            .build(&schema)
+            .map_err(|e| format!("{} is not a valid JSON Schema: {}", path, e))?;
+        Ok(ValidationSchema { path: path.to_string(), schema, validator })
+    }
+
+    /// One issue per violation, in record order.
+    pub fn validate(&self, records: &[BiblioRecord], progress: &mut ProgressFn) -> Vec<RecordIssue> {
+        let mut issues = Vec::new();
+        for (idx, record) in records.iter().enumerate() {
+            if idx.is_multiple_of(RECORDS_PER_PROGRESS_UPDATE) && !progress(idx) {
+                break;
+            }
+            for error in self.validator.iter_errors(record) {
+                if issues.len() == MAX_VIOLATIONS {
+                    issues.push(RecordIssue {
+                        kind: IssueKind::Schema,
+                        severity: Severity::Info,
+                        source: "Validation limited".to_string(),
+                        record_index: None,
+                        path: None,
+                        value: None,
+                        description: format!("Stopped after {} schema violations (at record #{})", MAX_VIOLATIONS, idx + 1),
+                        suggestion: Some("Fix the most common violations and validate again".to_string()),
+                    });
+                    return issues;
+                }
+
+                let schema_path = error.schema_path().as_str().to_string();
+                let expected = self.keyword_value(&error).map(compact);
+                let path = pointer_to_path(record, error.instance_path().as_str());
+                issues.push(RecordIssue {
+                    kind: IssueKind::Schema,
+                    severity: Severity::Error,
+                    source: if schema_path.is_empty() { "/".to_string() } else { schema_path },
+                    record_index: Some(idx),
+                    path: Some(path).filter(|p| !p.is_empty()),
+                    value: Some(compact(error.instance())),
+                    description: error.to_string(),
+                    suggestion: expected.map(|expected| format!("Expected {}: {}", error.kind().keyword(), expected)),
+                });
+            }
+        }
+        issues
+    }
+
+    /// The value of the schema keyword that failed. Its location is relative
+    /// to the schema resource holding it: the root, or a subschema with its
+    /// own `$id` (`$ref`s within the document are already resolved). `None`
+    /// if that resource is not part of the schema document.
+    fn keyword_value(&self, error: &ValidationError) -> Option<&Value> {
+        let resource = match error.absolute_keyword_location() {
+            Some(uri) => find_resource(&self.schema, uri.as_str().split('#').next().unwrap_or_default())?,
+            None => &self.schema,
+        };
+        resource.pointer(error.schema_path().as_str())
+    }
+}
+
+/// The subschema whose `$id` is `base`, relative ids matched by their end.
+fn find_resource<'s>(schema: &'s Value, base: &str) -> Option<&'s Value> {
+    match schema {
+        Value::Object(obj) => {
+            let id = obj.get("$id").and_then(|id| id.as_str()).map(|id| id.trim_end_matches('#'));
+            if id.is_some_and(|id| !id.is_empty() && (base == id || base.ends_with(&format!("/{}", id)))) {
+                return Some(schema);
+            }
+            obj.values().find_map(|child| find_resource(child, base))
+        }
+        Value::Array(arr) => arr.iter().find_map(|child| find_resource(child, base)),
+        _ => None,
+    }
+}
+
+/// A value as compact JSON, shortened for display.
+fn compact(value: &Value) -> String {
+    let text = value.to_string();
+    if text.chars().count() > 120 {
+        format!("{}...", text.chars().take(120).collect::<String>())
+    } else {
+        text
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn suggestions(schema: Value, record: Value) -> Vec<Option<String>> {
+        let validator = jsonschema::options().build(&schema).unwrap();
+        let schema = ValidationSchema { path: String::new(), schema, validator };
+        schema.validate(&[record], &mut |_| true).into_iter().map(|issue| issue.suggestion).collect()
+    }
+
+    #[test]
+    fn expected_value_follows_local_refs() {
+        let schema = json!({
+            "$defs": {"year": {"type": "integer", "minimum": 1000}, "name": {"$ref": "#/$defs/text"}, "text": {"type": "string"}},
+            "properties": {"year": {"$ref": "#/$defs/year"}, "author": {"$ref": "#/$defs/name"}},
+        });
+        let mut found = suggestions(schema, json!({"year": 999, "author": 5}));
+        found.sort();
+        assert_eq!(found, vec![Some("Expected minimum: 1000".to_string()), Some("Expected type: \"string\"".to_string())]);
+    }
+
+    #[test]
+    fn expected_value_inside_embedded_resources() {
+        let schema = json!({
+            "type": "object",
+            "$defs": {"isbn": {"$id": "https://example.com/isbn", "type": "string", "$defs": {"short": {"maxLength": 4}}}},
+            "properties": {"isbn": {"$ref": "https://example.com/isbn"}, "code": {"$ref": "https://example.com/isbn#/$defs/short"}},
+        });
+        let mut found = suggestions(schema, json!({"isbn": 12, "code": "12345"}));
+        found.sort();
+        assert_eq!(found, vec![Some("Expected maxLength: 4".to_string()), Some("Expected type: \"string\"".to_string())]);
+    }
+
+    #[test]
+    fn issues_point_at_the_record_path() {
+        let schema = json!({"properties": {"authors": {"items": {"required": ["name"]}}}});
+        let validator = jsonschema::options().build(&schema).unwrap();
+        let schema = ValidationSchema { path: String::new(), schema, validator };
+        let issues = schema.validate(&[json!({"authors": [{"name": "A"}, {}]}), json!({})], &mut |_| true);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].record_index, Some(0));
+        assert_eq!(issues[0].path.as_deref(), Some("authors[]"));
+        assert_eq!(issues[0].source, "/properties/authors/items/required");
+    }
+}
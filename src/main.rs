@@ -8,6 +8,9 @@ use app::BiblioAnalyzerApp;
 
 impl eframe::App for BiblioAnalyzerApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll_loading();
+        self.poll_checks();
+        self.poll_export();
         ui::render_main_ui(self, ctx);
     }
 }
@@ -1,6 +1,8 @@
-use serde_json::Value;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::fs;
 use std::path::PathBuf;
+use crate::data::duplicates::DuplicateOptions;
 
 fn get_prefs_path() -> PathBuf {
     let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
@@ -10,22 +12,119 @@ fn get_prefs_path() -> PathBuf {
     path
 }
 
+fn load_prefs() -> Value {
+    fs::read_to_string(get_prefs_path())
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .filter(|prefs| prefs.is_object())
+        .unwrap_or_else(|| serde_json::json!({}))
+}
+
+fn save_prefs(prefs: &Value) {
+    fs::write(get_prefs_path(), prefs.to_string()).ok();
+}
+
+/// The settings object of one file, created if missing. Non-object values
+/// left by an old or hand-edited prefs file are replaced, since indexing
+/// into them would panic.
+fn file_prefs<'a>(prefs: &'a mut Value, file: &str) -> &'a mut Map<String, Value> {
+    let files = as_object(prefs).entry("files").or_insert(Value::Null);
+    as_object(as_object(files).entry(file).or_insert(Value::Null))
+}
+
+fn as_object(value: &mut Value) -> &mut Map<String, Value> {
+    if !value.is_object() {
+        *value = Value::Object(Map::new());
+    }
+    match value {
+        Value::Object(map) => map,
+        _ => unreachable!("replaced by an object above"),
+    }
+}
+
 pub fn save_last_file_path(path: &str) {
-    let prefs_path = get_prefs_path();
-    let prefs = serde_json::json!({"last_file": path});
-    fs::write(prefs_path, prefs.to_string()).ok();
+    let mut prefs = load_prefs();
+    prefs["last_file"] = Value::from(path);
+    save_prefs(&prefs);
 }
 
 pub fn load_last_file_path() -> Option<String> {
-    let prefs_path = get_prefs_path();
-    if let Ok(content) = fs::read_to_string(prefs_path) {
-        if let Ok(prefs) = serde_json::from_str::<Value>(&content) {
-            if let Some(last_file) = prefs.get("last_file").and_then(|v| v.as_str()) {
-                if PathBuf::from(last_file).exists() {
-                    return Some(last_file.to_string());
-                }
-            }
+    let prefs = load_prefs();
+    if let Some(last_file) = prefs.get("last_file").and_then(|v| v.as_str()) {
+        if PathBuf::from(last_file).exists() {
+            return Some(last_file.to_string());
         }
     }
     None
-}
\ No newline at end of file
+}
+
+/// Remembers which array inside a wrapped document holds the records.
+pub fn save_records_pointer(file: &str, pointer: &str) {
+    let mut prefs = load_prefs();
+    file_prefs(&mut prefs, file).insert("records_pointer".to_string(), Value::from(pointer));
+    save_prefs(&prefs);
+}
+
+pub fn load_records_pointer(file: &str) -> Option<String> {
+    load_prefs()
+        .get("files")
+        .and_then(|files| files.get(file))
+        .and_then(|f| f.get("records_pointer"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+/// Columns and sort order of the Browse table for one file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrowseLayout {
+    pub columns: Vec<String>,
+    /// Column path and whether it sorts descending
+    pub sort: Option<(String, bool)>,
+}
+
+pub fn save_browse_layout(file: &str, layout: &BrowseLayout) {
+    let mut prefs = load_prefs();
+    file_prefs(&mut prefs, file).insert("browse_layout".to_string(), serde_json::to_value(layout).unwrap_or(Value::Null));
+    save_prefs(&prefs);
+}
+
+pub fn load_browse_layout(file: &str) -> Option<BrowseLayout> {
+    load_prefs()
+        .get("files")
+        .and_then(|files| files.get(file))
+        .and_then(|f| f.get("browse_layout"))
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+}
+
+/// Remembers the rule pack used to check one data file.
+pub fn save_rule_pack_path(file: &str, pack: Option<&str>) {
+    let mut prefs = load_prefs();
+    file_prefs(&mut prefs, file).insert("rule_pack".to_string(), pack.map(Value::from).unwrap_or(Value::Null));
+    save_prefs(&prefs);
+}
+
+pub fn load_rule_pack_path(file: &str) -> Option<String> {
+    load_prefs()
+        .get("files")
+        .and_then(|files| files.get(file))
+        .and_then(|f| f.get("rule_pack"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+/// Remembers the duplicate detection settings last run on one file, so the
+/// check on load uses them too.
+pub fn save_duplicate_options(file: &str, options: &DuplicateOptions) {
+    let mut prefs = load_prefs();
+    let options = serde_json::to_value(options).unwrap_or(Value::Null);
+    file_prefs(&mut prefs, file).insert("duplicates".to_string(), options);
+    save_prefs(&prefs);
+}
+
+pub fn load_duplicate_options(file: &str) -> Option<DuplicateOptions> {
+    load_prefs()
+        .get("files")
+        .and_then(|files| files.get(file))
+        .and_then(|f| f.get("duplicates"))
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+}
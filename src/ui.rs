@@ -1,5 +1,8 @@
 pub mod browse;
+pub mod crosstab;
 pub mod details;
+pub mod duplicates;
+pub mod export;
 pub mod facets;
 pub mod patterns;
 pub mod other_tabs;
@@ -7,6 +10,8 @@ pub mod other_tabs;
 use eframe::egui;
 use crate::app::BiblioAnalyzerApp;
 use crate::data::Tab;
+use crate::data::compression::COMPRESSED_EXTENSIONS;
+use crate::data::loader::InputFormat;
 
 pub fn render_main_ui(app: &mut BiblioAnalyzerApp, ctx: &egui::Context) {
     egui::CentralPanel::default().show(ctx, |ui| {
@@ -20,32 +25,97 @@ pub fn render_main_ui(app: &mut BiblioAnalyzerApp, ctx: &egui::Context) {
 
             if ui.button("Browse...").clicked() {
                 if let Some(path) = rfd::FileDialog::new()
-                    .add_filter("JSON", &["json"])
+                    .add_filter("JSON", &["json", "jsonl", "ndjson", "gz", "zst", "zstd", "zip"])
+                    .add_filter("JSON Lines", &["jsonl", "ndjson"])
+                    .add_filter("Compressed", &COMPRESSED_EXTENSIONS)
                     .add_filter("All files", &["*"])
                     .pick_file()
                 {
                     app.file_path = path.display().to_string();
-                    app.load_file(&app.file_path.clone());
+                    app.load_file(ctx, &app.file_path.clone());
                 }
             }
 
+            egui::ComboBox::from_id_salt("input_format")
+                .selected_text(app.input_format.label())
+                .show_ui(ui, |ui| {
+                    for format in InputFormat::all() {
+                        ui.selectable_value(&mut app.input_format, format, format.label());
+                    }
+                });
+
             let load_button = ui.add_enabled(
-                !app.loading && !app.file_path.is_empty(),
-                egui::Button::new(if app.loading { "Loading..." } else { "Load File" })
+                !app.is_loading() && !app.file_path.is_empty(),
+                egui::Button::new(if app.is_loading() { "Loading..." } else { "Load File" })
             );
 
             if load_button.clicked() {
-                app.load_file(&app.file_path.clone());
+                app.load_file(ctx, &app.file_path.clone());
             }
         });
 
+        let is_document = app.input_format == InputFormat::JsonDocument
+            || !app.records_pointer.is_empty()
+            || !app.pointer_candidates.is_empty();
+        if is_document {
+            render_records_pointer_row(app, ui, ctx);
+        }
+
         if !app.error_message.is_empty() {
             ui.colored_label(egui::Color32::from_rgb(200, 100, 80), &app.error_message);
         }
 
-        if app.loading {
-            ui.spinner();
-            ui.label("Loading and analyzing structure...");
+        if let Some(job) = &app.load_job {
+            let progress = &job.progress;
+            let mut cancel_clicked = false;
+
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.add(egui::ProgressBar::new(progress.fraction())
+                    .desired_width(400.0)
+                    .show_percentage());
+
+                cancel_clicked = ui.add_enabled(!job.is_cancelling(), egui::Button::new("✖ Cancel"))
+                    .clicked();
+            });
+
+            ui.label(format!(
+                "{}: {:.1} / {:.1} MB read, {} records parsed",
+                progress.phase.label(),
+                progress.bytes_read as f64 / 1_048_576.0,
+                progress.total_bytes as f64 / 1_048_576.0,
+                progress.records_parsed,
+            ));
+
+            if cancel_clicked {
+                app.cancel_loading();
+            }
+        }
+
+        if let Some(job) = &app.check_job {
+            let progress = &job.progress;
+            let mut cancel_clicked = false;
+
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.add(egui::ProgressBar::new(progress.fraction())
+                    .desired_width(400.0)
+                    .show_percentage());
+
+                cancel_clicked = ui.add_enabled(!job.is_cancelling(), egui::Button::new("✖ Cancel"))
+                    .clicked();
+            });
+
+            ui.label(format!(
+                "{}: {} / {} records",
+                progress.step.label(),
+                progress.records_checked,
+                progress.total_records,
+            ));
+
+            if cancel_clicked {
+                app.cancel_checks();
+            }
         }
 
         if !app.records.is_empty() {
@@ -57,9 +127,11 @@ pub fn render_main_ui(app: &mut BiblioAnalyzerApp, ctx: &egui::Context) {
                 ui.selectable_value(&mut app.current_tab, Tab::Schema, "🔍 Schema");
                 ui.selectable_value(&mut app.current_tab, Tab::Facets, "📊 Facets");
                 ui.selectable_value(&mut app.current_tab, Tab::Patterns, "🔎 Patterns");
+                ui.selectable_value(&mut app.current_tab, Tab::Crosstab, "🧮 Crosstab");
                 ui.selectable_value(&mut app.current_tab, Tab::Statistics, "📈 Statistics");
                 ui.selectable_value(&mut app.current_tab, Tab::Issues,
                     format!("⚠ Issues ({})", app.issues.len()));
+                ui.selectable_value(&mut app.current_tab, Tab::Duplicates, "👯 Duplicates");
                 ui.selectable_value(&mut app.current_tab, Tab::Details, "📄 Details");
             });
 
@@ -69,12 +141,84 @@ pub fn render_main_ui(app: &mut BiblioAnalyzerApp, ctx: &egui::Context) {
             match app.current_tab {
                 Tab::Browse => browse::render_browse_tab(app, ui),
                 Tab::Schema => other_tabs::render_schema_tab(app, ui),
-                Tab::Facets => facets::render_facets_tab(ui, &mut app.facets_state, &app.records, &app.top_level_fields),
-                Tab::Patterns => patterns::render_patterns_tab(ui, &mut app.patterns_state, &app.facets_state),
+                Tab::Facets => {
+                    let checking = app.is_checking();
+                    let action = facets::render_facets_tab(
+                        ui, &mut app.facets_state, &app.records, &app.field_schema, &app.filters, checking);
+                    match action {
+                        Some(facets::FacetsAction::Filter(filters)) => {
+                            for filter in filters {
+                                app.add_filter(filter);
+                            }
+                        }
+                        Some(facets::FacetsAction::MergeClusters) => app.merge_clusters(ctx),
+                        Some(facets::FacetsAction::SetRange { path, range }) => app.set_range_filter(&path, range),
+                        None => {}
+                    }
+                }
+                Tab::Patterns => {
+                    let filter = patterns::render_patterns_tab(ui, &mut app.patterns_state, &app.facets_state);
+                    if let Some(filter) = filter {
+                        app.add_filter(filter);
+                    }
+                }
+                Tab::Crosstab => {
+                    let filters = crosstab::render_crosstab_tab(
+                        ui, &mut app.crosstab_state, &app.records, &app.field_schema);
+                    for filter in filters.into_iter().flatten() {
+                        app.add_filter(filter);
+                    }
+                }
                 Tab::Statistics => other_tabs::render_statistics_tab(app, ui),
                 Tab::Issues => other_tabs::render_issues_tab(app, ui),
+                Tab::Duplicates => {
+                    let checking = app.is_checking();
+                    let action = duplicates::render_duplicates_tab(
+                        ui, &mut app.duplicates_state, &app.records, &app.field_schema, checking);
+                    match action {
+                        Some(duplicates::DuplicatesAction::Find) => app.find_duplicates(ctx),
+                        Some(duplicates::DuplicatesAction::Open(idx)) => app.open_record(idx, None),
+                        None => {}
+                    }
+                }
                 Tab::Details => details::render_details_tab(app, ui),
             }
         }
     });
-}
\ No newline at end of file
+
+    if app.export_state.open {
+        export::render_export_window(app, ctx);
+    }
+}
+
+fn render_records_pointer_row(app: &mut BiblioAnalyzerApp, ui: &mut egui::Ui, ctx: &egui::Context) {
+    ui.horizontal(|ui| {
+        ui.label("Records array:");
+
+        let selected = if app.records_pointer.is_empty() {
+            "(auto-detect)".to_string()
+        } else {
+            app.records_pointer.clone()
+        };
+        egui::ComboBox::from_id_salt("records_pointer")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut app.records_pointer, String::new(), "(auto-detect)");
+                for candidate in &app.pointer_candidates {
+                    ui.selectable_value(&mut app.records_pointer, candidate.pointer.clone(), candidate.label());
+                }
+            });
+
+        ui.add(egui::TextEdit::singleline(&mut app.records_pointer)
+            .hint_text("/response/docs")
+            .desired_width(200.0));
+
+        let reload = ui.add_enabled(
+            !app.is_loading() && !app.file_path.is_empty(),
+            egui::Button::new("🔄 Reload"),
+        );
+        if reload.clicked() {
+            app.reload_with_pointer(ctx);
+        }
+    });
+}
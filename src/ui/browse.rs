@@ -1,11 +1,13 @@
 use eframe::egui;
+use egui_extras::{Column, TableBuilder};
 use crate::app::BiblioAnalyzerApp;
-use crate::data::{Tab, get_display_value};
 
 pub fn render_browse_tab(app: &mut BiblioAnalyzerApp, ui: &mut egui::Ui) {
     ui.horizontal(|ui| {
         ui.label("🔎 Search:");
-        let response = ui.text_edit_singleline(&mut app.search_query);
+        let response = ui.add(egui::TextEdit::singleline(&mut app.search_query)
+            .desired_width(400.0)
+            .hint_text("title:war  year>=1800  has:isbn  (a OR b) -c  /regex/i"));
         if response.changed() {
             app.apply_filter();
         }
@@ -13,87 +15,227 @@ pub fn render_browse_tab(app: &mut BiblioAnalyzerApp, ui: &mut egui::Ui) {
             .color(egui::Color32::from_rgb(200, 160, 100)));
     });
 
-    let total_records = app.filtered_records.len();
-    let total_pages = (total_records + app.page_size - 1).max(1) / app.page_size;
-
-    ui.add_space(5.0);
-    ui.horizontal(|ui| {
-        ui.label("Page size:");
-        ui.add(egui::DragValue::new(&mut app.page_size).range(10..=1000));
-
-        ui.separator();
-
-        if ui.button("⏮ First").clicked() {
-            app.page = 0;
-        }
-        if ui.button("◀ Prev").clicked() && app.page > 0 {
-            app.page -= 1;
-        }
-
-        ui.label(format!("Page {} of {}", app.page + 1, total_pages));
-
-        if ui.button("Next ▶").clicked() && app.page < total_pages.saturating_sub(1) {
-            app.page += 1;
-        }
-        if ui.button("Last ⏭").clicked() {
-            app.page = total_pages.saturating_sub(1);
-        }
-
-        ui.separator();
+    if let Some(error) = &app.query_error {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(format!("⚠ {}", error))
+                .color(egui::Color32::from_rgb(200, 100, 80)));
+            let query: String = app.search_query.chars().take(error.position).collect();
+            let rest: String = app.search_query.chars().skip(error.position).collect();
+            ui.label(egui::RichText::new(query).monospace());
+            ui.label(egui::RichText::new(format!("⮜ {}", rest)).monospace()
+                .color(egui::Color32::from_rgb(200, 100, 80)));
+        });
+    }
 
-        let start = app.page * app.page_size;
-        let end = (start + app.page_size).min(total_records);
-        ui.label(format!("Showing {}-{} of {}", start + 1, end, total_records));
-    });
+    if !app.filters.is_empty() {
+        render_filter_chips(app, ui);
+    }
 
+    ui.add_space(5.0);
+    render_column_chooser(app, ui);
     ui.separator();
 
-    egui::ScrollArea::vertical().show(ui, |ui| {
-        let display_fields: Vec<_> = app.top_level_fields.iter().take(5).cloned().collect();
-
-        // Header row
-        ui.horizontal(|ui| {
-            ui.label(egui::RichText::new("Index").strong());
-            for field in &display_fields {
-                ui.label(egui::RichText::new(field).strong());
+    render_records_table(app, ui);
+}
+
+/// The records as a table with one row per filtered record. Only the visible
+/// rows are laid out, so scrolling stays smooth on large files.
+fn render_records_table(app: &mut BiblioAnalyzerApp, ui: &mut egui::Ui) {
+    let mut sort_clicked = None;
+    let mut open_record = None;
+
+    let mut table = TableBuilder::new(ui)
+        .striped(true)
+        .resizable(true)
+        .sense(egui::Sense::click())
+        .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+        .column(Column::exact(70.0));
+    for _ in &app.browse_state.columns {
+        table = table.column(Column::initial(180.0).at_least(40.0).clip(true));
+    }
+    table = table.column(Column::remainder());
+
+    let state = &app.browse_state;
+    table
+        .header(24.0, |mut header| {
+            header.col(|ui| {
+                ui.label(egui::RichText::new("Record").strong());
+            });
+            for (i, column) in state.columns.iter().enumerate() {
+                header.col(|ui| {
+                    let arrow = match state.sort {
+                        Some((sorted, false)) if sorted == i => " ⏶",
+                        Some((sorted, true)) if sorted == i => " ⏷",
+                        _ => "",
+                    };
+                    let label = egui::RichText::new(format!("{}{}", column.path, arrow)).strong();
+                    if ui.add(egui::Button::new(label).frame(false))
+                        .on_hover_text(format!("{}\nClick to sort", column.path))
+                        .clicked()
+                    {
+                        sort_clicked = Some(i);
+                    }
+                });
             }
+            header.col(|_| {});
+        })
+        .body(|body| {
+            body.rows(20.0, app.filtered_records.len(), |mut row| {
+                let idx = app.filtered_records[row.index()];
+                let record = &app.records[idx];
+                row.set_selected(app.selected_record == Some(idx));
+
+                row.col(|ui| {
+                    ui.label(format!("#{}", idx + 1));
+                });
+                for column in &state.columns {
+                    row.col(|ui| match column.display(record, 200) {
+                        Some(text) => {
+                            ui.add(egui::Label::new(text).truncate().selectable(false));
+                        }
+                        None => {
+                            ui.add(egui::Label::new(egui::RichText::new("—")
+                                .color(egui::Color32::from_rgb(180, 170, 150))).selectable(false));
+                        }
+                    });
+                }
+                row.col(|_| {});
+
+                if row.response().clicked() {
+                    open_record = Some(idx);
+                }
+            });
         });
 
-        ui.separator();
+    if let Some(i) = sort_clicked {
+        app.browse_state.toggle_sort(i);
+        app.browse_layout_changed();
+    }
+    if let Some(idx) = open_record {
+        app.open_record(idx, None);
+    }
+}
 
-        let start = app.page * app.page_size;
-        let end = (start + app.page_size).min(app.filtered_records.len());
+/// Menu for choosing, ordering and removing columns. Any field path from the
+/// schema can be picked, or typed in directly.
+fn render_column_chooser(app: &mut BiblioAnalyzerApp, ui: &mut egui::Ui) {
+    let mut changed = false;
 
-        for &idx in &app.filtered_records[start..end] {
-            let record = &app.records[idx];
-            let is_selected = app.selected_record == Some(idx);
+    ui.horizontal(|ui| {
+        ui.menu_button("🗂 Columns", |ui| {
+            ui.set_min_width(280.0);
+            ui.label(egui::RichText::new("Shown").strong());
+            let mut remove = None;
+            let mut move_column = None;
+            let column_count = app.browse_state.columns.len();
+            for (i, column) in app.browse_state.columns.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(i > 0, egui::Button::new("⏴").small()).clicked() {
+                        move_column = Some((i, -1));
+                    }
+                    if ui.add_enabled(i + 1 < column_count, egui::Button::new("⏵").small()).clicked() {
+                        move_column = Some((i, 1));
+                    }
+                    if ui.small_button("✖").on_hover_text("Remove column").clicked() {
+                        remove = Some(column.path.clone());
+                    }
+                    ui.label(&column.path);
+                });
+            }
+            if let Some((i, offset)) = move_column {
+                app.browse_state.move_column(i, offset);
+                changed = true;
+            }
+            if let Some(path) = remove {
+                app.browse_state.remove_column(&path);
+                changed = true;
+            }
 
-            let response = ui.horizontal(|ui| {
-                if is_selected {
-                    let rect = ui.available_rect_before_wrap();
-                    ui.painter().rect_filled(
-                        rect,
-                        4.0,
-                        egui::Color32::from_rgb(120, 100, 70),
-                    );
+            ui.separator();
+            ui.horizontal(|ui| {
+                let response = ui.add(egui::TextEdit::singleline(&mut app.browse_state.new_column)
+                    .desired_width(180.0)
+                    .hint_text("authors[].name"));
+                let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.button("Add").clicked() || submitted {
+                    let path = app.browse_state.new_column.trim().to_string();
+                    app.browse_state.add_column(&path);
+                    app.browse_state.new_column.clear();
+                    changed = true;
+                }
+            });
+
+            ui.separator();
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                for field in &app.field_schema {
+                    ui.horizontal(|ui| {
+                        ui.add_space(field.depth as f32 * 12.0);
+                        let mut shown = app.browse_state.has_column(&field.name);
+                        if ui.checkbox(&mut shown, &field.name).changed() {
+                            if shown {
+                                app.browse_state.add_column(&field.name);
+                            } else {
+                                app.browse_state.remove_column(&field.name);
+                            }
+                            changed = true;
+                        }
+                    });
                 }
+            });
 
-                ui.label(format!("#{}", idx + 1));
+            ui.separator();
+            if ui.button("Reset to default").clicked() {
+                app.browse_state.reset(&app.top_level_fields);
+                changed = true;
+            }
+        });
 
-                if let serde_json::Value::Object(obj) = record {
-                    for field in &display_fields {
-                        let value = obj.get(field).unwrap_or(&serde_json::Value::Null);
-                        ui.label(get_display_value(value, 50));
-                    }
-                }
-            }).response;
+        let export_label = format!("💾 Export {} records...", app.filtered_records.len());
+        if ui.button(export_label).clicked() {
+            app.export_state.open = true;
+        }
 
-            if response.interact(egui::Sense::click()).clicked() {
-                app.selected_record = Some(idx);
-                app.current_tab = Tab::Details;
+        if let Some((i, descending)) = app.browse_state.sort {
+            let direction = if descending { "descending" } else { "ascending" };
+            ui.label(egui::RichText::new(format!("Sorted by {} ({})", app.browse_state.columns[i].path, direction))
+                .color(egui::Color32::from_rgb(180, 170, 150)));
+            if ui.small_button("Unsort").clicked() {
+                app.browse_state.sort = None;
+                changed = true;
             }
+        }
+    });
 
-            ui.add_space(2.0);
+    if changed {
+        app.browse_layout_changed();
+    }
+}
+
+/// Active filters as removable chips. Filters on the same field are ORed,
+/// different fields are ANDed.
+fn render_filter_chips(app: &mut BiblioAnalyzerApp, ui: &mut egui::Ui) {
+    let mut remove = None;
+    let mut clear_all = false;
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label(egui::RichText::new("Filters:").color(egui::Color32::from_rgb(200, 160, 100)));
+        for (i, filter) in app.filters.iter().enumerate() {
+            let chip = egui::Button::new(format!("{}  ✖", filter.label()))
+                .fill(egui::Color32::from_rgb(85, 72, 56))
+                .rounding(10.0);
+            if ui.add(chip).on_hover_text("Remove this filter").clicked() {
+                remove = Some(i);
+            }
+        }
+        if ui.small_button("Clear all").clicked() {
+            clear_all = true;
         }
     });
-}
\ No newline at end of file
+
+    if let Some(i) = remove {
+        app.remove_filter(i);
+    }
+    if clear_all {
+        app.clear_filters();
+    }
+}
@@ -0,0 +1,191 @@
+use eframe::egui;
+use crate::app::CrosstabState;
+use crate::data::{BiblioRecord, FacetMode, FieldInfo};
+use crate::data::crosstab::{CrosstabSort, is_filterable};
+use crate::data::filters::{FilterKind, RecordFilter};
+
+/// Renders the two-field crosstab. Returns the filters for a clicked cell or
+/// total.
+pub fn render_crosstab_tab(
+    ui: &mut egui::Ui,
+    state: &mut CrosstabState,
+    records: &[BiblioRecord],
+    available_fields: &[FieldInfo],
+) -> Option<Vec<RecordFilter>> {
+    ui.heading("🧮 Cross-tabulation");
+    ui.label("Count records for every combination of values in two fields");
+    ui.separator();
+
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.label("Rows:");
+        changed |= field_selector(ui, "crosstab_rows", &mut state.row_field, available_fields);
+        ui.label("Columns:");
+        changed |= field_selector(ui, "crosstab_columns", &mut state.col_field, available_fields);
+
+        if ui.button("⇄ Swap").clicked() {
+            std::mem::swap(&mut state.row_field, &mut state.col_field);
+            changed = true;
+        }
+    });
+    if changed {
+        state.analyze(records);
+    }
+
+    let mut clicked = None;
+    let mut row_sort = None;
+    let mut col_sort = None;
+
+    let Some(table) = &state.analysis else {
+        ui.vertical_centered(|ui| {
+            ui.add_space(100.0);
+            ui.heading("👆 Select a row field and a column field");
+            ui.label("Array elements are counted individually; values beyond the 50 most common are grouped as (other)");
+        });
+        return None;
+    };
+
+    ui.add_space(5.0);
+    ui.label(egui::RichText::new("Click a header to sort, a count to filter the browse list")
+        .italics()
+        .color(egui::Color32::from_rgb(180, 170, 150)));
+    ui.add_space(5.0);
+
+    let max_cell = table.counts.iter().flatten().copied().max().unwrap_or(1).max(1);
+    let arrow = |(sort, descending): (CrosstabSort, bool), key: CrosstabSort| {
+        if sort != key {
+            ""
+        } else if descending {
+            " ⏷"
+        } else {
+            " ⏶"
+        }
+    };
+
+    let filter_for = |path: &str, value: &str| RecordFilter {
+        path: path.to_string(),
+        kind: FilterKind::FacetValue { mode: FacetMode::ExplodeArrays, value: value.to_string() },
+    };
+
+    egui::ScrollArea::both().show(ui, |ui| {
+        egui::Grid::new("crosstab_grid")
+            .striped(true)
+            .min_col_width(50.0)
+            .show(ui, |ui| {
+                // Header row: corner sorts rows by label, column headers sort
+                // rows by that column, "Total" sorts rows by total
+                let corner = format!("{} ↓ / {} →{}", table.row_field, table.col_field,
+                    arrow(state.row_sort, CrosstabSort::Label));
+                if ui.button(egui::RichText::new(corner).strong()).clicked() {
+                    row_sort = Some(CrosstabSort::Label);
+                }
+                for (j, column) in table.columns.iter().enumerate() {
+                    let label = format!("{}{}", truncate(column, 24), arrow(state.row_sort, CrosstabSort::Cell(j)));
+                    if ui.button(egui::RichText::new(label).strong())
+                        .on_hover_text(format!("{}\nSort rows by this column", column))
+                        .clicked()
+                    {
+                        row_sort = Some(CrosstabSort::Cell(j));
+                    }
+                }
+                let total_label = format!("Total{}", arrow(state.row_sort, CrosstabSort::Total));
+                if ui.button(egui::RichText::new(total_label).strong()).clicked() {
+                    row_sort = Some(CrosstabSort::Total);
+                }
+                ui.end_row();
+
+                for (i, row) in table.rows.iter().enumerate() {
+                    let label = format!("{}{}", truncate(row, 40), arrow(state.col_sort, CrosstabSort::Cell(i)));
+                    if ui.button(label)
+                        .on_hover_text(format!("{}\nSort columns by this row", row))
+                        .clicked()
+                    {
+                        col_sort = Some(CrosstabSort::Cell(i));
+                    }
+
+                    for (j, column) in table.columns.iter().enumerate() {
+                        let count = table.counts[i][j];
+                        let filterable = count > 0 && is_filterable(row) && is_filterable(column);
+                        let shade = count as f32 / max_cell as f32;
+                        let fill = egui::Color32::from_rgb(
+                            (65.0 + 135.0 * shade) as u8,
+                            (55.0 + 105.0 * shade) as u8,
+                            (43.0 + 57.0 * shade) as u8,
+                        );
+                        let text = if count == 0 { String::new() } else { count.to_string() };
+                        let button = egui::Button::new(text).fill(fill).min_size(egui::vec2(50.0, 0.0));
+                        if ui.add_enabled(filterable, button)
+                            .on_hover_text(format!("{} = {}\n{} = {}\n{} records", table.row_field, row,
+                                table.col_field, column, count))
+                            .clicked()
+                        {
+                            clicked = Some(vec![filter_for(&table.row_field, row), filter_for(&table.col_field, column)]);
+                        }
+                    }
+
+                    let total = egui::Button::new(egui::RichText::new(table.row_totals[i].to_string()).strong());
+                    if ui.add_enabled(is_filterable(row), total).clicked() {
+                        clicked = Some(vec![filter_for(&table.row_field, row)]);
+                    }
+                    ui.end_row();
+                }
+
+                // Footer: column totals; "Total" sorts columns by total,
+                // the corner by label
+                let footer = format!("Total{}", arrow(state.col_sort, CrosstabSort::Total));
+                if ui.button(egui::RichText::new(footer).strong())
+                    .on_hover_text("Sort columns by total")
+                    .clicked()
+                {
+                    col_sort = Some(CrosstabSort::Total);
+                }
+                for (j, column) in table.columns.iter().enumerate() {
+                    let total = egui::Button::new(egui::RichText::new(table.col_totals[j].to_string()).strong());
+                    if ui.add_enabled(is_filterable(column), total).clicked() {
+                        clicked = Some(vec![filter_for(&table.col_field, column)]);
+                    }
+                }
+                let label = format!("{} records  A-Z{}", table.record_count, arrow(state.col_sort, CrosstabSort::Label));
+                if ui.button(label).on_hover_text("Sort columns by label").clicked() {
+                    col_sort = Some(CrosstabSort::Label);
+                }
+                ui.end_row();
+            });
+    });
+
+    if let Some(sort) = row_sort {
+        state.sort_rows(sort);
+    }
+    if let Some(sort) = col_sort {
+        state.sort_columns(sort);
+    }
+
+    clicked
+}
+
+fn field_selector(ui: &mut egui::Ui, id: &str, selected: &mut Option<String>, fields: &[FieldInfo]) -> bool {
+    let mut changed = false;
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(selected.as_deref().unwrap_or("Choose a field..."))
+        .height(400.0)
+        .show_ui(ui, |ui| {
+            for field in fields {
+                ui.horizontal(|ui| {
+                    ui.add_space(field.depth as f32 * 12.0);
+                    if ui.selectable_label(selected.as_ref() == Some(&field.name), &field.name).clicked() {
+                        *selected = Some(field.name.clone());
+                        changed = true;
+                    }
+                });
+            }
+        });
+    changed
+}
+
+fn truncate(s: &str, max_len: usize) -> String {
+    if s.chars().count() > max_len {
+        format!("{}...", s.chars().take(max_len).collect::<String>())
+    } else {
+        s.to_string()
+    }
+}
@@ -2,19 +2,46 @@ use eframe::egui;
 use serde_json::Value;
 use crate::app::BiblioAnalyzerApp;
 use crate::data::get_display_value;
+use crate::data::extjson::as_ext_scalar;
+use crate::data::paths::{child_key_path, element_path};
 
-pub fn render_details_tab(app: &BiblioAnalyzerApp, ui: &mut egui::Ui) {
+/// Where the tree is and what to look out for while rendering it.
+struct TreeCursor<'a> {
+    row_num: usize,
+    focus: Option<&'a str>,
+    scroll_pending: bool,
+}
+
+pub fn render_details_tab(app: &mut BiblioAnalyzerApp, ui: &mut egui::Ui) {
     if let Some(idx) = app.selected_record {
         if idx < app.records.len() {
             let record = &app.records[idx];
 
             ui.heading(format!("📄 Record #{}", idx + 1));
+            let mut clear_focus = false;
+            if let Some(focus) = &app.details_state.focus_path {
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(format!("Highlighting {}", focus))
+                        .color(egui::Color32::from_rgb(200, 160, 100)));
+                    if ui.small_button("✖").on_hover_text("Clear highlight").clicked() {
+                        clear_focus = true;
+                    }
+                });
+            }
             ui.separator();
 
+            let mut cursor = TreeCursor {
+                row_num: 0,
+                focus: app.details_state.focus_path.as_deref(),
+                scroll_pending: app.details_state.scroll_pending,
+            };
             egui::ScrollArea::vertical().show(ui, |ui| {
-                let mut row_num = 0;
-                render_value_tree(ui, record, 0, &mut row_num);
+                render_value_tree(ui, record, "", 0, &mut cursor);
             });
+            app.details_state.scroll_pending = false;
+            if clear_focus {
+                app.details_state.focus_path = None;
+            }
         }
     } else {
         ui.vertical_centered(|ui| {
@@ -25,21 +52,35 @@ pub fn render_details_tab(app: &BiblioAnalyzerApp, ui: &mut egui::Ui) {
     }
 }
 
-fn render_value_tree(ui: &mut egui::Ui, value: &Value, depth: usize, row_num: &mut usize) {
+/// Row background: striped, or highlighted for the focused path (which is
+/// scrolled into view once).
+fn row_background(ui: &mut egui::Ui, path: &str, cursor: &mut TreeCursor) -> egui::Color32 {
+    cursor.row_num += 1;
+    if cursor.focus == Some(path) {
+        if cursor.scroll_pending {
+            ui.scroll_to_cursor(Some(egui::Align::Center));
+            cursor.scroll_pending = false;
+        }
+        egui::Color32::from_rgb(110, 80, 45)
+    } else if cursor.row_num.is_multiple_of(2) {
+        egui::Color32::from_rgb(55, 47, 38)
+    } else {
+        egui::Color32::from_rgb(45, 38, 30)
+    }
+}
+
+fn render_value_tree(ui: &mut egui::Ui, value: &Value, path: &str, depth: usize, cursor: &mut TreeCursor) {
     let indent = (depth as f32) * 20.0;
 
     match value {
-        Value::Object(obj) => {
+        Value::Object(obj) if as_ext_scalar(value).is_none() => {
             for (key, val) in obj {
-                *row_num += 1;
-                let bg_color = if *row_num % 2 == 0 {
-                    egui::Color32::from_rgb(55, 47, 38)
-                } else {
-                    egui::Color32::from_rgb(45, 38, 30)
-                };
+                let child_path = child_key_path(path, key);
+                let bg_color = row_background(ui, &child_path, cursor);
 
+                let ext = as_ext_scalar(val);
                 match val {
-                    Value::Object(_) => {
+                    Value::Object(_) if ext.is_none() => {
                         ui.horizontal(|ui| {
                             ui.add_space(indent);
                             let rect = ui.available_rect_before_wrap();
@@ -48,7 +89,7 @@ fn render_value_tree(ui: &mut egui::Ui, value: &Value, depth: usize, row_num: &m
                                 .strong()
                                 .color(egui::Color32::from_rgb(200, 160, 100)));
                         });
-                        render_value_tree(ui, val, depth + 1, row_num);
+                        render_value_tree(ui, val, &child_path, depth + 1, cursor);
                     }
                     Value::Array(_) => {
                         ui.horizontal(|ui| {
@@ -59,7 +100,7 @@ fn render_value_tree(ui: &mut egui::Ui, value: &Value, depth: usize, row_num: &m
                                 .strong()
                                 .color(egui::Color32::from_rgb(200, 160, 100)));
                         });
-                        render_value_tree(ui, val, depth + 1, row_num);
+                        render_value_tree(ui, val, &child_path, depth + 1, cursor);
                     }
                     _ => {
                         ui.horizontal(|ui| {
@@ -70,19 +111,20 @@ fn render_value_tree(ui: &mut egui::Ui, value: &Value, depth: usize, row_num: &m
                                 .color(egui::Color32::from_rgb(245, 230, 200)));
                             ui.label(egui::RichText::new(get_display_value(val, 200))
                                 .color(egui::Color32::from_rgb(180, 220, 180)));
+                            if let Some(ext) = &ext {
+                                ui.label(egui::RichText::new(format!("({})", ext.ext_type.name()))
+                                    .italics()
+                                    .color(egui::Color32::from_rgb(180, 170, 150)));
+                            }
                         });
                     }
                 }
             }
         }
         Value::Array(arr) => {
+            let item_path = element_path(path);
             for (i, item) in arr.iter().enumerate() {
-                *row_num += 1;
-                let bg_color = if *row_num % 2 == 0 {
-                    egui::Color32::from_rgb(55, 47, 38)
-                } else {
-                    egui::Color32::from_rgb(45, 38, 30)
-                };
+                let bg_color = row_background(ui, &item_path, cursor);
 
                 ui.horizontal(|ui| {
                     ui.add_space(indent);
@@ -91,7 +133,7 @@ fn render_value_tree(ui: &mut egui::Ui, value: &Value, depth: usize, row_num: &m
                     ui.label(egui::RichText::new(format!("[{}]:", i))
                         .color(egui::Color32::from_rgb(200, 180, 140)));
                 });
-                render_value_tree(ui, item, depth + 1, row_num);
+                render_value_tree(ui, item, &item_path, depth + 1, cursor);
             }
         }
         _ => {
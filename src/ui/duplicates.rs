@@ -0,0 +1,181 @@
+use eframe::egui;
+use serde_json::Value;
+use crate::app::DuplicatesState;
+use crate::data::{BiblioRecord, FieldInfo, get_display_value};
+use crate::data::duplicates::{DuplicateCluster, DuplicateMode};
+
+/// Records shown side by side before the rest of a cluster is folded away.
+const MAX_RECORDS_COMPARED: usize = 8;
+
+pub enum DuplicatesAction {
+    Find,
+    /// Open a record in the Details tab
+    Open(usize),
+}
+
+pub fn render_duplicates_tab(
+    ui: &mut egui::Ui,
+    state: &mut DuplicatesState,
+    records: &[BiblioRecord],
+    available_fields: &[FieldInfo],
+    checking: bool,
+) -> Option<DuplicatesAction> {
+    let mut action = None;
+
+    ui.heading("👯 Duplicate Records");
+    ui.label("Find identical records, records sharing a key, or near duplicates by title, author and year");
+    ui.separator();
+
+    let options = &mut state.options;
+    ui.horizontal(|ui| {
+        for mode in DuplicateMode::ALL {
+            ui.selectable_value(&mut options.mode, mode, mode.label());
+        }
+    });
+    ui.horizontal(|ui| {
+        match options.mode {
+            DuplicateMode::Identical => {
+                ui.label(egui::RichText::new("Records equal as JSON, ignoring key order")
+                    .color(egui::Color32::from_rgb(180, 170, 150)));
+            }
+            DuplicateMode::SameKey => {
+                path_selector(ui, "dup_key", "Key:", &mut options.key_field, available_fields);
+                ui.label(egui::RichText::new("Case, spaces and punctuation are ignored (978-3-16 = 978316)")
+                    .color(egui::Color32::from_rgb(180, 170, 150)));
+            }
+            DuplicateMode::Similar => {
+                path_selector(ui, "dup_title", "Title:", &mut options.title_field, available_fields);
+                path_selector(ui, "dup_author", "Author:", &mut options.author_field, available_fields);
+                path_selector(ui, "dup_year", "Year:", &mut options.year_field, available_fields);
+            }
+        }
+    });
+    if options.mode == DuplicateMode::Similar {
+        ui.horizontal(|ui| {
+            ui.label("Similarity ≥");
+            ui.add(egui::Slider::new(&mut options.threshold, 0.5..=1.0).fixed_decimals(2));
+            ui.label("Block on first");
+            ui.add(egui::DragValue::new(&mut options.block_chars).range(1..=20));
+            ui.label("title letters");
+        });
+    }
+
+    let ready = match options.mode {
+        DuplicateMode::Identical => true,
+        DuplicateMode::SameKey => !options.key_field.is_empty(),
+        DuplicateMode::Similar => !options.title_field.is_empty(),
+    };
+    if ui.add_enabled(ready && !checking, egui::Button::new("🔎 Find duplicates")).clicked() {
+        action = Some(DuplicatesAction::Find);
+    }
+    ui.separator();
+
+    if state.clusters.is_empty() {
+        ui.vertical_centered(|ui| {
+            ui.add_space(50.0);
+            ui.heading(format!("No duplicates found ({})", state.found_with.mode.label()));
+        });
+        return action;
+    }
+
+    let duplicate_count: usize = state.clusters.iter().map(|c| c.records.len()).sum();
+    ui.label(format!("{} clusters, {} records ({})", state.clusters.len(), duplicate_count, state.found_with.mode.label()));
+    ui.add_space(5.0);
+
+    egui::SidePanel::left("duplicate_clusters")
+        .resizable(true)
+        .default_width(260.0)
+        .show_inside(ui, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (i, cluster) in state.clusters.iter().enumerate() {
+                    if ui.selectable_label(state.selected == Some(i), cluster_label(cluster)).clicked() {
+                        state.selected = Some(i);
+                    }
+                }
+            });
+        });
+
+    if let Some(cluster) = state.selected.and_then(|i| state.clusters.get(i)) {
+        if let Some(idx) = render_comparison(ui, cluster, records) {
+            action = Some(DuplicatesAction::Open(idx));
+        }
+    }
+
+    action
+}
+
+fn cluster_label(cluster: &DuplicateCluster) -> String {
+    let ids = cluster.records.iter().take(3).map(|r| format!("#{}", r + 1)).collect::<Vec<_>>().join(", ");
+    let more = if cluster.records.len() > 3 { format!(" +{}", cluster.records.len() - 3) } else { String::new() };
+    let score = if cluster.score < 1.0 { format!(" ({:.2})", cluster.score) } else { String::new() };
+    let key: String = cluster.key.chars().take(40).collect();
+    format!("{}{}{}  {}", ids, more, score, key)
+}
+
+/// The cluster's records as columns, one row per top-level field. Fields
+/// whose values differ are highlighted. Returns a record to open.
+fn render_comparison(ui: &mut egui::Ui, cluster: &DuplicateCluster, records: &[BiblioRecord]) -> Option<usize> {
+    let mut open = None;
+    let shown: Vec<usize> = cluster.records.iter().copied().take(MAX_RECORDS_COMPARED).collect();
+
+    let mut fields: Vec<&str> = Vec::new();
+    for &idx in &shown {
+        if let Some(Value::Object(obj)) = records.get(idx) {
+            for key in obj.keys() {
+                if !fields.contains(&key.as_str()) {
+                    fields.push(key);
+                }
+            }
+        }
+    }
+
+    egui::ScrollArea::both().show(ui, |ui| {
+        egui::Grid::new("duplicate_comparison")
+            .striped(true)
+            .min_col_width(120.0)
+            .show(ui, |ui| {
+                ui.label(egui::RichText::new("Field").strong());
+                for &idx in &shown {
+                    if ui.link(egui::RichText::new(format!("#{}", idx + 1)).strong())
+                        .on_hover_text("Show in Details")
+                        .clicked()
+                    {
+                        open = Some(idx);
+                    }
+                }
+                ui.end_row();
+
+                for field in &fields {
+                    let values: Vec<Option<&Value>> = shown.iter().map(|&idx| records.get(idx).and_then(|r| r.get(*field))).collect();
+                    let differs = values.iter().any(|v| *v != values[0]);
+                    let label = egui::RichText::new(*field).strong();
+                    ui.label(if differs { label.color(egui::Color32::from_rgb(200, 160, 100)) } else { label });
+                    for value in values {
+                        let text = value.map(|v| get_display_value(v, 120)).unwrap_or_default();
+                        let text = egui::RichText::new(text);
+                        ui.label(if differs { text.color(egui::Color32::from_rgb(245, 230, 200)) } else { text.color(egui::Color32::from_rgb(180, 170, 150)) });
+                    }
+                    ui.end_row();
+                }
+            });
+
+        if cluster.records.len() > MAX_RECORDS_COMPARED {
+            ui.label(egui::RichText::new(format!("... and {} more records", cluster.records.len() - MAX_RECORDS_COMPARED))
+                .italics()
+                .color(egui::Color32::from_rgb(180, 170, 150)));
+        }
+    });
+    open
+}
+
+fn path_selector(ui: &mut egui::Ui, id: &str, label: &str, path: &mut String, available_fields: &[FieldInfo]) {
+    ui.label(label);
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(if path.is_empty() { "(none)" } else { path.as_str() })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(path, String::new(), "(none)");
+            for field in available_fields {
+                ui.selectable_value(path, field.name.clone(), &field.name);
+            }
+        });
+}
@@ -0,0 +1,167 @@
+use eframe::egui;
+use crate::app::{BiblioAnalyzerApp, ExportColumns};
+use crate::data::export::{ArrayHandling, ExportFormat};
+use crate::data::reports::ReportFormat;
+
+/// Window with the export options for the filtered records; the file is
+/// chosen when the user clicks Export.
+pub fn render_export_window(app: &mut BiblioAnalyzerApp, ctx: &egui::Context) {
+    let mut open = app.export_state.open;
+    let mut export_clicked = false;
+    let mut cancel_clicked = false;
+    let record_count = app.filtered_records.len();
+    let export_progress = app.export_job.as_ref().map(|job| (job.progress.clone(), job.is_cancelling()));
+
+    egui::Window::new("💾 Export records")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(true)
+        .default_width(360.0)
+        .show(ctx, |ui| {
+            let state = &mut app.export_state;
+            ui.label(format!("{} filtered records, in the current Browse order", record_count));
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("Format:");
+                for format in ExportFormat::all() {
+                    ui.radio_value(&mut state.options.format, format, format.label());
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Fields:");
+                ui.radio_value(&mut state.columns, ExportColumns::WholeRecords, "All");
+                ui.radio_value(&mut state.columns, ExportColumns::BrowseColumns, "Browse columns");
+                ui.radio_value(&mut state.columns, ExportColumns::Chosen, "Choose...");
+            });
+
+            if state.columns == ExportColumns::Chosen {
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    for field in &app.field_schema {
+                        ui.horizontal(|ui| {
+                            ui.add_space(field.depth as f32 * 12.0);
+                            let mut chosen = state.chosen.contains(&field.name);
+                            if ui.checkbox(&mut chosen, &field.name).changed() {
+                                if chosen {
+                                    state.chosen.push(field.name.clone());
+                                } else {
+                                    state.chosen.retain(|c| c != &field.name);
+                                }
+                            }
+                        });
+                    }
+                });
+            }
+
+            ui.separator();
+            let is_csv = state.options.format == ExportFormat::Csv;
+            ui.add_enabled_ui(!is_csv, |ui| {
+                let mut flatten = state.options.flatten || is_csv;
+                if ui.checkbox(&mut flatten, "Flatten nested objects and arrays")
+                    .on_hover_text("Nested objects become dotted columns (publisher.place), array elements authors[].name")
+                    .changed()
+                {
+                    state.options.flatten = flatten;
+                }
+            });
+
+            ui.add_enabled_ui(state.options.flatten || is_csv, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Arrays:");
+                    egui::ComboBox::from_id_salt("export_arrays")
+                        .selected_text(state.options.arrays.label())
+                        .show_ui(ui, |ui| {
+                            for arrays in ArrayHandling::all() {
+                                ui.selectable_value(&mut state.options.arrays, arrays, arrays.label());
+                            }
+                        });
+                    if state.options.arrays == ArrayHandling::Join {
+                        ui.label("Delimiter:");
+                        ui.add(egui::TextEdit::singleline(&mut state.options.delimiter).desired_width(40.0));
+                    }
+                });
+            });
+
+            ui.separator();
+            let can_export = record_count > 0
+                && (state.columns != ExportColumns::Chosen || !state.chosen.is_empty());
+            if let Some((progress, cancelling)) = &export_progress {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.add(egui::ProgressBar::new(progress.fraction())
+                        .desired_width(200.0)
+                        .show_percentage());
+                    cancel_clicked = ui.add_enabled(!cancelling, egui::Button::new("✖ Cancel")).clicked();
+                });
+                let action = if progress.collecting_columns { "Collecting CSV columns" } else { "Writing" };
+                ui.label(format!("{}: {} / {} records", action, progress.records_done, progress.total_records));
+                return;
+            }
+
+            ui.horizontal(|ui| {
+                if ui.add_enabled(can_export, egui::Button::new("💾 Export...")).clicked() {
+                    export_clicked = true;
+                }
+                if !state.status.is_empty() {
+                    ui.label(egui::RichText::new(&state.status)
+                        .color(egui::Color32::from_rgb(180, 170, 150)));
+                }
+            });
+        });
+
+    app.export_state.open = open;
+
+    if cancel_clicked {
+        app.cancel_export();
+    }
+
+    if export_clicked {
+        let format = app.export_state.options.format;
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter(format.label(), &[format.extension()])
+            .set_file_name(format!("export.{}", format.extension()))
+            .save_file()
+        {
+            app.export_filtered(ctx, &path.display().to_string());
+        }
+    }
+}
+
+/// "Export report" menu with one entry per format, writing the report `make`
+/// builds to the chosen file.
+pub fn report_menu(ui: &mut egui::Ui, file_stem: &str, make: impl Fn(ReportFormat) -> String) {
+    let stem: String = file_stem
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    ui.menu_button("💾 Export report", |ui| {
+        for format in [ReportFormat::Csv, ReportFormat::Json, ReportFormat::Markdown] {
+            if ui.button(format.label()).clicked() {
+                ui.close_menu();
+                save_text_file(&format!("{}.{}", stem, format.extension()), format.label(), || make(format));
+            }
+        }
+    });
+}
+
+/// Asks for a file name and writes `contents` there; write errors are shown
+/// in a dialog.
+pub fn save_text_file(default_name: &str, filter_label: &str, contents: impl FnOnce() -> String) {
+    let extension = default_name.rsplit('.').next().unwrap_or_default();
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter(filter_label, &[extension])
+        .set_file_name(default_name)
+        .save_file()
+    else {
+        return;
+    };
+
+    if let Err(e) = std::fs::write(&path, contents()) {
+        rfd::MessageDialog::new()
+            .set_level(rfd::MessageLevel::Error)
+            .set_title("Export failed")
+            .set_description(format!("Could not write {}: {}", path.display(), e))
+            .show();
+    }
+}
@@ -1,13 +1,30 @@
 use eframe::egui;
-use crate::data::{FacetAnalysis, BiblioRecord};
-use crate::app::FacetsState;
+use crate::data::{BiblioRecord, FacetMode, FieldInfo, TimeGranularity};
+use crate::data::filters::{FilterKind, RecordFilter};
+use crate::data::clustering::ClusterMethod;
+use crate::data::facets::format_number;
+use crate::data::reports::facet_report;
+use crate::ui::export::report_menu;
+use crate::app::{FacetsState, RangeDrag};
+
+/// Something the user asked for in the Facets tab that needs the whole app.
+pub enum FacetsAction {
+    Filter(Vec<RecordFilter>),
+    MergeClusters,
+    /// Replace (or with `None` remove) the numeric range filter on a path
+    SetRange { path: String, range: Option<(f64, f64)> },
+}
 
 pub fn render_facets_tab(
     ui: &mut egui::Ui,
     state: &mut FacetsState,
     records: &[BiblioRecord],
-    available_fields: &[String],
-) {
+    available_fields: &[FieldInfo],
+    active_filters: &[RecordFilter],
+    checking: bool,
+) -> Option<FacetsAction> {
+    let mut action = None;
+
     ui.heading("🔍 Facet Analysis");
     ui.label("Explore the distribution of values in any field");
     ui.separator();
@@ -17,16 +34,32 @@ pub fn render_facets_tab(
         ui.label("Select field to analyze:");
         egui::ComboBox::from_label("")
             .selected_text(state.selected_field.as_deref().unwrap_or("Choose a field..."))
+            .height(400.0)
             .show_ui(ui, |ui| {
                 for field in available_fields {
-                    if ui.selectable_label(
-                        state.selected_field.as_ref() == Some(field),
-                        field
-                    ).clicked() {
-                        state.analyze_field(records, field);
-                    }
+                    ui.horizontal(|ui| {
+                        ui.add_space(field.depth as f32 * 12.0);
+                        if ui.selectable_label(
+                            state.selected_field.as_ref() == Some(&field.name),
+                            &field.name
+                        ).clicked() {
+                            state.analyze_field(records, &field.name);
+                        }
+                    });
                 }
             });
+
+        ui.separator();
+
+        let mut mode_changed = false;
+        for mode in FacetMode::all() {
+            mode_changed |= ui.radio_value(&mut state.mode, mode, mode.label()).changed();
+        }
+        if mode_changed {
+            if let Some(field) = state.selected_field.clone() {
+                state.analyze_field(records, &field);
+            }
+        }
     });
 
     ui.add_space(10.0);
@@ -36,7 +69,7 @@ pub fn render_facets_tab(
         // Summary section with visual styling
         ui.group(|ui| {
             ui.vertical(|ui| {
-                ui.heading(format!("Field: {}", analysis.field_name));
+                ui.heading(format!("Field: {} ({})", analysis.field_name, analysis.mode.label()));
                 ui.add_space(5.0);
                 
                 ui.horizontal(|ui| {
@@ -56,9 +89,19 @@ pub fn render_facets_tab(
                         .color(egui::Color32::from_rgb(200, 160, 100)));
                     ui.label(format!("{}", analysis.null_count));
                 });
+
+                ui.add_space(5.0);
+                report_menu(ui, &format!("facet-{}", analysis.field_name), |format| facet_report(analysis, format));
             });
         });
+    }
+
+    if state.current_analysis.as_ref().is_some_and(|a| a.mode != FacetMode::ArrayLength) {
+        ui.add_space(10.0);
+        render_cluster_panel(ui, state, checking, &mut action);
+    }
 
+    if let Some(analysis) = &state.current_analysis {
         ui.add_space(15.0);
         ui.heading("Value Distribution");
         ui.separator();
@@ -78,15 +121,30 @@ pub fn render_facets_tab(
                         ui.label(egui::RichText::new("Bar").strong());
                         ui.end_row();
 
-                        // Values
+                        // Values - clicking one filters the browse list
                         for facet_value in &analysis.values {
+                            let filter = RecordFilter {
+                                path: analysis.field_name.clone(),
+                                kind: FilterKind::FacetValue {
+                                    mode: analysis.mode,
+                                    value: facet_value.value.clone(),
+                                },
+                            };
+                            let active = active_filters.contains(&filter);
+
                             // Value (truncated if too long)
-                            let display_value = if facet_value.value.len() > 60 {
-                                format!("{}...", &facet_value.value[..60])
+                            let display_value = if facet_value.value.chars().count() > 60 {
+                                let truncated: String = facet_value.value.chars().take(60).collect();
+                                format!("{}...", truncated)
                             } else {
                                 facet_value.value.clone()
                             };
-                            ui.label(display_value);
+                            if ui.selectable_label(active, display_value)
+                                .on_hover_text("Show matching records in Browse")
+                                .clicked()
+                            {
+                                action = Some(FacetsAction::Filter(vec![filter]));
+                            }
 
                             // Count
                             ui.label(format!("{}", facet_value.count));
@@ -125,6 +183,10 @@ pub fn render_facets_tab(
                         }
                     });
             });
+    } else if state.numeric_analysis.is_some() {
+        render_numeric_facet(ui, state, active_filters, &mut action);
+    } else if state.timeline_analysis.is_some() {
+        render_timeline_facet(ui, state, records, active_filters, &mut action);
     } else {
         ui.vertical_centered(|ui| {
             ui.add_space(100.0);
@@ -132,4 +194,501 @@ pub fn render_facets_tab(
             ui.label("The facet view will show all unique values and their distribution");
         });
     }
-}
\ No newline at end of file
+
+    action
+}
+
+/// Summary statistics and a histogram; drag across the bars (or drag a
+/// handle of an existing selection) to filter the browse list by range.
+fn render_numeric_facet(
+    ui: &mut egui::Ui,
+    state: &mut FacetsState,
+    active_filters: &[RecordFilter],
+    action: &mut Option<FacetsAction>,
+) {
+    let Some(facet) = &state.numeric_analysis else {
+        return;
+    };
+    let accent = egui::Color32::from_rgb(200, 160, 100);
+
+    ui.group(|ui| {
+        ui.vertical(|ui| {
+            ui.heading(format!("Field: {} ({})", facet.field_name, FacetMode::Numeric.label()));
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new("📊 Records with numbers:").color(accent));
+                ui.label(format!("{}", facet.record_count));
+                ui.label(egui::RichText::new("🔢 Numbers:").color(accent));
+                ui.label(format!("{}", facet.value_count));
+                ui.label(egui::RichText::new("❌ Non-numeric values:").color(accent));
+                ui.label(format!("{}", facet.non_numeric_count));
+            });
+        });
+    });
+
+    if facet.bins.is_empty() {
+        ui.add_space(20.0);
+        ui.label("No numeric values at this path.");
+        return;
+    }
+
+    ui.add_space(10.0);
+    egui::Grid::new("numeric_stats")
+        .striped(true)
+        .min_col_width(70.0)
+        .show(ui, |ui| {
+            let mut columns = vec![("Min".to_string(), facet.min)];
+            columns.extend(facet.quantiles.iter()
+                .filter(|(q, _)| *q < 0.5)
+                .map(|(q, v)| (format!("{:.0}%", q * 100.0), *v)));
+            columns.push(("Median".to_string(), facet.median));
+            columns.extend(facet.quantiles.iter()
+                .filter(|(q, _)| *q > 0.5)
+                .map(|(q, v)| (format!("{:.0}%", q * 100.0), *v)));
+            columns.push(("Max".to_string(), facet.max));
+            columns.push(("Mean".to_string(), facet.mean));
+
+            for (label, _) in &columns {
+                ui.label(egui::RichText::new(label).strong());
+            }
+            ui.end_row();
+            for (_, value) in &columns {
+                ui.label(format_number(*value));
+            }
+            ui.end_row();
+        });
+
+    ui.add_space(15.0);
+    ui.heading("Histogram");
+    ui.separator();
+
+    let path = facet.field_name.clone();
+    let filtered_range = active_filters.iter().find_map(|f| match f.kind {
+        FilterKind::NumericRange { min, max } if f.path == path => Some((min, max)),
+        _ => None,
+    });
+    let selection = state.range_drag.as_ref().map(RangeDrag::range).or(filtered_range);
+
+    let axis_min = facet.bins[0].start;
+    let axis_max = facet.bins[facet.bins.len() - 1].end.max(axis_min + f64::EPSILON);
+    // One bar per whole number: a selection of `hi` extends to the bar's right edge
+    let unit_bins = facet.integers && facet.bins[0].end - facet.bins[0].start == 1.0;
+    let max_count = facet.bins.iter().map(|b| b.count).max().unwrap_or(1).max(1);
+
+    let width = ui.available_width().min(800.0);
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(width, 180.0), egui::Sense::click_and_drag());
+    let chart = egui::Rect::from_min_max(rect.min, egui::pos2(rect.max.x, rect.max.y - 18.0));
+    let x_of = |v: f64| chart.left() + ((v - axis_min) / (axis_max - axis_min)) as f32 * chart.width();
+    let value_of = |x: f32| {
+        let t = ((x - chart.left()) / chart.width()).clamp(0.0, 1.0) as f64;
+        axis_min + t * (axis_max - axis_min)
+    };
+    // Whole-number data snaps to integers
+    let snap = |v: f64| if facet.integers { v.floor().clamp(facet.min, facet.max) } else { v };
+
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(chart, 2.0, egui::Color32::from_rgb(65, 55, 43));
+
+    for bin in &facet.bins {
+        let height = bin.count as f32 / max_count as f32 * (chart.height() - 4.0);
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(x_of(bin.start) + 1.0, chart.bottom() - height),
+            egui::pos2((x_of(bin.end) - 1.0).max(x_of(bin.start) + 2.0), chart.bottom()),
+        );
+        let upper = if unit_bins { bin.start } else { bin.end };
+        let selected = selection.is_none_or(|(lo, hi)| bin.start <= hi && upper >= lo);
+        let color = if selected { accent } else { egui::Color32::from_rgb(120, 100, 70) };
+        painter.rect_filled(bar, 1.0, color);
+    }
+
+    if let Some((lo, hi)) = selection {
+        let hi_edge = if unit_bins { hi + 1.0 } else { hi };
+        let band = egui::Rect::from_min_max(
+            egui::pos2(x_of(lo), chart.top()),
+            egui::pos2(x_of(hi_edge.min(axis_max)), chart.bottom()),
+        );
+        painter.rect_filled(band, 0.0, egui::Color32::from_rgba_unmultiplied(245, 230, 200, 25));
+        for x in [band.left(), band.right()] {
+            painter.line_segment(
+                [egui::pos2(x, chart.top()), egui::pos2(x, chart.bottom())],
+                egui::Stroke::new(2.0, egui::Color32::from_rgb(245, 230, 200)),
+            );
+        }
+    }
+
+    let text_color = egui::Color32::from_rgb(180, 170, 150);
+    painter.text(rect.left_bottom(), egui::Align2::LEFT_BOTTOM, format_number(facet.min),
+        egui::FontId::proportional(12.0), text_color);
+    painter.text(rect.right_bottom(), egui::Align2::RIGHT_BOTTOM, format_number(facet.max),
+        egui::FontId::proportional(12.0), text_color);
+
+    if let Some(pos) = response.hover_pos() {
+        let v = value_of(pos.x);
+        if let Some(bin) = facet.bins.iter().find(|b| v >= b.start && v <= b.end) {
+            let range = if unit_bins {
+                format_number(bin.start)
+            } else {
+                format!("{} – {}", format_number(bin.start), format_number(bin.end))
+            };
+            response.clone().on_hover_text(format!("{}: {} values", range, bin.count));
+        }
+    }
+
+    // Dragging: near an existing edge moves that edge, elsewhere starts a new range
+    if response.drag_started() {
+        if let Some(pos) = response.interact_pointer_pos() {
+            let grabbed = selection.and_then(|(lo, hi)| {
+                let hi_edge = if unit_bins { hi + 1.0 } else { hi };
+                if (pos.x - x_of(lo)).abs() < 8.0 {
+                    Some(hi)
+                } else if (pos.x - x_of(hi_edge)).abs() < 8.0 {
+                    Some(lo)
+                } else {
+                    None
+                }
+            });
+            let anchor = grabbed.unwrap_or_else(|| snap(value_of(pos.x)));
+            state.range_drag = Some(RangeDrag { anchor, current: snap(value_of(pos.x)) });
+        }
+    }
+    if response.dragged() {
+        if let (Some(drag), Some(pos)) = (&mut state.range_drag, response.interact_pointer_pos()) {
+            drag.current = snap(value_of(pos.x));
+        }
+    }
+    if response.drag_stopped() {
+        if let Some(drag) = state.range_drag.take() {
+            *action = Some(FacetsAction::SetRange { path: path.clone(), range: Some(drag.range()) });
+        }
+    } else if response.clicked() {
+        // A click selects the bar under the pointer
+        if let Some(pos) = response.interact_pointer_pos() {
+            let v = value_of(pos.x);
+            if let Some(bin) = facet.bins.iter().find(|b| v >= b.start && v <= b.end) {
+                let range = if unit_bins { (bin.start, bin.start) } else { (bin.start, bin.end) };
+                *action = Some(FacetsAction::SetRange { path: path.clone(), range: Some(range) });
+            }
+        }
+    }
+
+    ui.add_space(5.0);
+    ui.horizontal(|ui| {
+        match filtered_range {
+            Some((mut lo, mut hi)) => {
+                ui.label("Range:");
+                let speed = ((facet.max - facet.min) / 200.0).max(if facet.integers { 1.0 } else { 0.01 });
+                let lo_changed = ui.add(egui::DragValue::new(&mut lo).speed(speed)).changed();
+                ui.label("–");
+                let hi_changed = ui.add(egui::DragValue::new(&mut hi).speed(speed)).changed();
+                if lo_changed || hi_changed {
+                    *action = Some(FacetsAction::SetRange { path: path.clone(), range: Some((lo.min(hi), lo.max(hi))) });
+                }
+                if ui.button("✖ Clear range").clicked() {
+                    *action = Some(FacetsAction::SetRange { path: path.clone(), range: None });
+                }
+            }
+            None => {
+                ui.label(egui::RichText::new("Drag across the histogram to filter records by range")
+                    .italics()
+                    .color(text_color));
+            }
+        }
+    });
+}
+
+/// Stacked bars of dated values per year, decade or century. Clicking a bar
+/// filters the browse list to records dated in that period.
+fn render_timeline_facet(
+    ui: &mut egui::Ui,
+    state: &mut FacetsState,
+    records: &[BiblioRecord],
+    active_filters: &[RecordFilter],
+    action: &mut Option<FacetsAction>,
+) {
+    let accent = egui::Color32::from_rgb(200, 160, 100);
+    let muted = egui::Color32::from_rgb(140, 115, 80);
+    let text_color = egui::Color32::from_rgb(180, 170, 150);
+
+    let mut regroup = false;
+    if let Some(timeline) = &state.timeline_analysis {
+        ui.group(|ui| {
+            ui.vertical(|ui| {
+                ui.heading(format!("Field: {} ({})", timeline.field_name, FacetMode::Timeline.label()));
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new("📊 Dated records:").color(accent));
+                    ui.label(format!("{}", timeline.record_count));
+                    ui.label(egui::RichText::new("📅 Dates parsed:").color(accent));
+                    ui.label(format!("{}", timeline.parsed_count));
+                    ui.label(egui::RichText::new("❌ Unparseable:").color(accent));
+                    ui.label(format!("{}", timeline.unparseable.iter().map(|v| v.count).sum::<usize>()));
+                });
+                ui.horizontal_wrapped(|ui| {
+                    for (kind, count) in &timeline.kind_counts {
+                        ui.label(egui::RichText::new(format!("{}: {}", kind.label(), count)).color(text_color));
+                    }
+                });
+            });
+        });
+    }
+
+    ui.add_space(10.0);
+    ui.horizontal(|ui| {
+        ui.label("Group by:");
+        for granularity in [TimeGranularity::Year, TimeGranularity::Decade, TimeGranularity::Century] {
+            regroup |= ui.radio_value(&mut state.granularity, granularity, granularity.label()).changed();
+        }
+    });
+    if regroup {
+        if let Some(field) = state.selected_field.clone() {
+            state.analyze_field(records, &field);
+        }
+    }
+
+    let Some(timeline) = &state.timeline_analysis else {
+        return;
+    };
+    if timeline.buckets.is_empty() {
+        ui.add_space(20.0);
+        ui.label("No parseable dates at this path.");
+    } else {
+        let path = &timeline.field_name;
+        let size = timeline.granularity.years();
+        let active_ranges: Vec<(i32, i32)> = active_filters.iter()
+            .filter(|f| &f.path == path)
+            .filter_map(|f| match f.kind {
+                FilterKind::DateRange { start, end } => Some((start, end)),
+                _ => None,
+            })
+            .collect();
+
+        let max_count = timeline.buckets.iter().map(|b| b.exact + b.approximate).max().unwrap_or(1).max(1);
+        let width = ui.available_width().min(900.0);
+        let (rect, response) = ui.allocate_exact_size(egui::vec2(width, 200.0), egui::Sense::click());
+        let chart = egui::Rect::from_min_max(rect.min, egui::pos2(rect.max.x, rect.max.y - 18.0));
+        let bar_width = chart.width() / timeline.buckets.len() as f32;
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(chart, 2.0, egui::Color32::from_rgb(65, 55, 43));
+
+        for (i, bucket) in timeline.buckets.iter().enumerate() {
+            let left = chart.left() + i as f32 * bar_width;
+            let right = (left + bar_width - 1.0).max(left + 1.0);
+            let scale = (chart.height() - 4.0) / max_count as f32;
+            let exact_top = chart.bottom() - bucket.exact as f32 * scale;
+            let approx_top = exact_top - bucket.approximate as f32 * scale;
+
+            let selected = active_ranges.iter().any(|(start, end)| bucket.start <= *end && bucket.start + size > *start);
+            let (exact_color, approx_color) = if active_ranges.is_empty() || selected {
+                (accent, muted)
+            } else {
+                (egui::Color32::from_rgb(110, 92, 66), egui::Color32::from_rgb(90, 76, 58))
+            };
+            painter.rect_filled(
+                egui::Rect::from_min_max(egui::pos2(left, exact_top), egui::pos2(right, chart.bottom())),
+                0.0,
+                exact_color,
+            );
+            painter.rect_filled(
+                egui::Rect::from_min_max(egui::pos2(left, approx_top), egui::pos2(right, exact_top)),
+                0.0,
+                approx_color,
+            );
+        }
+
+        // Roughly six labels along the axis
+        let label_every = (timeline.buckets.len() / 6).max(1);
+        for (i, bucket) in timeline.buckets.iter().enumerate().step_by(label_every) {
+            painter.text(
+                egui::pos2(chart.left() + i as f32 * bar_width, rect.bottom()),
+                egui::Align2::LEFT_BOTTOM,
+                bucket_label(bucket.start, timeline.granularity),
+                egui::FontId::proportional(12.0),
+                text_color,
+            );
+        }
+
+        let bucket_at = |x: f32| {
+            let index = ((x - chart.left()) / bar_width) as usize;
+            timeline.buckets.get(index.min(timeline.buckets.len() - 1))
+        };
+        if let Some(bucket) = response.hover_pos().and_then(|pos| bucket_at(pos.x)) {
+            response.clone().on_hover_text(format!(
+                "{}: {} dated, {} approximate (ranges, circa, periods)",
+                bucket_label(bucket.start, timeline.granularity), bucket.exact, bucket.approximate,
+            ));
+        }
+        if response.clicked() {
+            if let Some(bucket) = response.interact_pointer_pos().and_then(|pos| bucket_at(pos.x)) {
+                *action = Some(FacetsAction::Filter(vec![RecordFilter {
+                    path: path.clone(),
+                    kind: FilterKind::DateRange { start: bucket.start, end: bucket.start + size - 1 },
+                }]));
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("■ dated").color(accent));
+            ui.label(egui::RichText::new("■ approximate, at its midpoint").color(muted));
+            ui.label(egui::RichText::new("Click a bar to filter records").italics().color(text_color));
+        });
+    }
+
+    if !timeline.unparseable.is_empty() {
+        ui.add_space(15.0);
+        ui.heading("Unparseable Values");
+        ui.separator();
+        egui::ScrollArea::vertical()
+            .id_salt("timeline_unparseable")
+            .max_height(250.0)
+            .show(ui, |ui| {
+                egui::Grid::new("timeline_unparseable_grid")
+                    .striped(true)
+                    .min_col_width(100.0)
+                    .show(ui, |ui| {
+                        ui.label(egui::RichText::new("Value").strong());
+                        ui.label(egui::RichText::new("Count").strong());
+                        ui.end_row();
+                        for value in timeline.unparseable.iter().take(500) {
+                            ui.label(&value.value);
+                            ui.label(format!("{}", value.count));
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+}
+
+fn bucket_label(start: i32, granularity: TimeGranularity) -> String {
+    match granularity {
+        TimeGranularity::Year => start.to_string(),
+        _ => format!("{}s", start),
+    }
+}
+
+/// OpenRefine-style "Cluster & edit": pick a method, review the clusters and
+/// merge the selected ones into a single value.
+/// Merging waits while checks run, since it re-runs them on the new values.
+fn render_cluster_panel(ui: &mut egui::Ui, state: &mut FacetsState, checking: bool, action: &mut Option<FacetsAction>) {
+    egui::CollapsingHeader::new("🧩 Cluster & Merge Variants")
+        .default_open(false)
+        .show(ui, |ui| {
+            let options = &mut state.clustering.options;
+            let mut run = false;
+
+            ui.horizontal(|ui| {
+                ui.label("Method:");
+                egui::ComboBox::from_id_salt("cluster_method")
+                    .selected_text(options.method.label())
+                    .show_ui(ui, |ui| {
+                        for method in ClusterMethod::all() {
+                            run |= ui.selectable_value(&mut options.method, method, method.label()).changed();
+                        }
+                    });
+
+                match options.method {
+                    ClusterMethod::NgramFingerprint => {
+                        ui.label("n-gram size:");
+                        run |= ui.add(egui::DragValue::new(&mut options.ngram_size).range(1..=6)).changed();
+                    }
+                    method if method.is_nearest_neighbour() => {
+                        ui.label("Radius:");
+                        run |= ui.add(egui::DragValue::new(&mut options.radius).range(0.0..=10.0).speed(0.1)).changed();
+                        ui.label("Block chars:");
+                        run |= ui.add(egui::DragValue::new(&mut options.block_size).range(1..=12)).changed();
+                    }
+                    _ => {}
+                }
+
+                if ui.button("🔄 Cluster").clicked() {
+                    run = true;
+                }
+            });
+
+            if run {
+                state.run_clustering();
+            }
+
+            if !state.clustering.status.is_empty() {
+                ui.label(egui::RichText::new(&state.clustering.status)
+                    .color(egui::Color32::from_rgb(180, 170, 150)));
+            }
+
+            let reviews = &mut state.clustering.reviews;
+            if reviews.is_empty() {
+                return;
+            }
+
+            ui.horizontal(|ui| {
+                if ui.small_button("Select all").clicked() {
+                    reviews.iter_mut().for_each(|r| r.merge = true);
+                }
+                if ui.small_button("Deselect all").clicked() {
+                    reviews.iter_mut().for_each(|r| r.merge = false);
+                }
+                let selected = reviews.iter().filter(|r| r.merge).count();
+                let merge = ui.add_enabled(selected > 0 && !checking, egui::Button::new(format!("✔ Merge {} selected", selected)))
+                    .on_disabled_hover_text(if checking { "Wait for the running checks to finish" } else { "Select clusters to merge" });
+                if merge.clicked() {
+                    *action = Some(FacetsAction::MergeClusters);
+                }
+            });
+
+            let field = state.current_analysis.as_ref().map(|a| (a.field_name.clone(), a.mode));
+
+            egui::ScrollArea::vertical()
+                .id_salt("cluster_reviews")
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("cluster_grid")
+                        .striped(true)
+                        .min_col_width(60.0)
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new("Merge?").strong());
+                            ui.label(egui::RichText::new("Values in Cluster").strong());
+                            ui.label(egui::RichText::new("Records").strong());
+                            ui.label(egui::RichText::new("New Value").strong());
+                            ui.label("");
+                            ui.end_row();
+
+                            for (i, review) in reviews.iter_mut().enumerate() {
+                                ui.checkbox(&mut review.merge, "");
+
+                                ui.vertical(|ui| {
+                                    for value in &review.cluster.values {
+                                        let text = format!("{} ({})", value.value, value.count);
+                                        if ui.selectable_label(review.new_value == value.value, text)
+                                            .on_hover_text("Use this as the new value")
+                                            .clicked()
+                                        {
+                                            review.new_value = value.value.clone();
+                                        }
+                                    }
+                                });
+
+                                ui.label(format!("{}", review.cluster.record_count));
+
+                                if ui.add(egui::TextEdit::singleline(&mut review.new_value)
+                                    .id_salt(("cluster_value", i))
+                                    .desired_width(220.0))
+                                    .changed()
+                                {
+                                    review.merge = true;
+                                }
+
+                                if let Some((path, mode)) = &field {
+                                    if ui.small_button("Browse →").clicked() {
+                                        let filters = review.cluster.values.iter()
+                                            .map(|v| RecordFilter {
+                                                path: path.clone(),
+                                                kind: FilterKind::FacetValue { mode: *mode, value: v.value.clone() },
+                                            })
+                                            .collect();
+                                        *action = Some(FacetsAction::Filter(filters));
+                                    }
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+        });
+}
@@ -1,49 +1,181 @@
 use eframe::egui;
+use std::collections::{HashMap, HashSet};
 use crate::app::BiblioAnalyzerApp;
+use crate::data::{FieldInfo, IssueKind, RecordIssue, Severity};
+use crate::data::issues::{IssueGroup, IssueSort};
+use crate::data::filters::{FilterKind, RecordFilter};
+use crate::data::paths::last_segment;
+use crate::data::reports::schema_report;
+use crate::data::rules::RuleSet;
+use crate::data::json_schema::infer_json_schema;
+use crate::ui::export::{report_menu, save_text_file};
 
-pub fn render_schema_tab(app: &BiblioAnalyzerApp, ui: &mut egui::Ui) {
+pub fn render_schema_tab(app: &mut BiblioAnalyzerApp, ui: &mut egui::Ui) {
     ui.heading("📋 Data Schema Analysis");
-    ui.label(format!("Detected {} unique fields across {} records",
-        app.field_schema.len(), app.records.len()));
+    let top_level = app.field_schema.iter().filter(|f| f.depth == 0).count();
+    let mixed = app.field_schema.iter().filter(|f| f.is_mixed_type()).count();
+    ui.label(format!("Detected {} top-level fields and {} nested paths across {} records ({} with mixed types)",
+        top_level, app.field_schema.len() - top_level, app.records.len(), mixed));
+
+    ui.horizontal(|ui| {
+        if ui.button("⊞ Expand all").clicked() {
+            app.schema_state.expanded = app.field_schema.iter().map(|f| f.name.clone()).collect();
+        }
+        if ui.button("⊟ Collapse all").clicked() {
+            app.schema_state.expanded.clear();
+        }
+        report_menu(ui, "schema", |format| schema_report(&app.field_schema, app.records.len(), format));
+    });
+    render_json_schema_panel(app, ui);
     ui.separator();
 
+    // Children of each path, in the (sorted) schema order
+    let mut children: HashMap<Option<&str>, Vec<&FieldInfo>> = HashMap::new();
+    for field in &app.field_schema {
+        children.entry(field.parent.as_deref()).or_default().push(field);
+    }
+
+    let total = app.records.len();
+    let expanded = &mut app.schema_state.expanded;
+    let mut jump_to: Option<(String, String)> = None;
+
     egui::ScrollArea::vertical().show(ui, |ui| {
         egui::Grid::new("schema_grid")
             .striped(true)
-            .min_col_width(150.0)
+            .min_col_width(100.0)
             .show(ui, |ui| {
-                ui.label(egui::RichText::new("Field Name").strong());
+                ui.label(egui::RichText::new("Field Path").strong());
                 ui.label(egui::RichText::new("Data Type").strong());
+                ui.label(egui::RichText::new("Minority Types").strong());
                 ui.label(egui::RichText::new("Present In").strong());
                 ui.label(egui::RichText::new("Null Count").strong());
+                ui.label(egui::RichText::new("Values").strong());
                 ui.label(egui::RichText::new("Coverage %").strong());
                 ui.end_row();
 
-                for field in &app.field_schema {
-                    let total = app.records.len();
-                    let coverage = if total > 0 {
-                        field.sample_count as f32 / total as f32 * 100.0
-                    } else {
-                        0.0
-                    };
+                for field in children.get(&None).into_iter().flatten() {
+                    render_schema_row(ui, field, &children, expanded, total, &mut jump_to);
+                }
+            });
+    });
 
-                    ui.label(&field.name);
-                    ui.label(&field.field_type);
-                    ui.label(format!("{} / {}", field.sample_count, total));
-                    ui.label(format!("{}", field.null_count));
+    if let Some((path, value_type)) = jump_to {
+        app.add_filter(RecordFilter { path, kind: FilterKind::ValueType(value_type) });
+    }
+}
 
-                    let color = if coverage > 90.0 {
-                        egui::Color32::from_rgb(100, 200, 100)
-                    } else if coverage > 50.0 {
-                        egui::Color32::from_rgb(200, 200, 100)
-                    } else {
-                        egui::Color32::from_rgb(200, 100, 100)
-                    };
-                    ui.colored_label(color, format!("{:.1}%", coverage));
-                    ui.end_row();
-                }
+/// Options and a save button for the inferred JSON Schema.
+fn render_json_schema_panel(app: &mut BiblioAnalyzerApp, ui: &mut egui::Ui) {
+    egui::CollapsingHeader::new("🧾 JSON Schema (draft 2020-12)")
+        .id_salt("json_schema_panel")
+        .show(ui, |ui| {
+            let options = &mut app.schema_state.json_schema_options;
+            ui.horizontal(|ui| {
+                ui.label("Required when present in at least");
+                ui.add(egui::Slider::new(&mut options.required_coverage, 50.0..=100.0).suffix("%"));
+                ui.label("of objects");
             });
+            ui.horizontal(|ui| {
+                ui.label("Enum for fields with at most");
+                ui.add(egui::DragValue::new(&mut options.max_enum_values).range(0..=100));
+                ui.label("distinct strings (0 = never)");
+            });
+            ui.checkbox(&mut options.detect_formats, "Detect formats (date, date-time, uri, email)");
+            ui.checkbox(&mut options.closed_objects, "Disallow additional properties");
+
+            if ui.button("💾 Save JSON Schema...").clicked() {
+                let title = std::path::Path::new(&app.file_path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "records".to_string());
+                let options = app.schema_state.json_schema_options.clone();
+                let records = &app.records;
+                save_text_file(&format!("{}.schema.json", title), "JSON Schema", || {
+                    let schema = infer_json_schema(records, &title, &options);
+                    serde_json::to_string_pretty(&schema).unwrap_or_default()
+                });
+            }
+        });
+}
+
+fn render_schema_row(
+    ui: &mut egui::Ui,
+    field: &FieldInfo,
+    children: &HashMap<Option<&str>, Vec<&FieldInfo>>,
+    expanded: &mut HashSet<String>,
+    total: usize,
+    jump_to: &mut Option<(String, String)>,
+) {
+    let kids = children.get(&Some(field.name.as_str()));
+    let is_expanded = expanded.contains(&field.name);
+
+    ui.horizontal(|ui| {
+        ui.add_space(field.depth as f32 * 20.0);
+        if kids.is_some() {
+            let icon = if is_expanded { "▼" } else { "▶" };
+            if ui.small_button(icon).clicked() {
+                if is_expanded {
+                    expanded.remove(&field.name);
+                } else {
+                    expanded.insert(field.name.clone());
+                }
+            }
+        } else {
+            ui.add_space(22.0);
+        }
+        ui.label(last_segment(&field.name, field.parent.as_deref()))
+            .on_hover_text(&field.name);
     });
+
+    let coverage = if total > 0 {
+        field.sample_count as f32 / total as f32 * 100.0
+    } else {
+        0.0
+    };
+
+    let typed_values: usize = field.type_counts.iter().map(|(_, count)| count).sum();
+    let type_breakdown = field.type_counts.iter()
+        .map(|(t, count)| format!("{}: {} ({:.1}%)", t, count, *count as f32 / typed_values.max(1) as f32 * 100.0))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if field.is_mixed_type() {
+        ui.colored_label(egui::Color32::from_rgb(220, 180, 100), format!("⚠ {} (mixed)", field.field_type))
+            .on_hover_text(type_breakdown);
+    } else {
+        ui.label(&field.field_type).on_hover_text(type_breakdown);
+    }
+
+    ui.horizontal(|ui| {
+        for (value_type, count) in field.type_counts.iter().skip(1) {
+            if ui.small_button(format!("{}: {} →", value_type, count))
+                .on_hover_text("Show these records in Browse")
+                .clicked()
+            {
+                *jump_to = Some((field.name.clone(), value_type.clone()));
+            }
+        }
+    });
+
+    ui.label(format!("{} / {}", field.sample_count, total));
+    ui.label(format!("{}", field.null_count));
+    ui.label(format!("{}", field.value_count));
+
+    let color = if coverage > 90.0 {
+        egui::Color32::from_rgb(100, 200, 100)
+    } else if coverage > 50.0 {
+        egui::Color32::from_rgb(200, 200, 100)
+    } else {
+        egui::Color32::from_rgb(200, 100, 100)
+    };
+    ui.colored_label(color, format!("{:.1}%", coverage));
+    ui.end_row();
+
+    if is_expanded {
+        for child in kids.into_iter().flatten() {
+            render_schema_row(ui, child, children, expanded, total, jump_to);
+        }
+    }
 }
 
 pub fn render_statistics_tab(app: &BiblioAnalyzerApp, ui: &mut egui::Ui) {
@@ -51,7 +183,8 @@ pub fn render_statistics_tab(app: &BiblioAnalyzerApp, ui: &mut egui::Ui) {
     ui.separator();
 
     ui.label(format!("Total Records: {}", app.records.len()));
-    ui.label(format!("Total Fields Detected: {}", app.field_schema.len()));
+    ui.label(format!("Total Fields Detected: {} top-level, {} paths",
+        app.top_level_fields.len(), app.field_schema.len()));
 
     ui.add_space(10.0);
     ui.heading("Field Coverage");
@@ -86,34 +219,198 @@ pub fn render_statistics_tab(app: &BiblioAnalyzerApp, ui: &mut egui::Ui) {
     });
 }
 
-pub fn render_issues_tab(app: &BiblioAnalyzerApp, ui: &mut egui::Ui) {
+/// Issues listed per group before the rest are folded away.
+const MAX_ISSUES_SHOWN: usize = 500;
+
+pub fn render_issues_tab(app: &mut BiblioAnalyzerApp, ui: &mut egui::Ui) {
     ui.heading("⚠️ Data Quality Issues");
     ui.separator();
 
-    ui.label(format!("Total Issues Found: {}", app.issues.len()));
-    ui.add_space(10.0);
+    if app.checks_outdated && !app.is_checking() {
+        ui.horizontal(|ui| {
+            ui.colored_label(egui::Color32::from_rgb(200, 100, 80),
+                "Records were edited since these issues were found; they may be out of date");
+            if ui.button("🔄 Re-check").clicked() {
+                app.recheck_all(ui.ctx());
+            }
+        });
+    }
 
-    egui::ScrollArea::vertical().show(ui, |ui| {
-        egui::Grid::new("issues_grid")
-            .striped(true)
-            .min_col_width(100.0)
-            .show(ui, |ui| {
-                ui.label(egui::RichText::new("Record").strong());
-                ui.label(egui::RichText::new("Issue Type").strong());
-                ui.label(egui::RichText::new("Description").strong());
-                ui.end_row();
+    let mut clear_schema = false;
+    ui.horizontal(|ui| {
+        let validate = ui.add_enabled(!app.is_checking(), egui::Button::new("📐 Validate against JSON Schema..."));
+        if validate.clicked() {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("JSON Schema", &["json"])
+                .pick_file()
+            {
+                app.load_validation_schema(ui.ctx(), &path.display().to_string());
+            }
+        }
+        if let Some(schema) = &app.validation_schema {
+            ui.label(egui::RichText::new(format!("Schema: {}", schema.path))
+                .color(egui::Color32::from_rgb(180, 170, 150)));
+            let clear = ui.add_enabled(!app.is_checking(), egui::Button::new("✖ Clear").small());
+            if clear.on_hover_text("Stop validating against this schema").clicked() {
+                clear_schema = true;
+            }
+        }
+    });
+    if clear_schema {
+        app.clear_validation_schema();
+    }
 
-                for issue in &app.issues {
-                    let record_label = if issue.record_index == usize::MAX {
-                        "SYSTEM".to_string()
-                    } else {
-                        format!("#{}", issue.record_index + 1)
-                    };
-                    ui.label(record_label);
-                    ui.label(&issue.issue_type);
-                    ui.label(&issue.description);
-                    ui.end_row();
+    let mut builtin_rules = false;
+    let mut rerun_rules = false;
+    ui.horizontal(|ui| {
+        let load_pack = ui.add_enabled(!app.is_checking(), egui::Button::new("📏 Load rule pack..."));
+        if load_pack.clicked() {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("Rule pack", &["json"])
+                .pick_file()
+            {
+                app.load_rule_pack(ui.ctx(), &path.display().to_string());
+            }
+        }
+        let rerun = ui.add_enabled(!app.is_checking(), egui::Button::new("🔄 Re-run rules"));
+        if rerun.on_hover_text("Re-read the rule pack and check every record again").clicked() {
+            rerun_rules = true;
+        }
+        if ui.button("💾 Save starter pack...").on_hover_text("A rule pack with one example of each check").clicked() {
+            save_text_file("rules.json", "Rule pack", RuleSet::example_pack);
+        }
+        ui.label(egui::RichText::new(format!("Rules: {} ({})", app.rules.name, app.rules.rule_count()))
+            .color(egui::Color32::from_rgb(180, 170, 150)));
+        if app.rules.path.is_some()
+            && ui.add_enabled(!app.is_checking(), egui::Button::new("✖ Built-in").small())
+                .on_hover_text("Go back to the built-in checks")
+                .clicked()
+        {
+            builtin_rules = true;
+        }
+    });
+    if rerun_rules {
+        app.rerun_rules(ui.ctx());
+    }
+    if builtin_rules {
+        app.use_builtin_rules(ui.ctx());
+    }
+
+    ui.separator();
+    let mut regroup = false;
+    ui.horizontal(|ui| {
+        let filter = &mut app.issues_state.filter;
+        for severity in Severity::ALL {
+            let mut shown = filter.severities.contains(&severity);
+            let text = egui::RichText::new(severity.label()).color(severity_color(severity));
+            if ui.checkbox(&mut shown, text).changed() {
+                if shown {
+                    filter.severities.insert(severity);
+                } else {
+                    filter.severities.remove(&severity);
+                }
+                regroup = true;
+            }
+        }
+        ui.separator();
+        egui::ComboBox::from_id_salt("issue_kind")
+            .selected_text(filter.kind.as_ref().map_or("All kinds", |k| k.label()))
+            .show_ui(ui, |ui| {
+                regroup |= ui.selectable_value(&mut filter.kind, None, "All kinds").changed();
+                for kind in IssueKind::ALL {
+                    regroup |= ui.selectable_value(&mut filter.kind, Some(kind), kind.label()).changed();
                 }
             });
+        ui.label("🔍");
+        regroup |= ui.add(egui::TextEdit::singleline(&mut filter.text)
+            .hint_text("rule, path, value...")
+            .desired_width(160.0))
+            .changed();
+        ui.separator();
+        ui.label("Sort:");
+        let sort = &mut app.issues_state.sort;
+        for option in IssueSort::ALL {
+            regroup |= ui.selectable_value(sort, option, option.label()).changed();
+        }
+    });
+    if regroup {
+        app.group_issues();
+    }
+
+    let groups = &app.issues_state.groups;
+    let shown: usize = groups.iter().map(|g| g.issues.len()).sum();
+    ui.label(format!("Total Issues Found: {} ({} shown in {} groups)", app.issues.len(), shown, groups.len()));
+    ui.add_space(10.0);
+
+    let mut open = None;
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for group in groups {
+            if let Some(target) = render_issue_group(ui, group, &app.issues) {
+                open = Some(target);
+            }
+        }
     });
-}
\ No newline at end of file
+    if let Some((idx, path)) = open {
+        app.open_record(idx, path);
+    }
+}
+
+fn severity_color(severity: Severity) -> egui::Color32 {
+    match severity {
+        Severity::Error => egui::Color32::from_rgb(200, 100, 80),
+        Severity::Warning => egui::Color32::from_rgb(200, 160, 100),
+        Severity::Info => egui::Color32::from_rgb(180, 170, 150),
+    }
+}
+
+/// One group of issues as a collapsible table. Returns the record (and
+/// path) to open when a record link was clicked.
+fn render_issue_group(ui: &mut egui::Ui, group: &IssueGroup, issues: &[RecordIssue]) -> Option<(usize, Option<String>)> {
+    let mut open = None;
+    let title = egui::RichText::new(format!("● {} × {}  ({}, {} records)",
+        group.issues.len(), group.source, group.kind.label(), group.record_count))
+        .strong()
+        .color(severity_color(group.severity));
+
+    egui::CollapsingHeader::new(title)
+        .id_salt(("issue_group", group.kind, &group.source))
+        .show(ui, |ui| {
+            egui::Grid::new(("issues_grid", group.kind, &group.source))
+                .striped(true)
+                .min_col_width(60.0)
+                .show(ui, |ui| {
+                    for header in ["Record", "Severity", "Path", "Value", "Description", "Suggested fix"] {
+                        ui.label(egui::RichText::new(header).strong());
+                    }
+                    ui.end_row();
+
+                    for &i in group.issues.iter().take(MAX_ISSUES_SHOWN) {
+                        let issue = &issues[i];
+                        match issue.record_index {
+                            Some(idx) => {
+                                if ui.link(format!("#{}", idx + 1)).on_hover_text("Show in Details").clicked() {
+                                    open = Some((idx, issue.path.clone()));
+                                }
+                            }
+                            None => {
+                                ui.label("dataset");
+                            }
+                        }
+                        ui.label(egui::RichText::new(issue.severity.label()).color(severity_color(issue.severity)));
+                        ui.label(egui::RichText::new(issue.path.as_deref().unwrap_or("")).monospace());
+                        ui.label(egui::RichText::new(issue.value.as_deref().unwrap_or("")).monospace());
+                        ui.label(&issue.description);
+                        ui.label(egui::RichText::new(issue.suggestion.as_deref().unwrap_or(""))
+                            .color(egui::Color32::from_rgb(180, 170, 150)));
+                        ui.end_row();
+                    }
+                });
+
+            if group.issues.len() > MAX_ISSUES_SHOWN {
+                ui.label(egui::RichText::new(format!("... and {} more", group.issues.len() - MAX_ISSUES_SHOWN))
+                    .italics()
+                    .color(egui::Color32::from_rgb(180, 170, 150)));
+            }
+        });
+    open
+}
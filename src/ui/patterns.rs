@@ -1,12 +1,17 @@
 use eframe::egui;
-use crate::data::PatternAnalysis;
+use crate::data::{PatternAnalysis, Severity};
 use crate::app::{PatternsState, FacetsState};
+use crate::data::filters::{FilterKind, RecordFilter};
+use crate::data::reports::pattern_report;
+use crate::ui::export::report_menu;
 
 pub fn render_patterns_tab(
     ui: &mut egui::Ui,
     patterns_state: &mut PatternsState,
     facets_state: &FacetsState,
-) {
+) -> Option<RecordFilter> {
+    let mut clicked = None;
+
     ui.heading("🔍 Pattern Analysis");
     ui.label("Automatic detection of data patterns and formats");
     ui.separator();
@@ -25,7 +30,7 @@ pub fn render_patterns_tab(
 
         // Show pattern analysis if available
         if let Some(analysis) = &patterns_state.current_analysis {
-            render_pattern_results(ui, analysis);
+            clicked = render_pattern_results(ui, analysis);
         } else {
             ui.vertical_centered(|ui| {
                 ui.add_space(50.0);
@@ -44,11 +49,16 @@ pub fn render_patterns_tab(
             ui.label("• Data types (numeric, text, mixed)");
             ui.label("• Bibliographic patterns (language codes, bracketed content)");
             ui.label("• Data quality issues (empty values, malformed data)");
+            ui.label("• Text hygiene (stray whitespace, decomposed diacritics, mojibake)");
         });
     }
+
+    clicked
 }
 
-fn render_pattern_results(ui: &mut egui::Ui, analysis: &PatternAnalysis) {
+fn render_pattern_results(ui: &mut egui::Ui, analysis: &PatternAnalysis) -> Option<RecordFilter> {
+    let mut clicked = None;
+
     // Summary section
     ui.group(|ui| {
         ui.vertical(|ui| {
@@ -66,9 +76,17 @@ fn render_pattern_results(ui: &mut egui::Ui, analysis: &PatternAnalysis) {
                     .color(egui::Color32::from_rgb(200, 160, 100)));
                 ui.label(format!("{}", analysis.pattern_groups.len()));
             });
+
+            ui.add_space(5.0);
+            report_menu(ui, &format!("patterns-{}", analysis.field_name), |format| pattern_report(analysis, format));
         });
     });
 
+    ui.add_space(15.0);
+    if let Some(filter) = render_hygiene_groups(ui, analysis) {
+        clicked = Some(filter);
+    }
+
     ui.add_space(15.0);
     ui.heading("Pattern Distribution");
     ui.separator();
@@ -89,6 +107,15 @@ fn render_pattern_results(ui: &mut egui::Ui, analysis: &PatternAnalysis) {
                             ui.label(egui::RichText::new(format!("({} values, {:.1}%)", 
                                 pattern_group.count, pattern_group.percentage))
                                 .color(egui::Color32::from_rgb(180, 180, 180)));
+                            if ui.small_button("Filter records →").clicked() {
+                                clicked = Some(RecordFilter {
+                                    path: analysis.field_name.clone(),
+                                    kind: FilterKind::Pattern {
+                                        mode: analysis.mode,
+                                        pattern: pattern_group.pattern_type.clone(),
+                                    },
+                                });
+                            }
                         });
 
                         ui.label(egui::RichText::new(pattern_group.pattern_type.description())
@@ -148,11 +175,60 @@ fn render_pattern_results(ui: &mut egui::Ui, analysis: &PatternAnalysis) {
                 ui.add_space(10.0);
             }
         });
+
+    clicked
+}
+
+/// Whitespace, Unicode and encoding problems, one compact row each.
+fn render_hygiene_groups(ui: &mut egui::Ui, analysis: &PatternAnalysis) -> Option<RecordFilter> {
+    let mut clicked = None;
+
+    ui.heading("Text Hygiene");
+    ui.separator();
+    if analysis.hygiene_groups.is_empty() {
+        ui.label(egui::RichText::new("✓ No whitespace, Unicode or encoding problems")
+            .color(egui::Color32::from_rgb(180, 220, 180)));
+        return None;
+    }
+
+    for group in &analysis.hygiene_groups {
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                let color = match group.problem.severity() {
+                    Severity::Error => egui::Color32::from_rgb(200, 100, 80),
+                    _ => egui::Color32::from_rgb(200, 160, 100),
+                };
+                ui.label(egui::RichText::new(group.problem.name()).strong().color(color));
+                ui.label(egui::RichText::new(format!("({} values, {:.1}%)", group.count, group.percentage))
+                    .color(egui::Color32::from_rgb(180, 180, 180)));
+                if ui.small_button("Filter records →").clicked() {
+                    clicked = Some(RecordFilter {
+                        path: analysis.field_name.clone(),
+                        kind: FilterKind::Hygiene { mode: analysis.mode, problem: group.problem },
+                    });
+                }
+            });
+            ui.label(egui::RichText::new(group.problem.description())
+                .italics()
+                .color(egui::Color32::from_rgb(200, 190, 170)));
+            let examples_text = group.examples
+                .iter()
+                .take(3)
+                .map(|ex| format!("\"{}\"", truncate_string(ex, 40)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            ui.label(egui::RichText::new(examples_text)
+                .monospace()
+                .color(egui::Color32::from_rgb(180, 220, 180)));
+        });
+    }
+
+    clicked
 }
 
 fn truncate_string(s: &str, max_len: usize) -> String {
-    if s.len() > max_len {
-        format!("{}...", &s[..max_len])
+    if s.chars().count() > max_len {
+        format!("{}...", s.chars().take(max_len).collect::<String>())
     } else {
         s.to_string()
     }